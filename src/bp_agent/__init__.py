@@ -1,6 +1,34 @@
 """BP Agent - Minimal task execution agent framework."""
 
-from bp_agent.agent import Agent, AgentConfig, AgentResult, CHAT_SYSTEM_PROMPT, DEFAULT_SYSTEM_PROMPT
+from bp_agent.agent import (
+    Agent,
+    AgentConfig,
+    AgentResult,
+    ChatBudgetExceeded,
+    ExecutionOptions,
+    CHAT_SYSTEM_PROMPT,
+    DEFAULT_SYSTEM_PROMPT,
+)
+from bp_agent.errors import BaseAgentError, ConfigError, NotFoundError, StoreError, ToolError
+from bp_agent.llm.types import ProviderError
+from bp_agent.sessions import Session, SessionNotFoundError, SessionStore
 
 __version__ = "0.3.0"
-__all__ = ["Agent", "AgentConfig", "AgentResult", "CHAT_SYSTEM_PROMPT", "DEFAULT_SYSTEM_PROMPT"]
+__all__ = [
+    "Agent",
+    "AgentConfig",
+    "AgentResult",
+    "ChatBudgetExceeded",
+    "ExecutionOptions",
+    "CHAT_SYSTEM_PROMPT",
+    "DEFAULT_SYSTEM_PROMPT",
+    "BaseAgentError",
+    "ConfigError",
+    "NotFoundError",
+    "ProviderError",
+    "Session",
+    "SessionNotFoundError",
+    "SessionStore",
+    "StoreError",
+    "ToolError",
+]
@@ -0,0 +1,60 @@
+"""Instruction complexity classification for model tier selection."""
+
+from __future__ import annotations
+
+import re
+from typing import Optional
+
+from bp_agent.llm import CompletionRequest, Message
+
+TIERS = ("simple", "complex")
+
+_COMPLEX_HINTS = re.compile(
+    r"\b(refactor|implement|design|architect\w*|analy[sz]e|debug|optimi[sz]e|prove|compare|"
+    r"step[- ]by[- ]step|trade-?offs?|investigate|migrate|plan)\b",
+    re.IGNORECASE,
+)
+
+CLASSIFIER_PROMPT = (
+    "Classify how hard the user's task is for an AI agent. "
+    "Answer SIMPLE for lookups, single commands, short factual or formatting tasks; "
+    "answer COMPLEX for multi-step reasoning, coding, analysis or planning. Answer with one word."
+)
+
+
+def classify_complexity(instruction: str) -> str:
+    """Cheap heuristic: long, multi-part, code-bearing or analysis-style instructions are complex."""
+    text = instruction.strip()
+    if len(text) > 400 or text.count("\n") >= 5 or "```" in text:
+        return "complex"
+    if _COMPLEX_HINTS.search(text):
+        return "complex"
+    return "simple"
+
+
+class ModelComplexityClassifier:
+    """Asks a small model; falls back to the heuristic on errors or unclear answers."""
+
+    def __init__(self, llm, model: Optional[str] = None, provider: Optional[str] = None):
+        self.llm = llm
+        self.model = model
+        self.provider = provider
+
+    def __call__(self, instruction: str) -> str:
+        request = CompletionRequest(
+            messages=[
+                Message(role="system", content=CLASSIFIER_PROMPT),
+                Message(role="user", content=instruction),
+            ],
+            temperature=0.0,
+            model=self.model,
+            provider=self.provider,
+        )
+        try:
+            verdict = self.llm.complete(request).content.strip().lower()
+        except Exception:
+            return classify_complexity(instruction)
+        for tier in TIERS:
+            if verdict.startswith(tier):
+                return tier
+        return classify_complexity(instruction)
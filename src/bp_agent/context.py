@@ -0,0 +1,34 @@
+"""Context helpers - keep the message history small."""
+
+from __future__ import annotations
+
+import hashlib
+from typing import Optional
+
+
+class ToolResultDeduper:
+    """Replaces repeated tool outputs with a reference to their first occurrence."""
+
+    def __init__(self, min_chars: int = 200):
+        self.min_chars = min_chars
+        self._seen: dict[str, tuple[int, str]] = {}  # digest -> (result number, tool name)
+        self._count = 0
+        self.refs: dict[int, int] = {}  # result number -> times referenced
+
+    def reset(self):
+        self._seen = {}
+        self._count = 0
+        self.refs = {}
+
+    def check(self, tool_name: str, output: str) -> tuple[int, Optional[str]]:
+        """Record a result. Returns (result number, reference text or None if new)."""
+        self._count += 1
+        if not isinstance(output, str) or len(output) < self.min_chars:
+            return self._count, None
+        digest = hashlib.sha256(output.encode("utf-8")).hexdigest()
+        if digest not in self._seen:
+            self._seen[digest] = (self._count, tool_name)
+            return self._count, None
+        first, first_tool = self._seen[digest]
+        self.refs[first] = self.refs.get(first, 0) + 1
+        return self._count, f"(identical to tool result #{first} from {first_tool} above, {len(output)} chars omitted)"
@@ -0,0 +1,34 @@
+"""Error hierarchy shared by the whole package.
+
+Every error raised on purpose derives from BaseAgentError and carries a
+machine-readable `code`. The ValueError/LookupError mixins keep older
+`except ValueError` call sites working.
+"""
+
+from __future__ import annotations
+
+
+class BaseAgentError(Exception):
+    code = "error"
+
+
+class ConfigError(BaseAgentError, ValueError):
+    """Bad settings or missing credentials (router, adapters, profiles)."""
+
+    code = "config_error"
+
+
+class ToolError(BaseAgentError, ValueError):
+    """Tool registration or manifest problems (not tool execution failures)."""
+
+    code = "tool_error"
+
+
+class StoreError(BaseAgentError):
+    """Task/checkpoint store failures."""
+
+    code = "store_error"
+
+
+class NotFoundError(StoreError, LookupError):
+    code = "not_found"
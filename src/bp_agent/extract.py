@@ -0,0 +1,76 @@
+"""Structured extraction - pull schema-validated JSON out of free text."""
+
+from __future__ import annotations
+
+import json
+import sys
+from dataclasses import dataclass, field
+from typing import Any, Optional
+
+from bp_agent.llm.json_repair import repair_json
+
+EXTRACT_SYSTEM_PROMPT = """Extract structured data from the user's text.
+Reply with a single JSON value that matches this JSON schema, and nothing else:
+{schema}
+Use null for optional fields the text does not mention. Never invent values."""
+
+
+@dataclass
+class ExtractResult:
+    success: bool
+    data: Any = None
+    errors: list[str] = field(default_factory=list)  # validation errors of the last attempt
+    attempts: int = 0
+    raw: str = ""  # last model reply
+
+
+def parse_reply(text: str) -> tuple[Any, Optional[str]]:
+    """Parse a model reply as JSON, repairing fences/quotes/commas. Returns (value, error)."""
+    try:
+        return json.loads(text), None
+    except ValueError:
+        pass
+    try:
+        return json.loads(repair_json(text)), None
+    except ValueError as exc:
+        return None, f"reply is not valid JSON ({exc})"
+
+
+def main(argv: Optional[list[str]] = None) -> int:
+    import argparse
+
+    parser = argparse.ArgumentParser(prog="bp-extract", description="Extract schema-validated JSON from text")
+    parser.add_argument("schema", help="JSON schema file")
+    parser.add_argument("input", nargs="?", default="-", help="Text file (default: stdin)")
+    parser.add_argument("--instruction", "-i", default=None, help="Extra guidance for the model")
+    parser.add_argument("--provider", "-p", default=None)
+    parser.add_argument("--model", "-m", default=None)
+    parser.add_argument("--attempts", type=int, default=2, help="Tries before giving up (default 2)")
+    args = parser.parse_args(argv)
+
+    try:
+        with open(args.schema, "r", encoding="utf-8") as handle:
+            schema = json.load(handle)
+        if args.input == "-":
+            text = sys.stdin.read()
+        else:
+            with open(args.input, "r", encoding="utf-8") as handle:
+                text = handle.read()
+    except (OSError, json.JSONDecodeError) as exc:
+        print(f"Error: {exc}", file=sys.stderr)
+        return 1
+
+    from bp_agent.agent import Agent, AgentConfig
+
+    kwargs = {key: value for key, value in (("provider", args.provider), ("model", args.model)) if value}
+    agent = Agent("extract", config=AgentConfig(enable_task_store=False, enable_builtin_tools=False, **kwargs))
+    result = agent.extract(text, schema, instruction=args.instruction, max_attempts=args.attempts)
+    if not result.success:
+        print("Error: " + "; ".join(result.errors), file=sys.stderr)
+        return 1
+    print(json.dumps(result.data, indent=2, ensure_ascii=False))
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
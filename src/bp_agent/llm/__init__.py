@@ -1,23 +1,41 @@
 """LLM client exports."""
 
-from .types import Message, ToolCall, LLMResponse, CompletionRequest, ProviderError, StreamChunk, ToolCallDelta, StreamIterator, accumulate_stream
-from .router import LLMRouter, ProviderAdapter
-from .rotation import RotationManager, RotationPolicy, RotationSlot
+from .types import (
+    Message, ToolCall, LLMResponse, CompletionRequest, ProviderError, StreamChunk, ToolCallDelta, StreamIterator,
+    Usage, accumulate_stream, ensure_tool_call_ids, tool_message,
+)
+from .router import LLMRouter, ProviderAdapter, ShadowConfig, ShadowResult
+from .rotation import RotationManager, RotationPolicy, RotationSlot, key_fingerprint
 from .gemini_adapter import GeminiAdapter, GeminiConfig, GEMINI_ALLOWED_MODELS
 from .codex_adapter import CodexAdapter, CodexConfig, CodexAuth, CODEX_MODELS
 from .opus_adapter import OpusAdapter, OpusConfig
+from .openai_adapter import OpenAIAdapter, OpenAIConfig
+from .ollama_adapter import OllamaAdapter, OllamaConfig
+from .tokenizer import count_tokens, count_message_tokens, model_family
+from .models import ModelCatalog
+from .pricing import ModelPrice, MODEL_PRICES, RunMeter, estimate_cost, get_price, register_price
+from .capabilities import ModelCapabilities, MODEL_CAPABILITIES, get_capabilities, register_model
+from .chaos import ChaosAdapter, ChaosConfig
+from .retry import Retrier, RetryPolicy
+from .egress import EgressPolicy, PROVIDER_HOSTS, check_egress, egress_policy, set_egress_policy
+from .capture import CaptureBuffer, CaptureEntry, redact
+from .fixtures import Fixture, FixtureResult, check_fixtures, load_fixtures, register_fixture, register_parser
 
 __all__ = [
     "Message",
     "ToolCall",
     "LLMResponse",
+    "Usage",
     "CompletionRequest",
     "ProviderError",
     "LLMRouter",
     "ProviderAdapter",
+    "ShadowConfig",
+    "ShadowResult",
     "RotationManager",
     "RotationPolicy",
     "RotationSlot",
+    "key_fingerprint",
     "GeminiAdapter",
     "GeminiConfig",
     "GEMINI_ALLOWED_MODELS",
@@ -27,8 +45,46 @@ __all__ = [
     "CODEX_MODELS",
     "OpusAdapter",
     "OpusConfig",
+    "OpenAIAdapter",
+    "OpenAIConfig",
+    "OllamaAdapter",
+    "OllamaConfig",
     "StreamChunk",
     "ToolCallDelta",
     "StreamIterator",
     "accumulate_stream",
+    "ensure_tool_call_ids",
+    "tool_message",
+    "count_tokens",
+    "count_message_tokens",
+    "model_family",
+    "ModelCapabilities",
+    "MODEL_CAPABILITIES",
+    "get_capabilities",
+    "register_model",
+    "ModelCatalog",
+    "ModelPrice",
+    "MODEL_PRICES",
+    "RunMeter",
+    "estimate_cost",
+    "get_price",
+    "register_price",
+    "ChaosAdapter",
+    "ChaosConfig",
+    "Retrier",
+    "RetryPolicy",
+    "EgressPolicy",
+    "PROVIDER_HOSTS",
+    "check_egress",
+    "egress_policy",
+    "set_egress_policy",
+    "CaptureBuffer",
+    "CaptureEntry",
+    "redact",
+    "Fixture",
+    "FixtureResult",
+    "check_fixtures",
+    "load_fixtures",
+    "register_fixture",
+    "register_parser",
 ]
@@ -0,0 +1,65 @@
+"""Model capability registry."""
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+from typing import Optional
+
+from .tokenizer import count_message_tokens
+from .types import CompletionRequest, ProviderError
+
+
+@dataclass(frozen=True)
+class ModelCapabilities:
+    context_window: int  # tokens
+    max_output_tokens: int
+    supports_tools: bool = True
+    supports_vision: bool = False
+    supports_json_mode: bool = False
+
+    @property
+    def input_budget(self) -> int:
+        """Tokens available for the prompt once room for the answer is reserved."""
+        return self.context_window - self.max_output_tokens
+
+
+_GEMINI_3 = ModelCapabilities(1_048_576, 65_536, supports_vision=True, supports_json_mode=True)
+_GPT_5 = ModelCapabilities(400_000, 128_000, supports_vision=True, supports_json_mode=True)
+
+MODEL_CAPABILITIES: dict[str, ModelCapabilities] = {
+    "gemini-3-flash-preview": _GEMINI_3,
+    "gemini-3-pro-preview": _GEMINI_3,
+    "gpt-5.2-codex": _GPT_5,
+    "gpt-5.1-codex-mini": _GPT_5,
+    "gpt-5.1-codex-max": _GPT_5,
+    "gpt-5.1-codex": _GPT_5,
+    "gpt-5-codex": _GPT_5,
+    "gpt-5-codex-mini": _GPT_5,
+    "gpt-5.2": _GPT_5,
+    "gpt-5.1": _GPT_5,
+    "gpt-5": _GPT_5,
+}
+
+
+def register_model(model: str, capabilities: ModelCapabilities):
+    MODEL_CAPABILITIES[model] = capabilities
+
+
+def get_capabilities(model: Optional[str]) -> Optional[ModelCapabilities]:
+    return MODEL_CAPABILITIES.get(model) if model else None
+
+
+def check_request(request: CompletionRequest):
+    """Reject requests the target model cannot serve. Unknown models are not checked."""
+    caps = get_capabilities(request.model)
+    if caps is None:
+        return
+    if request.tools and not caps.supports_tools:
+        raise ProviderError("unsupported", f"Model {request.model} does not support tools", retryable=False)
+    tokens = count_message_tokens(request.model, request.messages, exact=False)
+    if tokens > caps.input_budget:
+        raise ProviderError(
+            "context_overflow",
+            f"Prompt is ~{tokens} tokens; {request.model} accepts {caps.input_budget}",
+            retryable=False,
+        )
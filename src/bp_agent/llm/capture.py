@@ -0,0 +1,136 @@
+"""Debug capture - rolling buffer of recent provider exchanges, secrets redacted."""
+
+from __future__ import annotations
+
+import re
+import threading
+import time
+from collections import deque
+from dataclasses import dataclass, field
+from typing import Any, Iterable, Optional
+
+from .types import CompletionRequest, LLMResponse
+
+REDACTED = "[redacted]"
+
+# Always masked, whatever the caller configures
+SECRET_FIELDS = frozenset({"api_key", "apikey", "key", "authorization", "x-api-key", "token", "access_token",
+                           "refresh_token", "id_token", "password", "secret"})
+
+_SECRET_VALUE = re.compile(
+    r"AIza[\w-]{20,}"  # Google API keys
+    r"|sk-[\w-]{16,}"  # OpenAI/Anthropic style keys
+    r"|Bearer\s+[\w.~+/=-]{8,}"
+    r"|eyJ[\w-]{8,}\.[\w-]{8,}\.[\w-]+"  # JWTs
+)
+
+
+def redact(value: Any, fields: Iterable[str] = ()) -> Any:
+    """Copy of value with secret-looking strings and the named fields (any depth) masked."""
+    names = SECRET_FIELDS | {name.lower() for name in fields}
+    return _redact(value, names)
+
+
+def _redact(value: Any, names: frozenset[str] | set[str]) -> Any:
+    if isinstance(value, dict):
+        return {
+            key: REDACTED if isinstance(key, str) and key.lower() in names else _redact(item, names)
+            for key, item in value.items()
+        }
+    if isinstance(value, (list, tuple)):
+        return [_redact(item, names) for item in value]
+    if isinstance(value, str):
+        return _SECRET_VALUE.sub(REDACTED, value)
+    return value
+
+
+@dataclass
+class CaptureEntry:
+    provider: str
+    model: Optional[str]
+    started_at: float
+    latency_ms: float
+    request: dict[str, Any] = field(default_factory=dict)
+    response: Optional[Any] = None  # provider raw body (or content/tool calls when there is none)
+    error: Optional[str] = None
+    stream: bool = False
+
+    def to_dict(self) -> dict[str, Any]:
+        return {
+            "provider": self.provider,
+            "model": self.model,
+            "started_at": self.started_at,
+            "latency_ms": round(self.latency_ms, 1),
+            "stream": self.stream,
+            "request": self.request,
+            "response": self.response,
+            "error": self.error,
+        }
+
+
+class CaptureBuffer:
+    """Keeps the last `size` exchanges. Entries are redacted when recorded, never stored raw."""
+
+    def __init__(self, size: int = 50, redact_fields: Iterable[str] = ()):
+        self.size = size
+        self.redact_fields = tuple(redact_fields)
+        self._entries: deque[CaptureEntry] = deque(maxlen=max(1, size))
+        self._lock = threading.Lock()
+
+    def record(
+        self,
+        provider: str,
+        request: CompletionRequest,
+        started_at: float,
+        response: Optional[LLMResponse] = None,
+        error: Optional[BaseException] = None,
+        stream: bool = False,
+    ) -> CaptureEntry:
+        entry = CaptureEntry(
+            provider=provider,
+            model=request.model,
+            started_at=started_at,
+            latency_ms=(time.time() - started_at) * 1000,
+            request=redact(_request_dict(request), self.redact_fields),
+            response=redact(_response_body(response), self.redact_fields) if response is not None else None,
+            error=redact(f"{type(error).__name__}: {error}", self.redact_fields) if error is not None else None,
+            stream=stream,
+        )
+        with self._lock:
+            self._entries.append(entry)
+        return entry
+
+    def entries(self, limit: Optional[int] = None) -> list[CaptureEntry]:
+        """Oldest first; `limit` keeps only the most recent N."""
+        with self._lock:
+            entries = list(self._entries)
+        return entries[-limit:] if limit else entries
+
+    def to_list(self, limit: Optional[int] = None) -> list[dict[str, Any]]:
+        return [entry.to_dict() for entry in self.entries(limit)]
+
+    def clear(self):
+        with self._lock:
+            self._entries.clear()
+
+
+def _request_dict(request: CompletionRequest) -> dict[str, Any]:
+    data: dict[str, Any] = {
+        "messages": [message.to_dict() for message in request.messages],
+        "tools": [getattr(tool, "name", tool) for tool in request.tools or []],
+        "temperature": request.temperature,
+    }
+    if request.metadata:
+        data["metadata"] = request.metadata
+    if request.timeout is not None:
+        data["timeout"] = request.timeout
+    return data
+
+
+def _response_body(response: LLMResponse) -> Any:
+    if response.raw is not None and not isinstance(response.raw, (bytes, bytearray)):
+        return response.raw
+    return {
+        "content": response.content,
+        "tool_calls": [{"id": call.id, "name": call.name, "args": call.args} for call in response.tool_calls or []],
+    }
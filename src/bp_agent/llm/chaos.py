@@ -0,0 +1,84 @@
+"""Chaos/testing wrapper - inject latency, retryable errors and rate limits into a provider."""
+
+from __future__ import annotations
+
+import random
+import time
+from dataclasses import dataclass, fields
+from typing import Callable, Optional
+
+from ..errors import ConfigError
+from .types import CompletionRequest, LLMResponse, ProviderError, StreamIterator
+
+
+@dataclass
+class ChaosConfig:
+    latency_ms: float = 0.0  # added to every call
+    jitter_ms: float = 0.0  # uniform 0..jitter_ms on top
+    error_rate: float = 0.0  # retryable server_error
+    rate_limit_rate: float = 0.0  # retryable rate_limit
+    timeout_rate: float = 0.0  # retryable network_error after the latency
+    seed: Optional[int] = None
+
+    @classmethod
+    def from_spec(cls, spec: str) -> "ChaosConfig":
+        """Parse `key=value` pairs, e.g. "latency_ms=200,jitter_ms=50,error_rate=0.1"."""
+        names = {f.name for f in fields(cls)}
+        values: dict = {}
+        for part in spec.split(","):
+            if not part.strip():
+                continue
+            key, sep, value = part.partition("=")
+            key = key.strip()
+            if not sep or key not in names:
+                raise ConfigError(f"Invalid chaos setting: {part.strip()!r}")
+            values[key] = int(value) if key == "seed" else float(value)
+        return cls(**values)
+
+
+class ChaosAdapter:
+    """Wraps a ProviderAdapter; failures are raised before the wrapped provider is called."""
+
+    def __init__(
+        self,
+        inner,
+        config: ChaosConfig,
+        sleep: Callable[[float], None] = time.sleep,
+    ):
+        self.inner = inner
+        self.config = config
+        self._sleep = sleep
+        self._random = random.Random(config.seed)
+        self.injected: dict[str, int] = {}
+
+    def __getattr__(self, name: str):
+        # list_models, config, ... pass through to the real adapter
+        return getattr(self.inner, name)
+
+    def _inject(self):
+        cfg = self.config
+        delay = cfg.latency_ms + (self._random.uniform(0, cfg.jitter_ms) if cfg.jitter_ms else 0.0)
+        if delay > 0:
+            self._sleep(delay / 1000)
+        roll = self._random.random()
+        for code, rate, message in (
+            ("rate_limit", cfg.rate_limit_rate, "chaos: injected 429 Too Many Requests"),
+            ("server_error", cfg.error_rate, "chaos: injected 503 Service Unavailable"),
+            ("network_error", cfg.timeout_rate, "chaos: injected timeout"),
+        ):
+            if roll < rate:
+                self.injected[code] = self.injected.get(code, 0) + 1
+                raise ProviderError(code, message, retryable=True)
+            roll -= rate
+
+    def complete(self, request: CompletionRequest) -> LLMResponse:
+        self._inject()
+        return self.inner.complete(request)
+
+    def complete_stream(self, request: CompletionRequest) -> StreamIterator:
+        self._inject()
+        if hasattr(self.inner, "complete_stream"):
+            return self.inner.complete_stream(request)
+        from .router import LLMRouter
+
+        return LLMRouter._fallback_stream(self.inner.complete(request))
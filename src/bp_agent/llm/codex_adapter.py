@@ -2,17 +2,23 @@
 
 from __future__ import annotations
 
+import itertools
 import json
 import os
 from dataclasses import dataclass
 from pathlib import Path
 from typing import Optional
-from urllib import request as urlrequest, error as urlerror
 
-from .rotation import RotationManager, RotationSlot
 import requests as http_requests
 
-from .types import CompletionRequest, LLMResponse, ToolCall, ProviderError, StreamChunk, StreamIterator, ToolCallDelta
+from ..errors import ConfigError
+from .egress import EgressPolicy, check_egress
+from .rotation import RotationManager, RotationSlot, key_fingerprint
+from .types import (
+    CompletionRequest, LLMResponse, ToolCall, ProviderError, StreamChunk, StreamIterator, ToolCallDelta,
+    call_timeout, finish_reason_from_raw, parse_tool_call, request_deadline, responses_logprobs, system_text,
+    usage_from_raw,
+)
 
 CODEX_MODELS = [
     "gpt-5.2-codex",
@@ -41,32 +47,48 @@ class CodexConfig:
     api_keys: list[str] | None = None
     auth_files: list[str] | None = None
     model: str = "gpt-5.2-codex"
-    reasoning_effort: str = "medium"
+    reasoning_effort: str = "medium"  # "" / "none" sends no reasoning block (and allows temperature)
     base_url: str = "https://api.openai.com/v1"
+    chatgpt_base_url: str = "https://chatgpt.com/backend-api/codex"  # used for auth_files (ChatGPT login)
 
 
 class CodexAdapter:
+    egress: Optional[EgressPolicy] = None  # the owning agent's policy (LLMRouter.set_egress_policy)
+
     def __init__(self, config: CodexConfig, rotation: RotationManager | None = None):
         self.config = config
         self.rotation = rotation or RotationManager()
-        self._slot_creds: dict[str, dict] = {}
-
-        api_keys = config.api_keys or []
-        auth_files = config.auth_files or []
+        self._slot_ids = itertools.count()
 
-        for idx, key in enumerate(api_keys):
-            slot_id = f"api:{idx}"
-            self.rotation.add_slot(RotationSlot(id=slot_id))
-            self._slot_creds[slot_id] = {"type": "api_key", "value": key}
+        for key in config.api_keys or []:
+            self.add_key(key)
 
-        for idx, path in enumerate(auth_files):
+        for idx, path in enumerate(config.auth_files or []):
             auth = load_auth(path)
-            slot_id = f"auth:{idx}"
-            self.rotation.add_slot(RotationSlot(id=slot_id))
-            self._slot_creds[slot_id] = {"type": "auth", "value": auth.access_token}
-
-        if not self._slot_creds:
-            raise ValueError("Codex requires api_keys or auth_files")
+            cred = {
+                "type": "auth",
+                "value": auth.access_token,
+                "account_id": auth.account_id,
+                "base_url": config.chatgpt_base_url,
+            }
+            self.rotation.add_slot(RotationSlot(
+                id=f"auth:{idx}", secret=cred, fingerprint=key_fingerprint(auth.access_token),
+            ))
+
+        if not self.rotation.slots():
+            raise ConfigError("Codex requires api_keys or auth_files")
+
+    def add_key(self, key: str) -> str:
+        """Put an API key into rotation (takes effect on the next request); returns its fingerprint."""
+        fingerprint = key_fingerprint(key)
+        cred = {"type": "api_key", "value": key, "base_url": self.config.base_url}
+        self.rotation.add_slot(RotationSlot(id=f"api:{next(self._slot_ids)}", secret=cred, fingerprint=fingerprint))
+        return fingerprint
+
+    def base_urls(self) -> list[str]:
+        """base_url of every credential in rotation (API keys and ChatGPT logins differ)."""
+        urls = [slot.secret.get("base_url") or self.config.base_url for slot in self.rotation.slots()]
+        return list(dict.fromkeys(urls))
 
     def complete(self, request: CompletionRequest) -> LLMResponse:
         model = request.model or self.config.model
@@ -75,84 +97,112 @@ class CodexAdapter:
 
         payload = self._build_payload(request, model)
 
+        deadline = request_deadline(request)
         attempt = 0
         while True:
             attempt += 1
+            timeout = call_timeout(deadline, None)
             slot = self.rotation.select_slot()
-            cred = self._slot_creds[slot.id]
+            cred = slot.secret
             try:
-                response = self._send_request(payload, cred)
+                response = self._send_request(payload, cred, timeout=timeout)
                 self.rotation.report_success(slot.id)
                 return self._parse_response(response)
             except ProviderError as exc:
-                if exc.code in ("rate_limit", "quota"):
-                    self.rotation.report_rate_limit(slot.id, exc.message)
-                elif exc.code == "auth_error":
-                    self.rotation.report_auth_error(slot.id)
+                self._report_error(slot.id, exc)
                 if not exc.retryable or attempt > self.rotation.policy.max_retries:
                     raise
                 self.rotation.backoff(attempt)
 
     def _build_payload(self, request: CompletionRequest, model: str) -> dict:
         temperature = request.temperature
+        effort = self.config.reasoning_effort
+        reasoning = bool(effort) and effort != "none"
         messages = request.messages
 
-        instructions = None
+        # System messages are joined into `instructions`; developer messages keep
+        # their position as developer-role input (o-series/codex models honor them)
+        instructions = system_text(messages, roles=("system",))
         input_items = []
         for msg in messages:
             if msg.role == "system":
-                instructions = msg.content
+                continue
+            elif msg.role == "tool" and msg.tool_call_id:
+                input_items.append({"type": "function_call_output", "call_id": msg.tool_call_id, "output": msg.content})
+            elif msg.role == "assistant" and msg.tool_calls:
+                if msg.content:
+                    input_items.append({"role": "assistant", "content": msg.content})
+                for call in msg.tool_calls:
+                    input_items.append({
+                        "type": "function_call",
+                        "call_id": call.id,
+                        "name": call.name,
+                        "arguments": json.dumps(call.args),
+                    })
             else:
-                input_items.append({"role": msg.role, "content": msg.content})
+                role = "user" if msg.role == "tool" else msg.role
+                input_items.append({"role": role, "content": msg.content})
 
         payload = {
             "model": model,
             "input": input_items,
             "stream": False,
             "store": False,
-            "reasoning": {"effort": self.config.reasoning_effort},
         }
+        if reasoning:
+            payload["reasoning"] = {"effort": effort}
         if instructions is not None:
             payload["instructions"] = instructions
-        if temperature is not None:
+        if temperature is not None and not reasoning:
+            # reasoning models reject sampling parameters
             payload["temperature"] = temperature
+        if request.logprobs:
+            payload["include"] = ["message.output_text.logprobs"]
+            if request.top_logprobs:
+                payload["top_logprobs"] = request.top_logprobs
         if request.tools:
+            # Responses API function tools are flat, not nested under "function"
             payload["tools"] = [
                 {
                     "type": "function",
-                    "function": {
-                        "name": t.name,
-                        "description": t.description,
-                        "parameters": t.parameters,
-                    },
+                    "name": t.name,
+                    "description": t.description,
+                    "parameters": t.parameters,
                 }
                 for t in request.tools
             ]
         return payload
 
-    def _send_request(self, payload: dict, cred: dict) -> dict:
-        url = f"{self.config.base_url}/responses"
-        data = json.dumps(payload).encode("utf-8")
-        req = urlrequest.Request(url, data=data, method="POST")
-        req.add_header("Content-Type", "application/json")
-        req.add_header("Authorization", f"Bearer {cred['value']}")
-
+    @staticmethod
+    def _headers(cred: dict) -> dict[str, str]:
+        headers = {"Content-Type": "application/json", "Authorization": f"Bearer {cred['value']}"}
+        if cred.get("account_id"):
+            headers["chatgpt-account-id"] = cred["account_id"]
+        return headers
+
+    @staticmethod
+    def _status_error(status: int, body: str) -> ProviderError:
+        if status in (401, 403):
+            return ProviderError("auth_error", body or "auth error", retryable=True)
+        if status == 429:
+            return ProviderError("rate_limit", body or "rate limit", retryable=True)
+        if status >= 500:
+            return ProviderError("server_error", body or "server error", retryable=True)
+        return ProviderError("api_error", body or "api error", retryable=False)
+
+    def _report_error(self, slot_id: str, exc: ProviderError):
+        self.rotation.report_error(slot_id, exc.code, exc.message)
+
+    def _send_request(self, payload: dict, cred: dict, timeout: Optional[float] = None) -> dict:
+        url = f"{cred.get('base_url') or self.config.base_url}/responses"
+        check_egress(url, self.egress)
         try:
-            with urlrequest.urlopen(req) as resp:
-                body = resp.read().decode("utf-8")
-                return json.loads(body)
-        except urlerror.HTTPError as err:
-            body = err.read().decode("utf-8") if err.fp else ""
-            status = err.code
-            if status in (401, 403):
-                raise ProviderError("auth_error", body or "auth error", retryable=True)
-            if status == 429:
-                raise ProviderError("rate_limit", body or "rate limit", retryable=True)
-            if status >= 500:
-                raise ProviderError("server_error", body or "server error", retryable=True)
-            raise ProviderError("api_error", body or "api error", retryable=False)
-        except urlerror.URLError as err:
+            resp = http_requests.post(url, json=payload, headers=self._headers(cred), timeout=timeout)
+        except http_requests.RequestException as err:
             raise ProviderError("network_error", str(err), retryable=True)
+        if resp.status_code >= 400:
+            raise self._status_error(resp.status_code, resp.text or "")
+        return resp.json()
 
     def complete_stream(self, request: CompletionRequest) -> StreamIterator:
         model = request.model or self.config.model
@@ -163,95 +213,107 @@ class CodexAdapter:
         payload["stream"] = True
 
         slot = self.rotation.select_slot()
-        cred = self._slot_creds[slot.id]
-        url = f"{self.config.base_url}/responses"
-        headers = {
-            "Content-Type": "application/json",
-            "Authorization": f"Bearer {cred['value']}",
-        }
+        cred = slot.secret
+        url = f"{cred.get('base_url') or self.config.base_url}/responses"
+        check_egress(url, self.egress)
         try:
-            resp = http_requests.post(url, json=payload, headers=headers, timeout=60, stream=True)
+            timeout = call_timeout(request_deadline(request), 60)
+            resp = http_requests.post(url, json=payload, headers=self._headers(cred), timeout=timeout, stream=True)
         except http_requests.RequestException as err:
-            raise ProviderError("network_error", str(err), retryable=True)
+            exc = ProviderError("network_error", str(err), retryable=True)
+            self._report_error(slot.id, exc)
+            raise exc from err
 
         if resp.status_code >= 400:
-            body = resp.text or ""
-            if resp.status_code in (401, 403):
-                raise ProviderError("auth_error", body or "auth error", retryable=True)
-            if resp.status_code == 429:
-                raise ProviderError("rate_limit", body or "rate limit", retryable=True)
-            if resp.status_code >= 500:
-                raise ProviderError("server_error", body or "server error", retryable=True)
-            raise ProviderError("api_error", body or "api error", retryable=False)
+            exc = self._status_error(resp.status_code, resp.text or "")
+            resp.close()
+            self._report_error(slot.id, exc)
+            raise exc
 
         self.rotation.report_success(slot.id)
         return self._iter_sse(resp)
 
     def _iter_sse(self, resp) -> StreamIterator:
-        for line in resp.iter_lines(decode_unicode=True):
-            if not line or not line.startswith("data: "):
-                continue
-            data_str = line[len("data: "):]
-            if data_str.strip() == "[DONE]":
-                yield StreamChunk(finish_reason="stop")
-                return
-            try:
-                event = json.loads(data_str)
-            except (ValueError, json.JSONDecodeError):
-                continue
-            etype = event.get("type", "")
-            if etype == "response.output_text.delta":
-                yield StreamChunk(delta=event.get("delta", ""))
-            elif etype == "response.function_call_arguments.delta":
-                yield StreamChunk(
-                    tool_call_delta=ToolCallDelta(
-                        index=event.get("output_index", 0),
-                        args_delta=event.get("delta", ""),
-                    )
-                )
-            elif etype == "response.output_item.added":
-                item = event.get("item", {})
-                if item.get("type") == "function_call":
+        # Closing the generator (client went away) releases the HTTP connection
+        try:
+            for line in resp.iter_lines(decode_unicode=True):
+                if not line or not line.startswith("data: "):
+                    continue
+                data_str = line[len("data: "):]
+                if data_str.strip() == "[DONE]":
+                    yield StreamChunk(finish_reason="stop")
+                    return
+                try:
+                    event = json.loads(data_str)
+                except (ValueError, json.JSONDecodeError):
+                    continue
+                etype = event.get("type", "")
+                if etype == "response.output_text.delta":
+                    yield StreamChunk(delta=event.get("delta", ""))
+                elif etype == "response.function_call_arguments.delta":
                     yield StreamChunk(
                         tool_call_delta=ToolCallDelta(
                             index=event.get("output_index", 0),
-                            name=item.get("name", ""),
+                            args_delta=event.get("delta", ""),
                         )
                     )
-            elif etype == "response.completed":
-                yield StreamChunk(finish_reason="stop")
-                return
-        yield StreamChunk(finish_reason="stop")
+                elif etype == "response.output_item.added":
+                    item = event.get("item", {})
+                    if item.get("type") == "function_call":
+                        yield StreamChunk(
+                            tool_call_delta=ToolCallDelta(
+                                index=event.get("output_index", 0),
+                                name=item.get("name", ""),
+                                id=item.get("call_id"),
+                            )
+                        )
+                elif etype == "response.completed":
+                    yield StreamChunk(finish_reason="stop", usage=usage_from_raw(event.get("response")))
+                    return
+                elif etype == "response.incomplete":
+                    body = event.get("response") or {}
+                    yield StreamChunk(finish_reason=finish_reason_from_raw(body) or "length", usage=usage_from_raw(body))
+                    return
+            yield StreamChunk(finish_reason="stop")
+        finally:
+            resp.close()
 
     def _parse_response(self, response: dict) -> LLMResponse:
         text = response.get("output_text") or ""
+        collect_text = not text
         tool_calls: list[ToolCall] = []
 
-        if not text and "output" in response:
-            for item in response.get("output", []):
-                for content in item.get("content", []):
-                    ctype = content.get("type")
-                    if ctype in ("output_text", "text"):
-                        text += content.get("text", "")
-                    if ctype in ("tool_call", "function_call"):
-                        tool_calls.append(
-                            ToolCall(
-                                name=content.get("name", ""),
-                                args=content.get("arguments", {}) or {},
-                            )
-                        )
+        for item in response.get("output") or []:
+            # function_call is a top-level output item; older bodies nested it in content
+            parts = [item] if item.get("type") == "function_call" else item.get("content") or []
+            for content in parts:
+                ctype = content.get("type")
+                if ctype in ("output_text", "text") and collect_text:
+                    text += content.get("text", "")
+                if ctype in ("tool_call", "function_call"):
+                    tool_calls.append(parse_tool_call(
+                        content.get("name", ""), content.get("arguments") or {}, content.get("call_id") or content.get("id"),
+                    ))
 
-        return LLMResponse(content=text, tool_calls=tool_calls if tool_calls else None, raw=response)
+        return LLMResponse(
+            content=text, tool_calls=tool_calls if tool_calls else None, raw=response,
+            logprobs=responses_logprobs(response),
+        )
 
 
 def load_auth(auth_file: str | None = None) -> CodexAuth:
     codex_home = Path(os.getenv("CODEX_HOME", Path.home() / ".codex"))
     path = Path(auth_file) if auth_file else codex_home / "auth.json"
-    data = json.loads(path.read_text())
-    tokens = data.get("tokens", {})
-    return CodexAuth(
-        access_token=tokens["access_token"],
-        refresh_token=tokens.get("refresh_token", ""),
-        account_id=tokens.get("account_id", ""),
-        id_token=tokens.get("id_token"),
-    )
+    try:
+        data = json.loads(path.read_text())
+        tokens = data.get("tokens", {})
+        return CodexAuth(
+            access_token=tokens["access_token"],
+            refresh_token=tokens.get("refresh_token", ""),
+            account_id=tokens.get("account_id", ""),
+            id_token=tokens.get("id_token"),
+        )
+    except OSError as exc:
+        raise ConfigError(f"Cannot read Codex auth file {path}: {exc.strerror or exc}") from exc
+    except (ValueError, AttributeError, KeyError, TypeError) as exc:
+        raise ConfigError(f"Malformed Codex auth file {path}: {exc!r}") from exc
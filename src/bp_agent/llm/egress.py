@@ -0,0 +1,131 @@
+"""Outbound network policy: which hosts adapters and network tools may reach.
+
+Off by default. A policy is either process-wide (set_egress_policy) or an
+agent's own (AgentConfig.egress_allowlist, handed to its adapters and network
+tools); a request must pass both. Every provider request and every
+http_request/manifest HTTP tool call is checked, so a prompt-injected URL or a
+mistyped base_url cannot send data elsewhere.
+
+Loopback is never in the default list: an adapter may reach its own loopback
+base_url (a local Ollama or vLLM server), but that allowance is not shared with
+the network tools.
+"""
+
+from __future__ import annotations
+
+import fnmatch
+import ipaddress
+import threading
+from typing import Iterable, Optional
+from urllib.parse import urlsplit
+
+from .types import ProviderError
+
+# Hosts of the built-in providers' default endpoints
+PROVIDER_HOSTS = [
+    "generativelanguage.googleapis.com",
+    "api.openai.com",
+    "chatgpt.com",
+    "api.anthropic.com",
+]
+
+
+_DEFAULT_PORTS = {"http": 80, "https": 443}
+
+
+def url_matches(url: str, patterns: Iterable[str]) -> bool:
+    """True if url is http(s) and matches a host glob ("*.example.com") or URL prefix.
+
+    A URL prefix matches on parsed parts, never on the raw string: same scheme,
+    host and port, and a path under the prefix's path on a "/" boundary (so
+    "https://api.github.com" admits neither "https://api.github.com@evil.test"
+    nor "https://api.github.com.evil.test").
+    """
+    parts = urlsplit(url)
+    try:
+        port = parts.port or _DEFAULT_PORTS.get(parts.scheme)
+    except ValueError:  # malformed port
+        return False
+    if parts.scheme not in _DEFAULT_PORTS or not parts.hostname:
+        return False
+    for pattern in patterns:
+        if "://" in pattern:
+            if _prefix_matches(parts, port, pattern):
+                return True
+        elif fnmatch.fnmatch(parts.hostname, pattern.lower()):
+            return True
+    return False
+
+
+def is_local_host(host: Optional[str]) -> bool:
+    """True for localhost names and loopback, link-local or unspecified addresses."""
+    if not host:
+        return False
+    host = host.lower().rstrip(".")
+    if host == "localhost" or host.endswith(".localhost"):
+        return True
+    try:
+        address = ipaddress.ip_address(host)
+    except ValueError:
+        return False
+    address = getattr(address, "ipv4_mapped", None) or address
+    return address.is_loopback or address.is_link_local or address.is_unspecified
+
+
+def _prefix_matches(parts, port: Optional[int], pattern: str) -> bool:
+    allowed = urlsplit(pattern)
+    try:
+        allowed_port = allowed.port or _DEFAULT_PORTS.get(allowed.scheme)
+    except ValueError:
+        return False
+    if (allowed.scheme, allowed.hostname, allowed_port) != (parts.scheme, parts.hostname, port):
+        return False
+    prefix = allowed.path.rstrip("/")
+    path = parts.path or "/"
+    return not prefix or path == prefix or path.startswith(prefix + "/")
+
+
+class EgressPolicy:
+    """Allowed outbound destinations: host globs or URL prefixes."""
+
+    def __init__(self, allow: Iterable[str] = (), providers: bool = True):
+        self.allow: list[str] = [*(PROVIDER_HOSTS if providers else []), *allow]
+        self._lock = threading.Lock()
+
+    def add(self, *patterns: str) -> None:
+        with self._lock:
+            self.allow.extend(pattern for pattern in patterns if pattern not in self.allow)
+
+    def allows(self, url: str) -> bool:
+        with self._lock:
+            return url_matches(url, list(self.allow))
+
+
+_policy: Optional[EgressPolicy] = None
+
+
+def set_egress_policy(policy: Optional[EgressPolicy]) -> None:
+    """Install the process-wide policy (None lifts the restriction)."""
+    global _policy
+    _policy = policy
+
+
+def egress_policy() -> Optional[EgressPolicy]:
+    return _policy
+
+
+def egress_allowed(url: str, policy: Optional[EgressPolicy] = None) -> bool:
+    """Allowed by the process-wide policy and by `policy` (an agent's own), where set."""
+    return (_policy is None or _policy.allows(url)) and (policy is None or policy.allows(url))
+
+
+def check_egress(url: str, policy: Optional[EgressPolicy] = None, local_base: Optional[str] = None) -> None:
+    """Raise a non-retryable ProviderError if a policy blocks url.
+
+    local_base: the adapter's own base_url; if it is a loopback address, URLs under it pass.
+    """
+    if local_base and is_local_host(urlsplit(local_base).hostname) and url_matches(url, [local_base]):
+        return
+    if not egress_allowed(url, policy):
+        host = urlsplit(url).hostname or url
+        raise ProviderError("egress_denied", f"Outbound host not allowed by egress policy: {host}", retryable=False)
@@ -0,0 +1,204 @@
+"""Recorded provider responses ("test vectors") replayed through adapter parsers.
+
+A fixture file is JSON - one object or a list of them:
+
+    {"name": "gemini-tool-call", "provider": "gemini",
+     "response": {...raw provider body...},        # or "sse": ["data: ...", ...]
+     "expected": {"content": "...", "tool_calls": [{"name": "bash", "args": {...}, "id": null}]}}
+
+Only the keys present in "expected" are compared (content, tool_calls, logprobs, finish_reason, usage).
+The vectors this package ships with live in llm/vectors/.
+"""
+
+from __future__ import annotations
+
+import json
+import math
+from dataclasses import dataclass, field
+from pathlib import Path
+from typing import Any, Callable, Iterable, Optional, Union
+
+from ..errors import ConfigError
+from .types import LLMResponse, StreamIterator, accumulate_stream
+
+VECTORS_DIR = Path(__file__).parent / "vectors"
+
+ParseFn = Callable[[dict], LLMResponse]
+StreamFn = Callable[[Iterable[str]], StreamIterator]
+
+
+@dataclass
+class Fixture:
+    name: str
+    provider: str
+    expected: dict[str, Any]
+    response: Optional[dict] = None  # raw body for the non-streaming parser
+    sse: Optional[list[str]] = None  # raw stream lines (SSE, or NDJSON for ollama) for the streaming parser
+    source: Optional[str] = None  # file it was loaded from
+
+    @classmethod
+    def from_dict(cls, data: dict, source: Optional[str] = None) -> "Fixture":
+        missing = [key for key in ("name", "provider", "expected") if key not in data]
+        if missing or ("response" in data) == ("sse" in data):
+            raise ConfigError(
+                f"Invalid fixture {data.get('name', '?')!r}: needs name, provider, expected and one of response/sse"
+            )
+        return cls(
+            name=data["name"],
+            provider=data["provider"],
+            expected=data["expected"],
+            response=data.get("response"),
+            sse=data.get("sse"),
+            source=source,
+        )
+
+
+@dataclass
+class FixtureResult:
+    fixture: Fixture
+    errors: list[str] = field(default_factory=list)
+    response: Optional[LLMResponse] = None
+
+    @property
+    def ok(self) -> bool:
+        return not self.errors
+
+
+class _Lines:
+    """Just enough of a requests.Response for the adapters' _iter_sse."""
+
+    def __init__(self, lines: Iterable[str]):
+        self._lines = list(lines)
+
+    def iter_lines(self, decode_unicode: bool = True):
+        return iter(self._lines)
+
+    def close(self):
+        pass
+
+
+def _adapter(provider: str):
+    from .codex_adapter import CodexAdapter, CodexConfig
+    from .gemini_adapter import GeminiAdapter, GeminiConfig
+    from .ollama_adapter import OllamaAdapter, OllamaConfig
+    from .openai_adapter import OpenAIAdapter, OpenAIConfig
+    from .opus_adapter import OpusAdapter, OpusConfig
+
+    # Parsers never touch the network, so placeholder credentials are fine
+    factories = {
+        "gemini": lambda: GeminiAdapter(GeminiConfig(api_keys=["fixture"])),
+        "codex": lambda: CodexAdapter(CodexConfig(api_keys=["fixture"])),
+        "opus": lambda: OpusAdapter(OpusConfig(api_keys=["fixture"], base_url="")),
+        "openai": lambda: OpenAIAdapter(OpenAIConfig()),
+        "ollama": lambda: OllamaAdapter(OllamaConfig()),
+    }
+    return factories[provider]() if provider in factories else None
+
+
+_PARSERS: dict[str, ParseFn] = {}
+_STREAM_PARSERS: dict[str, StreamFn] = {}
+_FIXTURES: dict[str, Fixture] = {}
+
+
+def register_parser(provider: str, parse: Optional[ParseFn] = None, stream: Optional[StreamFn] = None):
+    """Use these parsers for `provider` fixtures (e.g. a custom adapter's)."""
+    if parse is not None:
+        _PARSERS[provider] = parse
+    if stream is not None:
+        _STREAM_PARSERS[provider] = stream
+
+
+def register_fixture(fixture: Union[Fixture, dict]) -> Fixture:
+    """Add a fixture to the set check_fixtures() runs by default; same name replaces."""
+    if isinstance(fixture, dict):
+        fixture = Fixture.from_dict(fixture)
+    _FIXTURES[fixture.name] = fixture
+    return fixture
+
+
+def registered_fixtures() -> list[Fixture]:
+    return list(_FIXTURES.values())
+
+
+def load_fixtures(path: Union[str, Path], register: bool = False) -> list[Fixture]:
+    """Read a fixture file, or every *.json file in a directory (sorted)."""
+    path = Path(path)
+    files = sorted(path.glob("*.json")) if path.is_dir() else [path]
+    fixtures: list[Fixture] = []
+    for file in files:
+        try:
+            data = json.loads(file.read_text(encoding="utf-8"))
+        except (OSError, ValueError) as exc:
+            raise ConfigError(f"Cannot read fixture file {file}: {exc}") from None
+        for item in data if isinstance(data, list) else [data]:
+            fixtures.append(Fixture.from_dict(item, source=str(file)))
+    if register:
+        for fixture in fixtures:
+            register_fixture(fixture)
+    return fixtures
+
+
+def builtin_fixtures() -> list[Fixture]:
+    return load_fixtures(VECTORS_DIR)
+
+
+def parse_fixture(fixture: Fixture) -> LLMResponse:
+    """Run a fixture's recorded body (or SSE lines) through its provider's parser."""
+    if fixture.sse is not None:
+        stream = _STREAM_PARSERS.get(fixture.provider)
+        if stream is None:
+            adapter = _adapter(fixture.provider)
+            # _iter_stream: adapters whose wire format is not SSE (ollama's NDJSON)
+            iterate = getattr(adapter, "_iter_sse", None) or getattr(adapter, "_iter_stream", None)
+            if iterate is None:
+                raise ConfigError(f"No stream parser registered for provider {fixture.provider!r}")
+            return accumulate_stream(iterate(_Lines(fixture.sse)))
+        return accumulate_stream(stream(fixture.sse))
+
+    parse = _PARSERS.get(fixture.provider)
+    if parse is None:
+        adapter = _adapter(fixture.provider)
+        if adapter is None:
+            raise ConfigError(f"No parser registered for provider {fixture.provider!r}")
+        parse = adapter._parse_response
+    return parse(fixture.response or {})
+
+
+def run_fixture(fixture: Fixture) -> FixtureResult:
+    result = FixtureResult(fixture=fixture)
+    try:
+        response = parse_fixture(fixture)
+    except Exception as exc:
+        result.errors.append(f"parser raised {type(exc).__name__}: {exc}")
+        return result
+    result.response = response
+    expected = fixture.expected
+
+    if "content" in expected and response.content != expected["content"]:
+        result.errors.append(f"content: expected {expected['content']!r}, got {response.content!r}")
+    if "tool_calls" in expected:
+        got = [{"name": c.name, "args": c.args, "id": c.id} for c in response.tool_calls or []]
+        want = [{"name": c["name"], "args": c.get("args", {}), "id": c.get("id")} for c in expected["tool_calls"] or []]
+        if got != want:
+            result.errors.append(f"tool_calls: expected {want}, got {got}")
+    if "finish_reason" in expected and response.finish_reason != expected["finish_reason"]:
+        result.errors.append(f"finish_reason: expected {expected['finish_reason']!r}, got {response.finish_reason!r}")
+    if "usage" in expected:
+        got_usage = response.usage.to_dict() if response.usage is not None else None
+        if got_usage != expected["usage"]:
+            result.errors.append(f"usage: expected {expected['usage']}, got {got_usage}")
+    if "logprobs" in expected:
+        want_lp, got_lp = expected["logprobs"], response.logprobs
+        if (want_lp is None) != (got_lp is None) or (
+            want_lp is not None
+            and (len(want_lp) != len(got_lp) or not all(math.isclose(a, b) for a, b in zip(want_lp, got_lp)))
+        ):
+            result.errors.append(f"logprobs: expected {want_lp}, got {got_lp}")
+    return result
+
+
+def check_fixtures(fixtures: Optional[Iterable[Fixture]] = None) -> list[FixtureResult]:
+    """Run fixtures (default: built-in vectors plus registered ones) and report each."""
+    if fixtures is None:
+        fixtures = [*builtin_fixtures(), *registered_fixtures()]
+    return [run_fixture(fixture) for fixture in fixtures]
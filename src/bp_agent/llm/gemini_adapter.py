@@ -3,12 +3,17 @@
 from __future__ import annotations
 
 from dataclasses import dataclass
-from typing import Optional
+from typing import Any, Optional
 
 import requests
 
-from .rotation import RotationManager, RotationSlot
-from .types import CompletionRequest, LLMResponse, ToolCall, ProviderError, StreamChunk, StreamIterator
+from ..errors import ConfigError
+from .egress import EgressPolicy, check_egress
+from .rotation import RotationManager, RotationSlot, key_fingerprint
+from .types import (
+    SYSTEM_ROLES, CompletionRequest, LLMResponse, ToolCall, ProviderError, StreamChunk, StreamIterator,
+    ToolCallDelta, call_timeout, normalize_finish_reason, request_deadline, usage_from_raw,
+)
 
 GEMINI_ALLOWED_MODELS = ["gemini-3-flash-preview", "gemini-3-pro-preview"]
 
@@ -22,13 +27,21 @@ class GeminiConfig:
 
 
 class GeminiAdapter:
+    egress: Optional[EgressPolicy] = None  # the owning agent's policy (LLMRouter.set_egress_policy)
+
     def __init__(self, config: GeminiConfig, rotation: RotationManager | None = None):
         if not config.api_keys:
-            raise ValueError("Gemini api_keys required")
+            raise ConfigError("Gemini api_keys required")
         self.config = config
         self.rotation = rotation or RotationManager()
         for key in config.api_keys:
-            self.rotation.add_slot(RotationSlot(id=key))
+            self.add_key(key)
+
+    def add_key(self, key: str) -> str:
+        """Put a key into rotation (takes effect on the next request); returns its fingerprint."""
+        fingerprint = key_fingerprint(key)
+        self.rotation.add_slot(RotationSlot(id=key, secret=key, fingerprint=fingerprint))
+        return fingerprint
 
     def complete(self, request: CompletionRequest) -> LLMResponse:
         model = request.model or self.config.model
@@ -38,30 +51,47 @@ class GeminiAdapter:
         temperature = request.temperature if request.temperature is not None else self.config.temperature
         payload = self._build_request(request, temperature)
 
+        deadline = request_deadline(request)
         attempt = 0
         while True:
             attempt += 1
+            timeout = call_timeout(deadline, 30)
             slot = self.rotation.select_slot()
             try:
-                response = self._send_request(payload, model, slot.id)
+                response = self._send_request(payload, model, slot.id, timeout=timeout)
                 self.rotation.report_success(slot.id)
                 return self._parse_response(response)
             except ProviderError as exc:
-                if exc.code in ("rate_limit", "quota"):
-                    self.rotation.report_rate_limit(slot.id, exc.message)
-                elif exc.code == "auth_error":
-                    self.rotation.report_auth_error(slot.id)
+                self.rotation.report_error(slot.id, exc.code, exc.message)
                 if not exc.retryable or attempt > self.rotation.policy.max_retries:
                     raise
                 self.rotation.backoff(attempt)
 
     def _build_request(self, request: CompletionRequest, temperature: float) -> dict:
         contents = []
-        system_instruction = None
+        # Gemini has one systemInstruction: each system/developer message becomes a part
+        system_parts = [{"text": m.content} for m in request.messages if m.role in SYSTEM_ROLES and m.content]
 
         for msg in request.messages:
-            if msg.role == "system":
-                system_instruction = msg.content
+            if msg.role in SYSTEM_ROLES:
+                continue
+            elif msg.role == "tool":
+                part = {"functionResponse": {"name": msg.name or "", "response": {"content": msg.content}}}
+                if msg.tool_call_id:
+                    part["functionResponse"]["id"] = msg.tool_call_id
+                # All responses to one model turn go in a single user turn
+                if contents and contents[-1]["role"] == "user" and "functionResponse" in contents[-1]["parts"][0]:
+                    contents[-1]["parts"].append(part)
+                else:
+                    contents.append({"role": "user", "parts": [part]})
+            elif msg.role == "assistant" and msg.tool_calls:
+                parts = [{"text": msg.content}] if msg.content else []
+                for call in msg.tool_calls:
+                    fc = {"name": call.name, "args": call.args}
+                    if call.id:
+                        fc["id"] = call.id
+                    parts.append({"functionCall": fc})
+                contents.append({"role": "model", "parts": parts})
             else:
                 role = "user" if msg.role == "user" else "model"
                 contents.append({"role": role, "parts": [{"text": msg.content}]})
@@ -70,9 +100,13 @@ class GeminiAdapter:
             "contents": contents,
             "generationConfig": {"temperature": temperature},
         }
+        if request.logprobs:
+            payload["generationConfig"]["responseLogprobs"] = True
+            if request.top_logprobs:
+                payload["generationConfig"]["logprobs"] = request.top_logprobs
 
-        if system_instruction:
-            payload["systemInstruction"] = {"parts": [{"text": system_instruction}]}
+        if system_parts:
+            payload["systemInstruction"] = {"parts": system_parts}
 
         if request.tools:
             payload["tools"] = [
@@ -90,31 +124,61 @@ class GeminiAdapter:
 
         return payload
 
-    def _send_request(self, payload: dict, model: str, api_key: str) -> dict:
+    def _send_request(self, payload: dict, model: str, api_key: str, timeout: Optional[float] = 30) -> dict:
         base_url = self.config.base_url.rstrip("/")
         url = f"{base_url}/v1beta/models/{model}:generateContent"
+        check_egress(url, self.egress)
         headers = {
             "Content-Type": "application/json",
             "x-goog-api-key": api_key,
         }
         try:
-            resp = requests.post(url, json=payload, headers=headers, timeout=30)
+            resp = requests.post(url, json=payload, headers=headers, timeout=timeout)
         except requests.RequestException as err:  # pragma: no cover - network issues
             raise ProviderError("network_error", str(err), retryable=True)
 
         if resp.status_code >= 400:
-            body = resp.text or ""
-            lowered = body.lower()
-            if resp.status_code in (401, 403):
-                raise ProviderError("auth_error", body or "auth error", retryable=True)
-            if resp.status_code == 429 or "quota" in lowered or "resource_exhausted" in lowered:
-                raise ProviderError("rate_limit", body or "rate limit", retryable=True)
-            if resp.status_code >= 500:
-                raise ProviderError("server_error", body or "server error", retryable=True)
-            raise ProviderError("api_error", body or "api error", retryable=False)
+            raise self._status_error(resp.status_code, resp.text or "")
 
         return resp.json()
 
+    @staticmethod
+    def _status_error(status: int, body: str) -> ProviderError:
+        lowered = body.lower()
+        if status in (401, 403):
+            return ProviderError("auth_error", body or "auth error", retryable=True)
+        if status == 429 or "quota" in lowered or "resource_exhausted" in lowered:
+            return ProviderError("rate_limit", body or "rate limit", retryable=True)
+        if status >= 500:
+            return ProviderError("server_error", body or "server error", retryable=True)
+        return ProviderError("api_error", body or "api error", retryable=False)
+
+    def list_models(self) -> list[str]:
+        """Allowed models the API currently serves (GET /v1beta/models, generateContent ones)."""
+        url = f"{self.config.base_url.rstrip('/')}/v1beta/models"
+        check_egress(url, self.egress)
+        slot = self.rotation.select_slot()
+        names: list[str] = []
+        params: dict[str, Any] = {"pageSize": 1000}
+        while True:
+            try:
+                resp = requests.get(url, params=params, headers={"x-goog-api-key": slot.id}, timeout=10)
+            except requests.RequestException as err:
+                raise ProviderError("network_error", str(err), retryable=True)
+            if resp.status_code >= 400:
+                exc = self._status_error(resp.status_code, resp.text or "")
+                self.rotation.report_error(slot.id, exc.code, exc.message)
+                raise exc
+            data = resp.json()
+            for model in data.get("models") or []:
+                if "generateContent" in (model.get("supportedGenerationMethods") or ["generateContent"]):
+                    names.append(model.get("name", "").removeprefix("models/"))
+            if not data.get("nextPageToken"):
+                break
+            params["pageToken"] = data["nextPageToken"]
+        self.rotation.report_success(slot.id)
+        return [name for name in names if name in GEMINI_ALLOWED_MODELS]
+
     def complete_stream(self, request: CompletionRequest) -> StreamIterator:
         model = request.model or self.config.model
         if model not in GEMINI_ALLOWED_MODELS:
@@ -126,22 +190,29 @@ class GeminiAdapter:
         slot = self.rotation.select_slot()
         base_url = self.config.base_url.rstrip("/")
         url = f"{base_url}/v1beta/models/{model}:streamGenerateContent?alt=sse"
+        check_egress(url, self.egress)
         headers = {
             "Content-Type": "application/json",
             "x-goog-api-key": slot.id,
         }
         try:
-            resp = requests.post(url, json=payload, headers=headers, timeout=60, stream=True)
+            timeout = call_timeout(request_deadline(request), 60)
+            resp = requests.post(url, json=payload, headers=headers, timeout=timeout, stream=True)
         except requests.RequestException as err:
+            self.rotation.report_failure(slot.id, str(err))
             raise ProviderError("network_error", str(err), retryable=True)
 
         if resp.status_code >= 400:
             body = resp.text or ""
+            resp.close()
             if resp.status_code in (401, 403):
+                self.rotation.report_auth_error(slot.id)
                 raise ProviderError("auth_error", body or "auth error", retryable=True)
             if resp.status_code == 429:
+                self.rotation.report_rate_limit(slot.id, body)
                 raise ProviderError("rate_limit", body or "rate limit", retryable=True)
             if resp.status_code >= 500:
+                self.rotation.report_failure(slot.id, body or "server error")
                 raise ProviderError("server_error", body or "server error", retryable=True)
             raise ProviderError("api_error", body or "api error", retryable=False)
 
@@ -150,25 +221,45 @@ class GeminiAdapter:
 
     def _iter_sse(self, resp) -> StreamIterator:
         import json as _json
-        for line in resp.iter_lines(decode_unicode=True):
-            if not line or not line.startswith("data: "):
-                continue
-            data_str = line[len("data: "):]
-            if data_str.strip() == "[DONE]":
-                yield StreamChunk(finish_reason="stop")
-                return
-            try:
-                data = _json.loads(data_str)
-            except (ValueError, _json.JSONDecodeError):
-                continue
-            candidates = data.get("candidates", [])
-            if not candidates:
-                continue
-            content = candidates[0].get("content", {})
-            for part in content.get("parts", []):
-                if "text" in part:
-                    yield StreamChunk(delta=part["text"])
-        yield StreamChunk(finish_reason="stop")
+        # Closing the generator (client went away) releases the HTTP connection
+        call_index = 0
+        finish_reason = "stop"
+        try:
+            for line in resp.iter_lines(decode_unicode=True):
+                if not line or not line.startswith("data: "):
+                    continue
+                data_str = line[len("data: "):]
+                if data_str.strip() == "[DONE]":
+                    yield StreamChunk(finish_reason=finish_reason)
+                    return
+                try:
+                    data = _json.loads(data_str)
+                except (ValueError, _json.JSONDecodeError):
+                    continue
+                if "usageMetadata" in data:
+                    yield StreamChunk(usage=usage_from_raw(data))  # running totals; the last one is final
+                candidates = data.get("candidates", [])
+                if not candidates:
+                    continue
+                if candidates[0].get("finishReason"):
+                    finish_reason = normalize_finish_reason(candidates[0]["finishReason"])
+                content = candidates[0].get("content", {})
+                for part in content.get("parts", []):
+                    if "text" in part:
+                        yield StreamChunk(delta=part["text"])
+                    if "functionCall" in part:
+                        # Gemini sends each call whole, never as argument fragments
+                        fc = part["functionCall"]
+                        yield StreamChunk(tool_call_delta=ToolCallDelta(
+                            index=call_index,
+                            name=fc.get("name", ""),
+                            args_delta=_json.dumps(fc.get("args") or {}),
+                            id=fc.get("id"),
+                        ))
+                        call_index += 1
+            yield StreamChunk(finish_reason=finish_reason)
+        finally:
+            resp.close()
 
     def _parse_response(self, response: dict) -> LLMResponse:
         candidates = response.get("candidates", [])
@@ -184,6 +275,10 @@ class GeminiAdapter:
                 text += part["text"]
             if "functionCall" in part:
                 fc = part["functionCall"]
-                tool_calls.append(ToolCall(name=fc.get("name", ""), args=fc.get("args", {})))
+                tool_calls.append(ToolCall(name=fc.get("name", ""), args=fc.get("args", {}), id=fc.get("id")))
 
-        return LLMResponse(content=text, tool_calls=tool_calls if tool_calls else None, raw=response)
+        chosen = (candidates[0].get("logprobsResult") or {}).get("chosenCandidates") or []
+        logprobs = [c["logProbability"] for c in chosen if "logProbability" in c] or None
+        return LLMResponse(
+            content=text, tool_calls=tool_calls if tool_calls else None, raw=response, logprobs=logprobs,
+        )
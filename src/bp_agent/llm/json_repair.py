@@ -0,0 +1,127 @@
+"""Lenient parsing of model-emitted tool arguments.
+
+Models regularly produce almost-JSON: fenced blocks, single quotes, trailing
+commas, bare keys, Python literals or a truncated closing brace. Strict
+parsing is tried first; the repair pass only runs when that fails.
+"""
+
+from __future__ import annotations
+
+import json
+import re
+from typing import Any, Optional
+
+_FENCE = re.compile(r"^\s*```[\w-]*\s*\n?(.*?)\n?\s*```\s*$", re.DOTALL)
+_BARE_WORDS = {"True": "true", "False": "false", "None": "null", "undefined": "null"}
+
+
+def parse_tool_args(raw: Any) -> tuple[dict, Optional[str]]:
+    """Return (args, repaired_json).
+
+    repaired_json is None when raw parsed as-is, and "" when it could not be
+    repaired (args is then {}, matching the old strict behavior).
+    """
+    if isinstance(raw, dict):
+        return raw, None
+    if not isinstance(raw, str) or not raw.strip():
+        return {}, None
+    try:
+        value = json.loads(raw)
+    except ValueError:
+        pass
+    else:
+        return (value, None) if isinstance(value, dict) else ({}, "")
+
+    repaired = repair_json(raw)
+    try:
+        value = json.loads(repaired)
+    except ValueError:
+        return {}, ""
+    return (value, repaired) if isinstance(value, dict) else ({}, "")
+
+
+def repair_json(text: str) -> str:
+    """Best-effort rewrite of almost-JSON into JSON (the result may still be invalid)."""
+    fenced = _FENCE.match(text)
+    if fenced:
+        text = fenced.group(1)
+    start = text.find("{")
+    if start > 0:
+        text = text[start:]  # drop prose before the object
+
+    out: list[str] = []
+    stack: list[str] = []
+    i, n = 0, len(text)
+    while i < n:
+        ch = text[i]
+        if ch in "\"'":
+            literal, i = _read_string(text, i)
+            out.append(literal)
+            continue
+        if ch in "{[":
+            stack.append("}" if ch == "{" else "]")
+            out.append(ch)
+        elif ch in "}]":
+            _drop_trailing_comma(out)
+            if stack:
+                stack.pop()
+            out.append(ch)
+            if not stack:
+                break  # ignore anything after the top-level object
+        elif ch.isalpha() or ch == "_":
+            j = i
+            while j < n and (text[j].isalnum() or text[j] == "_"):
+                j += 1
+            word = text[i:j]
+            rest = text[j:].lstrip()
+            if rest.startswith(":"):
+                out.append(json.dumps(word))  # bare key
+            else:
+                out.append(_BARE_WORDS.get(word, word))
+            i = j
+            continue
+        else:
+            out.append(ch)
+        i += 1
+
+    _drop_trailing_comma(out)
+    out.extend(reversed(stack))  # close truncated objects/arrays
+    return "".join(out)
+
+
+def _read_string(text: str, i: int) -> tuple[str, int]:
+    """Read a '...' or "..." literal starting at i and return it as a JSON string."""
+    quote = text[i]
+    chars: list[str] = []
+    i += 1
+    while i < len(text):
+        ch = text[i]
+        if ch == "\\" and i + 1 < len(text):
+            nxt = text[i + 1]
+            if nxt == "'":
+                chars.append("'")  # \' is not a JSON escape
+            elif nxt == '"':
+                chars.append('\\"')
+            else:
+                chars.append(ch + nxt)
+            i += 2
+            continue
+        if ch == quote:
+            i += 1
+            break
+        if ch == "\n":
+            chars.append("\\n")
+        elif ch == '"':
+            chars.append('\\"')
+        else:
+            chars.append(ch)
+        i += 1
+    return '"' + "".join(chars) + '"', i
+
+
+def _drop_trailing_comma(out: list[str]):
+    j = len(out) - 1
+    while j >= 0 and out[j].isspace():
+        j -= 1
+    if j >= 0 and out[j] == ",":
+        del out[j]
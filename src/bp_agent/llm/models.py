@@ -0,0 +1,97 @@
+"""Provider model lists, cached with stale-while-revalidate."""
+
+from __future__ import annotations
+
+import threading
+import time
+from dataclasses import dataclass, field
+from typing import TYPE_CHECKING, Callable, Mapping, Optional
+
+if TYPE_CHECKING:
+    from .router import LLMRouter
+
+
+@dataclass
+class _Entry:
+    models: list[str] = field(default_factory=list)
+    fetched_at: Optional[float] = None  # last successful fetch (None = never)
+    checked_at: Optional[float] = None  # last attempt, successful or not
+    error: Optional[str] = None  # why the last attempt failed
+    refreshing: bool = False
+
+
+class ModelCatalog:
+    """Currently available model names per provider.
+
+    The first call per provider fetches the list (router.list_models). After
+    that the cached list is always served right away: once it is older than
+    ttl, a background thread refreshes it, and a failed refresh keeps the old
+    list. Lists are narrowed to `allow` (provider -> model names) when given.
+    Providers that can't list models, or whose first fetch failed, report the
+    allowlist, else their configured model.
+    """
+
+    def __init__(
+        self,
+        router: "LLMRouter",
+        ttl: float = 3600.0,
+        allow: Optional[Mapping[str, list[str]]] = None,
+        clock: Callable[[], float] = time.time,
+    ):
+        self.router = router
+        self.ttl = ttl
+        self.allow = dict(allow or {})
+        self._clock = clock
+        self._entries: dict[str, _Entry] = {}
+        self._lock = threading.Lock()
+
+    def models(self, provider: str) -> list[str]:
+        return self.info(provider)["models"]
+
+    def all(self) -> dict[str, list[str]]:
+        return {provider: self.models(provider) for provider in self.router.providers()}
+
+    def info(self, provider: str) -> dict:
+        """models, source (live | stale | configured), fetched_at and the last fetch error."""
+        with self._lock:
+            entry = self._entries.setdefault(provider, _Entry())
+            first = entry.checked_at is None
+            due = not first and self._clock() - entry.checked_at >= self.ttl and not entry.refreshing
+            if due:
+                entry.refreshing = True
+        if first:
+            self.refresh(provider)
+        elif due:
+            threading.Thread(target=self.refresh, args=(provider,), daemon=True).start()
+
+        with self._lock:
+            entry = self._entries[provider]
+            if entry.fetched_at is None:
+                models, source = self._configured(provider), "configured"
+            else:
+                models = entry.models
+                source = "stale" if self._clock() - entry.fetched_at >= self.ttl else "live"
+            return {"models": list(models), "source": source, "fetched_at": entry.fetched_at, "error": entry.error}
+
+    def refresh(self, provider: str) -> None:
+        """Fetch the provider's list now (what the background refresh runs)."""
+        error, listed = None, None
+        try:
+            listed = self.router.list_models(provider)
+        except Exception as exc:
+            error = f"{type(exc).__name__}: {exc}"
+        with self._lock:
+            entry = self._entries.setdefault(provider, _Entry())
+            entry.checked_at = self._clock()
+            entry.refreshing = False
+            entry.error = error
+            if listed is not None:
+                allowed = self.allow.get(provider)
+                entry.models = [name for name in listed if allowed is None or name in allowed]
+                entry.fetched_at = entry.checked_at
+
+    def _configured(self, provider: str) -> list[str]:
+        if provider in self.allow:
+            return list(self.allow[provider])
+        model = self.router.default_model(provider)
+        return [model] if model else []
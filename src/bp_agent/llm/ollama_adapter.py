@@ -0,0 +1,175 @@
+"""Ollama adapter - local models over the Ollama HTTP API, no keys needed."""
+
+from __future__ import annotations
+
+import json
+from dataclasses import dataclass
+from typing import Any, Optional
+from urllib import request as urlrequest, error as urlerror
+
+import requests as http_requests
+
+from .egress import EgressPolicy, check_egress
+from .types import (
+    CompletionRequest, LLMResponse, Message, ToolCall, ProviderError, StreamChunk, StreamIterator, ToolCallDelta,
+    call_timeout, parse_tool_call, request_deadline, usage_from_raw,
+)
+
+OLLAMA_BASE_URL = "http://localhost:11434"
+
+
+@dataclass
+class OllamaConfig:
+    base_url: str = OLLAMA_BASE_URL
+    model: str = "llama3.2"
+    models: Optional[list[str]] = None  # allowed models; None = whatever the server has pulled
+    temperature: float = 0.3
+    keep_alive: Optional[str] = None  # how long the server keeps the model loaded, e.g. "10m"
+
+
+def normalize_base_url(value: str) -> str:
+    """OLLAMA_HOST style values ("0.0.0.0:11434") get a scheme."""
+    value = value.strip().rstrip("/")
+    return value if "://" in value else f"http://{value}"
+
+
+class OllamaAdapter:
+    egress: Optional[EgressPolicy] = None  # the owning agent's policy (LLMRouter.set_egress_policy)
+
+    def __init__(self, config: OllamaConfig):
+        self.config = config
+
+    def complete(self, request: CompletionRequest) -> LLMResponse:
+        payload = self._build_payload(request)
+        timeout = call_timeout(request_deadline(request), None)
+        return self._parse_response(self._post("/api/chat", payload, timeout=timeout))
+
+    def complete_stream(self, request: CompletionRequest) -> StreamIterator:
+        payload = self._build_payload(request)
+        payload["stream"] = True
+        check_egress(f"{self.config.base_url}/api/chat", self.egress, local_base=self.config.base_url)
+        try:
+            timeout = call_timeout(request_deadline(request), None)
+            resp = http_requests.post(f"{self.config.base_url}/api/chat", json=payload, timeout=timeout, stream=True)
+        except http_requests.RequestException as err:
+            raise ProviderError("network_error", f"Ollama not reachable at {self.config.base_url}: {err}", retryable=True)
+        if resp.status_code >= 400:
+            exc = self._status_error(resp.status_code, resp.text or "")
+            resp.close()
+            raise exc
+        return self._iter_stream(resp)
+
+    def list_models(self) -> list[str]:
+        """Models pulled on the server (GET /api/tags)."""
+        check_egress(f"{self.config.base_url}/api/tags", self.egress, local_base=self.config.base_url)
+        req = urlrequest.Request(f"{self.config.base_url}/api/tags", method="GET")
+        try:
+            with urlrequest.urlopen(req, timeout=10) as resp:
+                data = json.loads(resp.read().decode("utf-8"))
+        except urlerror.HTTPError as err:
+            raise self._status_error(err.code, err.read().decode("utf-8") if err.fp else "")
+        except urlerror.URLError as err:
+            raise ProviderError("network_error", f"Ollama not reachable at {self.config.base_url}: {err}", retryable=True)
+        names = [m.get("name") or m.get("model", "") for m in data.get("models") or []]
+        if self.config.models is not None:
+            names = [name for name in names if name in self.config.models]
+        return names
+
+    def _build_payload(self, request: CompletionRequest) -> dict:
+        model = request.model or self.config.model
+        if self.config.models is not None and model not in self.config.models:
+            raise ProviderError("invalid_model", f"Model {model} not allowed", retryable=False)
+        payload: dict[str, Any] = {
+            "model": model,
+            "messages": [_message_payload(m) for m in request.messages],
+            "stream": False,
+            "options": {
+                "temperature": request.temperature if request.temperature is not None else self.config.temperature,
+            },
+        }
+        if self.config.keep_alive:
+            payload["keep_alive"] = self.config.keep_alive
+        if request.tools:
+            payload["tools"] = [
+                {
+                    "type": "function",
+                    "function": {"name": t.name, "description": t.description, "parameters": t.parameters},
+                }
+                for t in request.tools
+            ]
+        return payload
+
+    @staticmethod
+    def _status_error(status: int, body: str) -> ProviderError:
+        if status == 404 and "not found" in body:
+            # e.g. {"error": "model \"llama3.2\" not found, try pulling it first"}
+            return ProviderError("invalid_model", body, retryable=False)
+        if status >= 500:
+            return ProviderError("server_error", body or "server error", retryable=True)
+        return ProviderError("api_error", body or "api error", retryable=False)
+
+    def _post(self, path: str, payload: dict, timeout: Optional[float] = None) -> dict:
+        check_egress(f"{self.config.base_url}{path}", self.egress, local_base=self.config.base_url)
+        data = json.dumps(payload).encode("utf-8")
+        req = urlrequest.Request(f"{self.config.base_url}{path}", data=data, method="POST")
+        req.add_header("Content-Type", "application/json")
+        try:
+            with urlrequest.urlopen(req, timeout=timeout) as resp:
+                return json.loads(resp.read().decode("utf-8"))
+        except urlerror.HTTPError as err:
+            raise self._status_error(err.code, err.read().decode("utf-8") if err.fp else "")
+        except urlerror.URLError as err:
+            raise ProviderError("network_error", f"Ollama not reachable at {self.config.base_url}: {err}", retryable=True)
+
+    def _iter_stream(self, resp) -> StreamIterator:
+        # Ollama streams newline-delimited JSON objects, not SSE
+        call_index = 0
+        try:
+            for line in resp.iter_lines(decode_unicode=True):
+                if not line:
+                    continue
+                try:
+                    event = json.loads(line)
+                except (ValueError, json.JSONDecodeError):
+                    continue
+                if event.get("error"):
+                    raise ProviderError("server_error", event["error"], retryable=False)
+                message = event.get("message") or {}
+                if message.get("content"):
+                    yield StreamChunk(delta=message["content"])
+                for call in message.get("tool_calls") or []:
+                    # tool calls arrive whole, never as argument fragments
+                    function = call.get("function") or {}
+                    yield StreamChunk(tool_call_delta=ToolCallDelta(
+                        index=call_index,
+                        name=function.get("name", ""),
+                        args_delta=json.dumps(function.get("arguments") or {}),
+                        id=call.get("id"),
+                    ))
+                    call_index += 1
+                if event.get("done"):
+                    yield StreamChunk(finish_reason=event.get("done_reason") or "stop", usage=usage_from_raw(event))
+                    return
+            yield StreamChunk(finish_reason="stop")
+        finally:
+            resp.close()
+
+    def _parse_response(self, response: dict) -> LLMResponse:
+        if response.get("error"):
+            raise ProviderError("api_error", response["error"], retryable=False)
+        message = response.get("message") or {}
+        tool_calls: list[ToolCall] = []
+        for call in message.get("tool_calls") or []:
+            function = call.get("function") or {}
+            tool_calls.append(parse_tool_call(function.get("name", ""), function.get("arguments") or {}, call.get("id")))
+        return LLMResponse(content=message.get("content") or "", tool_calls=tool_calls or None, raw=response)
+
+
+def _message_payload(msg: Message) -> dict:
+    if msg.role == "tool":
+        return {"role": "tool", "content": msg.content, "tool_name": msg.name or ""}
+    payload: dict[str, Any] = {"role": "system" if msg.role == "developer" else msg.role, "content": msg.content}
+    if msg.role == "assistant" and msg.tool_calls:
+        # Ollama takes arguments as an object, not a JSON string
+        payload["tool_calls"] = [{"function": {"name": c.name, "arguments": c.args}} for c in msg.tool_calls]
+    return payload
@@ -0,0 +1,270 @@
+"""OpenAI chat/completions adapter using shared rotation policy.
+
+Also works against OpenAI-compatible gateways (vLLM, LiteLLM, ...) via
+base_url; those often run without a key.
+"""
+
+from __future__ import annotations
+
+import itertools
+import json
+from dataclasses import dataclass, field
+from typing import Any, Optional
+from urllib import request as urlrequest, error as urlerror
+from urllib.parse import urlsplit
+
+import requests as http_requests
+
+from .egress import EgressPolicy, check_egress
+from .rotation import RotationManager, RotationSlot, key_fingerprint
+from .types import (
+    CompletionRequest, LLMResponse, Message, ToolCall, ProviderError, StreamChunk, StreamIterator, ToolCallDelta,
+    call_timeout, parse_tool_call, request_deadline, usage_from_raw,
+)
+
+
+@dataclass
+class OpenAIConfig:
+    api_keys: list[str] = field(default_factory=list)  # empty = unauthenticated gateway
+    base_url: str = "https://api.openai.com/v1"
+    model: str = "gpt-5.1"
+    temperature: Optional[float] = None  # None leaves the server default (reasoning models reject others)
+    max_tokens: Optional[int] = None  # sent as max_completion_tokens
+    extra_headers: dict[str, str] = field(default_factory=dict)
+    # Send developer messages as "developer"; None = only to api.openai.com (most compatible servers reject it)
+    developer_role: Optional[bool] = None
+
+
+class OpenAIAdapter:
+    egress: Optional[EgressPolicy] = None  # the owning agent's policy (LLMRouter.set_egress_policy)
+
+    def __init__(self, config: OpenAIConfig, rotation: RotationManager | None = None):
+        self.config = config
+        self.rotation = rotation or RotationManager()
+        self._slot_ids = itertools.count()
+        for key in config.api_keys:
+            self.add_key(key)
+        if not config.api_keys:
+            self.rotation.add_slot(RotationSlot(id="anonymous", secret=""))
+
+    def add_key(self, key: str) -> str:
+        """Put a key into rotation (takes effect on the next request); returns its fingerprint."""
+        fingerprint = key_fingerprint(key)
+        self.rotation.add_slot(RotationSlot(id=f"k{next(self._slot_ids)}", secret=key, fingerprint=fingerprint))
+        self.rotation.remove_slot("anonymous")  # once keyed, never send unauthenticated requests
+        return fingerprint
+
+    def complete(self, request: CompletionRequest) -> LLMResponse:
+        payload = self._build_payload(request)
+
+        deadline = request_deadline(request)
+        attempt = 0
+        while True:
+            attempt += 1
+            timeout = call_timeout(deadline, None)
+            slot = self.rotation.select_slot()
+            try:
+                response = self._send_request(payload, slot.secret, timeout=timeout)
+                self.rotation.report_success(slot.id)
+                return self._parse_response(response)
+            except ProviderError as exc:
+                self._report_error(slot.id, exc)
+                if not exc.retryable or attempt > self.rotation.policy.max_retries:
+                    raise
+                self.rotation.backoff(attempt)
+
+    def complete_stream(self, request: CompletionRequest) -> StreamIterator:
+        payload = self._build_payload(request)
+        payload["stream"] = True
+        payload["stream_options"] = {"include_usage": True}
+
+        check_egress(f"{self.config.base_url}/chat/completions", self.egress, local_base=self.config.base_url)
+        slot = self.rotation.select_slot()
+        try:
+            timeout = call_timeout(request_deadline(request), 60)
+            resp = http_requests.post(
+                f"{self.config.base_url}/chat/completions",
+                json=payload, headers=self._headers(slot.secret), timeout=timeout, stream=True,
+            )
+        except http_requests.RequestException as err:
+            exc = ProviderError("network_error", str(err), retryable=True)
+            self._report_error(slot.id, exc)
+            raise exc from err
+
+        if resp.status_code >= 400:
+            exc = self._status_error(resp.status_code, resp.text or "")
+            resp.close()
+            self._report_error(slot.id, exc)
+            raise exc
+
+        self.rotation.report_success(slot.id)
+        return self._iter_sse(resp)
+
+    def _developer_role(self) -> bool:
+        if self.config.developer_role is not None:
+            return self.config.developer_role
+        return urlsplit(self.config.base_url).hostname == "api.openai.com"
+
+    def _build_payload(self, request: CompletionRequest) -> dict:
+        payload: dict[str, Any] = {
+            "model": request.model or self.config.model,
+            "messages": [message_payload(m, developer_role=self._developer_role()) for m in request.messages],
+        }
+        temperature = request.temperature if request.temperature is not None else self.config.temperature
+        if temperature is not None:
+            payload["temperature"] = temperature
+        if self.config.max_tokens:
+            payload["max_completion_tokens"] = self.config.max_tokens
+        if request.logprobs:
+            payload["logprobs"] = True
+            if request.top_logprobs:
+                payload["top_logprobs"] = request.top_logprobs
+        if request.tools:
+            payload["tools"] = [
+                {
+                    "type": "function",
+                    "function": {"name": t.name, "description": t.description, "parameters": t.parameters},
+                }
+                for t in request.tools
+            ]
+        return payload
+
+    def _headers(self, key: str) -> dict[str, str]:
+        headers = {"Content-Type": "application/json", **self.config.extra_headers}
+        if key:
+            headers["Authorization"] = f"Bearer {key}"
+        return headers
+
+    @staticmethod
+    def _status_error(status: int, body: str) -> ProviderError:
+        if status in (401, 403):
+            return ProviderError("auth_error", body or "auth error", retryable=True)
+        if status == 429:
+            # insufficient_quota is per key; rotation parks the key either way
+            code = "quota" if "insufficient_quota" in body else "rate_limit"
+            return ProviderError(code, body or "rate limit", retryable=True)
+        if status >= 500:
+            return ProviderError("server_error", body or "server error", retryable=True)
+        if status == 404 and "model" in body:
+            return ProviderError("invalid_model", body, retryable=False)
+        return ProviderError("api_error", body or "api error", retryable=False)
+
+    def list_models(self) -> list[str]:
+        """Model ids the endpoint serves (GET /models)."""
+        url = f"{self.config.base_url}/models"
+        check_egress(url, self.egress, local_base=self.config.base_url)
+        slot = self.rotation.select_slot()
+        req = urlrequest.Request(url, method="GET")
+        for name, value in self._headers(slot.secret).items():
+            req.add_header(name, value)
+        try:
+            with urlrequest.urlopen(req, timeout=10) as resp:
+                data = json.loads(resp.read().decode("utf-8"))
+        except urlerror.HTTPError as err:
+            exc = self._status_error(err.code, err.read().decode("utf-8") if err.fp else "")
+            self._report_error(slot.id, exc)
+            raise exc
+        except urlerror.URLError as err:
+            raise ProviderError("network_error", str(err), retryable=True)
+        self.rotation.report_success(slot.id)
+        return [model["id"] for model in data.get("data") or [] if model.get("id")]
+
+    def _report_error(self, slot_id: str, exc: ProviderError):
+        self.rotation.report_error(slot_id, exc.code, exc.message)
+
+    def _send_request(self, payload: dict, api_key: str, timeout: Optional[float] = None) -> dict:
+        url = f"{self.config.base_url}/chat/completions"
+        check_egress(url, self.egress, local_base=self.config.base_url)
+        data = json.dumps(payload).encode("utf-8")
+        req = urlrequest.Request(url, data=data, method="POST")
+        for name, value in self._headers(api_key).items():
+            req.add_header(name, value)
+
+        try:
+            with urlrequest.urlopen(req, timeout=timeout) as resp:
+                body = resp.read().decode("utf-8")
+                return json.loads(body)
+        except urlerror.HTTPError as err:
+            body = err.read().decode("utf-8") if err.fp else ""
+            raise self._status_error(err.code, body)
+        except urlerror.URLError as err:
+            raise ProviderError("network_error", str(err), retryable=True)
+
+    def _iter_sse(self, resp) -> StreamIterator:
+        # Closing the generator (client went away) releases the HTTP connection
+        finished = False
+        try:
+            for line in resp.iter_lines(decode_unicode=True):
+                if not line or not line.startswith("data:"):
+                    continue
+                data_str = line[len("data:"):].strip()
+                if data_str == "[DONE]":
+                    break
+                try:
+                    event = json.loads(data_str)
+                except (ValueError, json.JSONDecodeError):
+                    continue
+                if event.get("usage"):
+                    # include_usage: a last chunk with no choices follows the finish_reason
+                    yield StreamChunk(usage=usage_from_raw(event))
+                for choice in event.get("choices") or []:
+                    delta = choice.get("delta") or {}
+                    if delta.get("content"):
+                        yield StreamChunk(delta=delta["content"], raw=event)
+                    for call in delta.get("tool_calls") or []:
+                        function = call.get("function") or {}
+                        yield StreamChunk(tool_call_delta=ToolCallDelta(
+                            index=call.get("index", 0),
+                            name=function.get("name"),
+                            args_delta=function.get("arguments") or "",
+                            id=call.get("id"),
+                        ))
+                    if choice.get("finish_reason"):
+                        yield StreamChunk(finish_reason=choice["finish_reason"])
+                        finished = True
+            if not finished:
+                yield StreamChunk(finish_reason="stop")
+        finally:
+            resp.close()
+
+    def _parse_response(self, response: dict) -> LLMResponse:
+        choices = response.get("choices") or []
+        if not choices:
+            raise ProviderError("api_error", f"No choices in response: {json.dumps(response)[:200]}", retryable=False)
+        choice = choices[0]
+        message = choice.get("message") or {}
+
+        content = message.get("content") or ""
+        if isinstance(content, list):  # some gateways return content parts
+            content = "".join(part.get("text", "") for part in content if isinstance(part, dict))
+
+        tool_calls: list[ToolCall] = []
+        for call in message.get("tool_calls") or []:
+            function = call.get("function") or {}
+            tool_calls.append(parse_tool_call(function.get("name", ""), function.get("arguments") or {}, call.get("id")))
+
+        logprobs = [
+            token["logprob"]
+            for token in (choice.get("logprobs") or {}).get("content") or []
+            if isinstance(token.get("logprob"), (int, float))
+        ]
+        return LLMResponse(
+            content=content, tool_calls=tool_calls or None, raw=response, logprobs=logprobs or None,
+        )
+
+
+def message_payload(msg: Message, developer_role: bool = False) -> dict:
+    """Map a Message to a chat/completions message; developer_role keeps the developer role as such."""
+    if msg.role == "tool":
+        if not msg.tool_call_id:
+            return {"role": "user", "content": msg.content}
+        return {"role": "tool", "tool_call_id": msg.tool_call_id, "content": msg.content}
+    # developer is an OpenAI role; other backends take it as another system message
+    role = "system" if msg.role == "developer" and not developer_role else msg.role
+    payload = {"role": role, "content": msg.content}
+    if msg.role == "assistant" and msg.tool_calls:
+        payload["tool_calls"] = [
+            {"id": call.id, "type": "function", "function": {"name": call.name, "arguments": json.dumps(call.args)}}
+            for call in msg.tool_calls
+        ]
+    return payload
@@ -1,63 +1,96 @@
-"""Generic Opus adapter using shared rotation policy."""
+"""Opus adapter using shared rotation policy.
+
+Speaks the Anthropic Messages API (endpoint "/messages", the default) or a
+generic OpenAI-style proxy (any other endpoint, e.g. "/responses").
+"""
 
 from __future__ import annotations
 
+import itertools
 import json
 from dataclasses import dataclass
 from typing import Optional
 from urllib import request as urlrequest, error as urlerror
 
-from .rotation import RotationManager, RotationSlot
-from .types import CompletionRequest, LLMResponse, ToolCall, ProviderError
+from ..errors import ConfigError
+from .egress import EgressPolicy, check_egress
+from .openai_adapter import message_payload
+from .rotation import RotationManager, RotationSlot, key_fingerprint
+from .types import (
+    CompletionRequest, LLMResponse, Message, ToolCall, ProviderError,
+    SYSTEM_ROLES, call_timeout, parse_tool_call, request_deadline, responses_logprobs,
+)
+
+ANTHROPIC_VERSION = "2023-06-01"
 
 
 @dataclass
 class OpusConfig:
     api_keys: list[str]
     base_url: str
-    endpoint: str = "/responses"
+    endpoint: str = "/messages"  # "/responses" etc. for an OpenAI-style proxy
     model: Optional[str] = None
     temperature: float = 0.3
+    api_format: str = "auto"  # auto | anthropic | openai - auto picks anthropic for a /messages endpoint
+    max_tokens: int = 8192  # required by the Messages API
+
+    @property
+    def anthropic(self) -> bool:
+        if self.api_format == "auto":
+            return self.endpoint.rstrip("/").endswith("/messages")
+        return self.api_format == "anthropic"
 
 
 class OpusAdapter:
+    egress: Optional[EgressPolicy] = None  # the owning agent's policy (LLMRouter.set_egress_policy)
+
     def __init__(self, config: OpusConfig, rotation: RotationManager | None = None):
         if not config.api_keys:
-            raise ValueError("Opus api_keys required")
+            raise ConfigError("Opus api_keys required")
         self.config = config
         self.rotation = rotation or RotationManager()
-        for idx, key in enumerate(config.api_keys):
-            self.rotation.add_slot(RotationSlot(id=f"k{idx}"))
-        self._keys = list(config.api_keys)
+        self._slot_ids = itertools.count()
+        for key in config.api_keys:
+            self.add_key(key)
+
+    def add_key(self, key: str) -> str:
+        """Put a key into rotation (takes effect on the next request); returns its fingerprint."""
+        fingerprint = key_fingerprint(key)
+        self.rotation.add_slot(RotationSlot(id=f"k{next(self._slot_ids)}", secret=key, fingerprint=fingerprint))
+        return fingerprint
 
     def complete(self, request: CompletionRequest) -> LLMResponse:
         payload = self._build_payload(request)
 
+        deadline = request_deadline(request)
         attempt = 0
         while True:
             attempt += 1
+            timeout = call_timeout(deadline, None)
             slot = self.rotation.select_slot()
-            key = self._keys[int(slot.id[1:])]
             try:
-                response = self._send_request(payload, key)
+                response = self._send_request(payload, slot.secret, timeout=timeout)
                 self.rotation.report_success(slot.id)
                 return self._parse_response(response)
             except ProviderError as exc:
-                if exc.code in ("rate_limit", "quota"):
-                    self.rotation.report_rate_limit(slot.id, exc.message)
-                elif exc.code == "auth_error":
-                    self.rotation.report_auth_error(slot.id)
+                self.rotation.report_error(slot.id, exc.code, exc.message)
                 if not exc.retryable or attempt > self.rotation.policy.max_retries:
                     raise
                 self.rotation.backoff(attempt)
 
     def _build_payload(self, request: CompletionRequest) -> dict:
+        if self.config.anthropic:
+            return self._build_messages_payload(request)
         model = request.model or self.config.model
         payload = {
             "model": model,
-            "messages": [{"role": m.role, "content": m.content} for m in request.messages],
+            "messages": [message_payload(m) for m in request.messages],
             "temperature": request.temperature if request.temperature is not None else self.config.temperature,
         }
+        if request.logprobs:
+            payload["logprobs"] = True
+            if request.top_logprobs:
+                payload["top_logprobs"] = request.top_logprobs
         if request.tools:
             payload["tools"] = [
                 {
@@ -72,15 +105,55 @@ class OpusAdapter:
             ]
         return payload
 
-    def _send_request(self, payload: dict, api_key: str) -> dict:
+    def _build_messages_payload(self, request: CompletionRequest) -> dict:
+        """Anthropic Messages body: system blocks on top, tool_use/tool_result content blocks."""
+        model = request.model or self.config.model
+        if not model:
+            raise ConfigError("Opus model required for the Messages API (OpusConfig.model or request.model)")
+        system = [
+            {"type": "text", "text": m.content} for m in request.messages if m.role in SYSTEM_ROLES and m.content
+        ]
+        messages: list[dict] = []
+        for msg in request.messages:
+            if msg.role in SYSTEM_ROLES:
+                continue
+            role, blocks = _content_blocks(msg)
+            if not blocks:
+                continue  # the API rejects empty text blocks
+            if messages and messages[-1]["role"] == role:
+                messages[-1]["content"].extend(blocks)  # the API wants alternating roles
+            else:
+                messages.append({"role": role, "content": blocks})
+
+        payload: dict = {
+            "model": model,
+            "max_tokens": self.config.max_tokens,
+            "messages": messages,
+            "temperature": request.temperature if request.temperature is not None else self.config.temperature,
+        }
+        if system:
+            payload["system"] = system
+        if request.tools:
+            payload["tools"] = [
+                {"name": t.name, "description": t.description, "input_schema": t.parameters}
+                for t in request.tools
+            ]
+        return payload
+
+    def _send_request(self, payload: dict, api_key: str, timeout: Optional[float] = None) -> dict:
         url = f"{self.config.base_url}{self.config.endpoint}"
+        check_egress(url, self.egress, local_base=self.config.base_url)
         data = json.dumps(payload).encode("utf-8")
         req = urlrequest.Request(url, data=data, method="POST")
         req.add_header("Content-Type", "application/json")
-        req.add_header("Authorization", f"Bearer {api_key}")
+        if self.config.anthropic:
+            req.add_header("x-api-key", api_key)
+            req.add_header("anthropic-version", ANTHROPIC_VERSION)
+        else:
+            req.add_header("Authorization", f"Bearer {api_key}")
 
         try:
-            with urlrequest.urlopen(req) as resp:
+            with urlrequest.urlopen(req, timeout=timeout) as resp:
                 body = resp.read().decode("utf-8")
                 return json.loads(body)
         except urlerror.HTTPError as err:
@@ -90,6 +163,8 @@ class OpusAdapter:
                 raise ProviderError("auth_error", body or "auth error", retryable=True)
             if status == 429:
                 raise ProviderError("rate_limit", body or "rate limit", retryable=True)
+            if status == 529 or "overloaded_error" in body:
+                raise ProviderError("server_error", body or "overloaded", retryable=True)
             if status >= 500:
                 raise ProviderError("server_error", body or "server error", retryable=True)
             raise ProviderError("api_error", body or "api error", retryable=False)
@@ -97,6 +172,8 @@ class OpusAdapter:
             raise ProviderError("network_error", str(err), retryable=True)
 
     def _parse_response(self, response: dict) -> LLMResponse:
+        if response.get("type") == "message" or isinstance(response.get("content"), list):
+            return _parse_messages_response(response)
         text = response.get("output_text") or ""
         tool_calls: list[ToolCall] = []
 
@@ -107,20 +184,38 @@ class OpusAdapter:
                     if ctype in ("output_text", "text"):
                         text += content.get("text", "")
                     if ctype in ("tool_call", "function_call"):
-                        args = content.get("arguments", {})
-                        if isinstance(args, str):
-                            try:
-                                args = json.loads(args)
-                            except (json.JSONDecodeError, ValueError):
-                                args = {}
-                        tool_calls.append(
-                            ToolCall(
-                                name=content.get("name", ""),
-                                args=args or {},
-                            )
-                        )
+                        tool_calls.append(parse_tool_call(
+                            content.get("name", ""), content.get("arguments") or {}, content.get("call_id") or content.get("id"),
+                        ))
 
         if not text and "text" in response:
             text = response.get("text") or ""
 
-        return LLMResponse(content=text, tool_calls=tool_calls if tool_calls else None, raw=response)
+        return LLMResponse(
+            content=text, tool_calls=tool_calls if tool_calls else None, raw=response,
+            logprobs=responses_logprobs(response),
+        )
+
+
+def _content_blocks(msg: Message) -> tuple[str, list[dict]]:
+    """Map a Message to an Anthropic (role, content blocks) pair."""
+    if msg.role == "tool":
+        if not msg.tool_call_id:
+            return "user", [{"type": "text", "text": msg.content}] if msg.content else []
+        return "user", [{"type": "tool_result", "tool_use_id": msg.tool_call_id, "content": msg.content}]
+    blocks: list[dict] = [{"type": "text", "text": msg.content}] if msg.content else []
+    if msg.role == "assistant":
+        for call in msg.tool_calls or []:
+            blocks.append({"type": "tool_use", "id": call.id, "name": call.name, "input": call.args})
+    return ("assistant" if msg.role == "assistant" else "user"), blocks
+
+
+def _parse_messages_response(response: dict) -> LLMResponse:
+    text = ""
+    tool_calls: list[ToolCall] = []
+    for block in response.get("content") or []:
+        if block.get("type") == "text":
+            text += block.get("text", "")
+        elif block.get("type") == "tool_use":
+            tool_calls.append(parse_tool_call(block.get("name", ""), block.get("input") or {}, block.get("id")))
+    return LLMResponse(content=text, tool_calls=tool_calls or None, raw=response)
@@ -0,0 +1,67 @@
+"""Model price table and per-run cost estimation."""
+
+from __future__ import annotations
+
+from dataclasses import dataclass, field
+from typing import Optional
+
+from .types import Usage
+
+
+@dataclass(frozen=True)
+class ModelPrice:
+    input_per_mtok: float  # USD per million prompt tokens
+    output_per_mtok: float  # USD per million completion tokens
+
+    def cost(self, usage: Usage) -> float:
+        return (usage.prompt_tokens * self.input_per_mtok + usage.completion_tokens * self.output_per_mtok) / 1_000_000
+
+
+# List prices at the standard tier; register_price() overrides them (discounts, new models)
+MODEL_PRICES: dict[str, ModelPrice] = {
+    "gemini-3-flash-preview": ModelPrice(0.50, 3.00),
+    "gemini-3-pro-preview": ModelPrice(2.00, 12.00),
+    "gpt-5.2-codex": ModelPrice(1.75, 14.00),
+    "gpt-5.2": ModelPrice(1.75, 14.00),
+    "gpt-5.1-codex-max": ModelPrice(1.25, 10.00),
+    "gpt-5.1-codex": ModelPrice(1.25, 10.00),
+    "gpt-5.1": ModelPrice(1.25, 10.00),
+    "gpt-5-codex": ModelPrice(1.25, 10.00),
+    "gpt-5": ModelPrice(1.25, 10.00),
+    "gpt-5.1-codex-mini": ModelPrice(0.25, 2.00),
+    "gpt-5-codex-mini": ModelPrice(0.25, 2.00),
+}
+
+
+def register_price(model: str, price: ModelPrice):
+    MODEL_PRICES[model] = price
+
+
+def get_price(model: Optional[str]) -> Optional[ModelPrice]:
+    return MODEL_PRICES.get(model) if model else None
+
+
+def estimate_cost(model: Optional[str], usage: Optional[Usage]) -> Optional[float]:
+    """USD for one call, or None when the model has no price or no usage was reported."""
+    price = get_price(model)
+    if price is None or usage is None:
+        return None
+    return price.cost(usage)
+
+
+@dataclass
+class RunMeter:
+    """Tokens and estimated cost of one run, added up call by call (each at its own model's price)."""
+
+    usage: Usage = field(default_factory=Usage)
+    cost_usd: float = 0.0  # calls to unpriced models count as 0
+    priced: bool = False  # at least one call had a known price
+
+    def record(self, model: Optional[str], usage: Optional[Usage]) -> None:
+        if usage is None:
+            return
+        self.usage.add(usage)
+        cost = estimate_cost(model, usage)
+        if cost is not None:
+            self.cost_usd += cost
+            self.priced = True
@@ -0,0 +1,122 @@
+"""Router-level retries for transient provider failures, with a shared retry budget."""
+
+from __future__ import annotations
+
+import asyncio
+import random
+import threading
+import time
+from collections import deque
+from dataclasses import dataclass, field, fields
+from typing import Awaitable, Callable, Optional
+
+from ..errors import ConfigError
+from .types import ProviderError
+
+
+@dataclass
+class RetryPolicy:
+    max_attempts: int = 3  # tries per call, the first one included
+    backoff_base: float = 0.5  # seconds before the first retry; doubles after each one
+    backoff_max: float = 8.0
+    jitter: float = 0.5  # up to this fraction of each delay is randomly taken off (0 = fixed delays)
+    retry_on: list[str] = field(default_factory=lambda: ["rate_limit", "server_error", "network_error"])
+    budget: Optional[int] = 30  # retries allowed per budget_window across all calls (None = unlimited)
+    budget_window: float = 60.0  # seconds
+
+    @classmethod
+    def from_spec(cls, spec: str) -> "RetryPolicy":
+        """Parse `key=value` pairs, e.g. "max_attempts=4,backoff_base=1,budget=20"."""
+        names = {f.name for f in fields(cls)}
+        values: dict = {}
+        for part in spec.split(","):
+            if not part.strip():
+                continue
+            key, sep, value = part.partition("=")
+            key = key.strip()
+            if not sep or key not in names or key == "retry_on":
+                raise ConfigError(f"Invalid retry setting: {part.strip()!r}")
+            if key == "budget" and value.strip().lower() == "none":
+                values[key] = None
+            else:
+                values[key] = int(value) if key in ("max_attempts", "budget") else float(value)
+        return cls(**values)
+
+
+class Retrier:
+    """Runs calls under a RetryPolicy. One instance is shared by all calls of a router,
+    so the budget caps retries when a provider is down instead of multiplying load."""
+
+    def __init__(
+        self,
+        policy: RetryPolicy,
+        sleep: Callable[[float], None] = time.sleep,
+        clock: Callable[[], float] = time.monotonic,
+        rng: Optional[random.Random] = None,
+    ):
+        self.policy = policy
+        self._sleep = sleep
+        self._clock = clock
+        self._random = rng or random.Random()
+        self._spent: deque[float] = deque()  # times of recent retries
+        self._lock = threading.Lock()
+        self.retries = 0
+        self.exhausted = 0  # retries refused because the budget was spent
+
+    def delay(self, retry: int) -> float:
+        base = min(self.policy.backoff_max, self.policy.backoff_base * (2 ** (retry - 1)))
+        return base * (1 - self._random.uniform(0, self.policy.jitter)) if self.policy.jitter else base
+
+    def should_retry(self, exc: BaseException, attempt: int, deadline: Optional[float] = None) -> Optional[float]:
+        """Seconds to wait before retrying after `attempt` failed, or None to give up."""
+        if not isinstance(exc, ProviderError) or not exc.retryable or exc.code not in self.policy.retry_on:
+            return None
+        if attempt >= self.policy.max_attempts:
+            return None
+        wait = self.delay(attempt)
+        if deadline is not None and time.time() + wait >= deadline:
+            return None
+        if not self._take_budget():
+            return None
+        return wait
+
+    def call(self, fn: Callable[[], object], deadline: Optional[float] = None):
+        attempt = 0
+        while True:
+            attempt += 1
+            try:
+                return fn()
+            except Exception as exc:
+                wait = self.should_retry(exc, attempt, deadline)
+                if wait is None:
+                    raise
+                self._sleep(wait)
+
+    async def acall(self, fn: Callable[[], Awaitable[object]], deadline: Optional[float] = None):
+        """call() for coroutine functions; waits with asyncio.sleep unless a custom sleep was given."""
+        attempt = 0
+        while True:
+            attempt += 1
+            try:
+                return await fn()
+            except Exception as exc:
+                wait = self.should_retry(exc, attempt, deadline)
+                if wait is None:
+                    raise
+                if self._sleep is time.sleep:
+                    await asyncio.sleep(wait)
+                else:
+                    self._sleep(wait)
+
+    def _take_budget(self) -> bool:
+        with self._lock:
+            if self.policy.budget is not None:
+                now = self._clock()
+                while self._spent and now - self._spent[0] > self.policy.budget_window:
+                    self._spent.popleft()
+                if len(self._spent) >= self.policy.budget:
+                    self.exhausted += 1
+                    return False
+                self._spent.append(now)
+            self.retries += 1
+            return True
@@ -2,10 +2,23 @@
 
 from __future__ import annotations
 
+import hashlib
 import random
+import threading
 import time
 from dataclasses import dataclass, field
-from typing import Optional
+from typing import Any, Callable, Optional
+
+from .types import ProviderError
+
+
+def key_fingerprint(secret: str) -> str:
+    """Short stable id for a key - safe to log and to use when removing it."""
+    return hashlib.sha256(secret.encode("utf-8")).hexdigest()[:12]
+
+
+# Error codes that count against a key; anything else (bad request, ...) is not the key's fault
+FAILURE_CODES = ("server_error", "network_error", "timeout")
 
 
 @dataclass
@@ -14,7 +27,9 @@ class RotationPolicy:
     backoff_base_ms: int = 500
     backoff_max_ms: int = 8000
     jitter: bool = True
-    cooldown_seconds: int = 60
+    cooldown_seconds: int = 60  # first cooldown; doubles with each one until the key succeeds again
+    max_cooldown_seconds: int = 900
+    failure_threshold: int = 3  # consecutive server/network errors that cool a key down
     rotate_on: list[str] = field(default_factory=lambda: ["rate_limit", "quota", "auth_error"])
 
 
@@ -25,48 +40,158 @@ class RotationSlot:
     last_error: Optional[str] = None
     cooldown_until: Optional[float] = None
     weight: int = 1
+    secret: Any = field(default=None, repr=False)  # key/credential the adapter sends for this slot
+    fingerprint: Optional[str] = None
+    requests: int = 0
+    successes: int = 0
+    failures: int = 0  # server/network errors
+    rate_limits: int = 0
+    auth_failures: int = 0
+    consecutive_failures: int = 0  # errors of any kind since the last success
+    cooldowns: int = 0  # cooldowns since the last success (sets the next cooldown's length)
+    last_used: Optional[float] = None
+    last_success: Optional[float] = None
 
 
 class RotationManager:
-    def __init__(self, policy: RotationPolicy | None = None):
+    def __init__(self, policy: RotationPolicy | None = None, clock: Callable[[], float] = time.time):
         self.policy = policy or RotationPolicy()
+        self._clock = clock
         self._slots: dict[str, RotationSlot] = {}
         self._rr_index = 0
+        # Slots can be added/removed while requests are in flight
+        self._lock = threading.Lock()
 
     def add_slot(self, slot: RotationSlot):
-        self._slots[slot.id] = slot
+        with self._lock:
+            self._slots[slot.id] = slot
 
-    def select_slot(self) -> RotationSlot:
-        self._refresh_cooldowns()
-        pool = self._eligible_pool()
-        if not pool:
-            raise RuntimeError("No available slots")
+    def remove_slot(self, slot_id: str) -> bool:
+        """Stop handing out a slot. Requests already holding it finish normally."""
+        with self._lock:
+            return self._slots.pop(slot_id, None) is not None
+
+    def remove_fingerprint(self, fingerprint: str) -> bool:
+        with self._lock:
+            ids = [slot.id for slot in self._slots.values() if slot.fingerprint == fingerprint]
+            for slot_id in ids:
+                del self._slots[slot_id]
+            return bool(ids)
 
-        slot_id = pool[self._rr_index % len(pool)]
-        self._rr_index += 1
-        return self._slots[slot_id]
+    def slots(self) -> list[RotationSlot]:
+        with self._lock:
+            return list(self._slots.values())
+
+    def select_slot(self) -> RotationSlot:
+        with self._lock:
+            self._refresh_cooldowns()
+            pool = self._eligible_pool()
+            if not pool:
+                # Cooling down = wait and retry; every key disabled = nothing left to retry with
+                cooling = self._soonest_cooldown_end() is not None
+                raise ProviderError(
+                    "rate_limit" if cooling else "no_available_keys", self._unavailable_message(), retryable=cooling
+                )
+
+            slot_id = pool[self._rr_index % len(pool)]
+            self._rr_index += 1
+            slot = self._slots[slot_id]
+            slot.requests += 1
+            slot.last_used = self._clock()
+            return slot
 
     def report_success(self, slot_id: str):
-        slot = self._slots[slot_id]
-        slot.state = "healthy"
-        slot.last_error = None
-        slot.cooldown_until = None
+        with self._lock:
+            slot = self._slots.get(slot_id)
+            if slot is None:  # removed while the request was in flight
+                return
+            slot.state = "healthy"
+            slot.last_error = None
+            slot.cooldown_until = None
+            slot.successes += 1
+            slot.consecutive_failures = 0
+            slot.cooldowns = 0
+            slot.last_success = self._clock()
+
+    def report_error(self, slot_id: str, code: str, message: str | None = None):
+        """Record a provider error against the slot it happened on."""
+        if code in ("rate_limit", "quota"):
+            self.report_rate_limit(slot_id, message)
+        elif code == "auth_error":
+            self.report_auth_error(slot_id)
+        elif code in FAILURE_CODES:
+            self.report_failure(slot_id, message or code)
 
     def report_rate_limit(self, slot_id: str, reason: str | None = None):
-        slot = self._slots[slot_id]
-        slot.state = "cooldown"
-        slot.last_error = reason or "rate_limit"
-        slot.cooldown_until = time.time() + self.policy.cooldown_seconds
+        with self._lock:
+            slot = self._slots.get(slot_id)
+            if slot is None:
+                return
+            slot.rate_limits += 1
+            slot.consecutive_failures += 1
+            self._cool_down(slot, reason or "rate_limit")
+
+    def report_failure(self, slot_id: str, reason: str | None = None):
+        """Server/network error: the key cools down after failure_threshold of them in a row."""
+        with self._lock:
+            slot = self._slots.get(slot_id)
+            if slot is None:
+                return
+            slot.failures += 1
+            slot.consecutive_failures += 1
+            slot.last_error = reason or "failure"
+            if slot.state == "healthy" and slot.consecutive_failures >= self.policy.failure_threshold:
+                self._cool_down(slot, slot.last_error)
 
     def report_auth_error(self, slot_id: str):
-        slot = self._slots[slot_id]
-        slot.state = "disabled"
-        slot.last_error = "auth_error"
+        with self._lock:
+            slot = self._slots.get(slot_id)
+            if slot is None:
+                return
+            slot.auth_failures += 1
+            slot.consecutive_failures += 1
+            slot.state = "disabled"  # permanent until the key is replaced
+            slot.last_error = "auth_error"
+            slot.cooldown_until = None
 
     def disable_slot(self, slot_id: str):
         slot = self._slots[slot_id]
         slot.state = "disabled"
 
+    def available_in(self) -> Optional[float]:
+        """Seconds until select_slot() can succeed: 0 now, the soonest cooldown end, or None (no usable slot)."""
+        with self._lock:
+            self._refresh_cooldowns()
+            if self._eligible_pool():
+                return 0.0
+            soonest = self._soonest_cooldown_end()
+            return max(soonest - self._clock(), 0.0) if soonest is not None else None
+
+    def stats(self) -> list[dict[str, Any]]:
+        """Health of every slot (never the secret): state, counters and cooldown left."""
+        with self._lock:
+            self._refresh_cooldowns()
+            now = self._clock()
+            return [
+                {
+                    "fingerprint": slot.fingerprint or key_fingerprint(str(slot.id)),
+                    "state": slot.state,
+                    "requests": slot.requests,
+                    "successes": slot.successes,
+                    "failures": slot.failures,
+                    "rate_limits": slot.rate_limits,
+                    "auth_failures": slot.auth_failures,
+                    "consecutive_failures": slot.consecutive_failures,
+                    "cooldown_remaining": (
+                        round(max(slot.cooldown_until - now, 0.0), 1) if slot.cooldown_until is not None else None
+                    ),
+                    "last_error": slot.last_error,
+                    "last_used": slot.last_used,
+                    "last_success": slot.last_success,
+                }
+                for slot in self._slots.values()
+            ]
+
     def backoff(self, attempt: int):
         base = min(self.policy.backoff_max_ms, self.policy.backoff_base_ms * (2 ** max(attempt - 1, 0)))
         delay_ms = base
@@ -83,8 +208,26 @@ class RotationManager:
             pool.extend([slot.id] * weight)
         return pool
 
+    def _cool_down(self, slot: RotationSlot, reason: str):
+        seconds = min(self.policy.cooldown_seconds * (2 ** slot.cooldowns), self.policy.max_cooldown_seconds)
+        slot.cooldowns += 1
+        if slot.state != "disabled":
+            slot.state = "cooldown"
+        slot.last_error = reason
+        slot.cooldown_until = self._clock() + seconds
+
+    def _soonest_cooldown_end(self) -> Optional[float]:
+        ends = [slot.cooldown_until for slot in self._slots.values() if slot.state == "cooldown" and slot.cooldown_until]
+        return min(ends) if ends else None
+
+    def _unavailable_message(self) -> str:
+        soonest = self._soonest_cooldown_end()
+        if soonest is None:
+            return "No available slots"
+        return f"No available slots (next key available in {max(soonest - self._clock(), 0):.0f}s)"
+
     def _refresh_cooldowns(self):
-        now = time.time()
+        now = self._clock()
         for slot in self._slots.values():
             if slot.state == "cooldown" and slot.cooldown_until is not None:
                 if now >= slot.cooldown_until:
@@ -2,41 +2,374 @@
 
 from __future__ import annotations
 
-from typing import Protocol
+import asyncio
+import concurrent.futures
+import json
+import random
+import threading
+import time
+from collections import deque
+from dataclasses import dataclass, replace
+from typing import Callable, Iterable, Optional, Protocol
 
-from .types import CompletionRequest, LLMResponse, StreamChunk, StreamIterator
+from ..errors import ConfigError
+from .capabilities import check_request
+from .capture import CaptureBuffer
+from .chaos import ChaosAdapter, ChaosConfig
+from .egress import EgressPolicy
+from .retry import Retrier, RetryPolicy
+from .types import (
+    CompletionRequest, LLMResponse, StreamChunk, StreamIterator, ToolCallDelta, accumulate_stream, request_deadline,
+    with_deadline,
+)
 
 
 class ProviderAdapter(Protocol):
+    # complete_stream(request) -> StreamIterator is optional; without it the
+    # router streams the complete() response as one chunk
     def complete(self, request: CompletionRequest) -> LLMResponse:
         ...
 
 
+@dataclass
+class ShadowConfig:
+    provider: str
+    model: Optional[str] = None
+    rate: float = 0.1  # fraction of complete() calls mirrored
+    on_result: Optional[Callable[["ShadowResult"], None]] = None
+
+
+@dataclass
+class ShadowResult:
+    primary_provider: str
+    primary_content: str
+    shadow_provider: str
+    shadow_model: Optional[str]
+    shadow_content: Optional[str]
+    latency_ms: float
+    error: Optional[str] = None
+
+
 class LLMRouter:
     def __init__(self, default_provider: str = "gemini"):
         self.default_provider = default_provider
         self._providers: dict[str, ProviderAdapter] = {}
+        self.shadow: Optional[ShadowConfig] = None
+        self.shadow_results: deque[ShadowResult] = deque(maxlen=200)
+        self._shadow_pool: Optional[concurrent.futures.ThreadPoolExecutor] = None
+        self._shadow_futures: list[concurrent.futures.Future] = []
+        self._shadow_lock = threading.Lock()
+        self._random = random.Random()
+        self.capture: Optional[CaptureBuffer] = None
+        self.retrier: Optional[Retrier] = None
+        self.egress: Optional[EgressPolicy] = None
 
     def register_provider(self, name: str, adapter: ProviderAdapter):
         self._providers[name] = adapter
+        if self.egress is not None:
+            _unwrap(adapter).egress = self.egress
+
+    def providers(self) -> list[str]:
+        return list(self._providers.keys())
+
+    # --- Chaos testing ---
+
+    def enable_chaos(self, config: ChaosConfig, providers: Optional[list[str]] = None) -> dict[str, ChaosAdapter]:
+        """Wrap providers (default: all) with injected latency and retryable failures."""
+        wrapped = {}
+        for name in providers or self.providers():
+            if name not in self._providers:
+                raise ConfigError(f"Provider not registered: {name}")
+            adapter = self._providers[name]
+            if isinstance(adapter, ChaosAdapter):
+                adapter = adapter.inner
+            wrapped[name] = self._providers[name] = ChaosAdapter(adapter, config)
+        return wrapped
+
+    def disable_chaos(self):
+        for name, adapter in self._providers.items():
+            if isinstance(adapter, ChaosAdapter):
+                self._providers[name] = adapter.inner
+
+    # --- Egress ---
+
+    def set_egress_policy(self, policy: Optional[EgressPolicy]):
+        """Check every provider's requests against policy (on top of any process-wide one)."""
+        self.egress = policy
+        for adapter in self._providers.values():
+            _unwrap(adapter).egress = policy
+
+    def base_urls(self) -> list[str]:
+        """Endpoints the registered providers are configured to call."""
+        urls: list[str] = []
+        for adapter in self._providers.values():
+            adapter = _unwrap(adapter)
+            lister = getattr(adapter, "base_urls", None)
+            found = lister() if lister is not None else [getattr(getattr(adapter, "config", None), "base_url", None)]
+            urls.extend(url for url in found if url and url not in urls)
+        return urls
+
+    def list_models(self, provider: str) -> Optional[list[str]]:
+        """Models the provider reports as available; None if its adapter can't list them."""
+        adapter = self._providers.get(provider)
+        if adapter is None:
+            raise ConfigError(f"Provider not registered: {provider}")
+        lister = getattr(adapter, "list_models", None)
+        return lister() if lister is not None else None
+
+    def default_model(self, provider: str) -> Optional[str]:
+        adapter = self._providers.get(provider)
+        return getattr(getattr(adapter, "config", None), "model", None)
+
+    # --- Key management ---
+
+    def _keyed_adapter(self, provider: str):
+        adapter = self._providers.get(provider)
+        if adapter is None:
+            raise ConfigError(f"Provider not registered: {provider}")
+        if not hasattr(adapter, "add_key") or not hasattr(adapter, "rotation"):
+            raise ConfigError(f"Provider {provider} does not use API keys")
+        return adapter
+
+    def add_key(self, provider: str, key: str) -> str:
+        """Add a key to a live provider's rotation; returns its fingerprint."""
+        return self._keyed_adapter(provider).add_key(key)
+
+    def remove_key(self, provider: str, fingerprint: str) -> bool:
+        """Take a key out of rotation. In-flight requests using it are not interrupted."""
+        return self._keyed_adapter(provider).rotation.remove_fingerprint(fingerprint)
+
+    def keys(self, provider: str) -> list[dict]:
+        """Fingerprint and state of every key slot (never the key itself)."""
+        return [
+            {"fingerprint": slot.fingerprint, "state": slot.state, "last_error": slot.last_error}
+            for slot in self._keyed_adapter(provider).rotation.slots()
+            if slot.fingerprint
+        ]
+
+    def key_stats(self, provider: str) -> list[dict]:
+        """Per-key health: request/error counters, cooldown left, last use (never the key itself)."""
+        return [entry for entry in self._keyed_adapter(provider).rotation.stats() if entry["fingerprint"]]
+
+    def available_in(self, provider: str) -> Optional[float]:
+        """Seconds until the provider has a key off cooldown (0 = capacity now).
+
+        None when it is not registered or every key is disabled. Providers without
+        key rotation (local servers) are always available.
+        """
+        adapter = self._providers.get(provider)
+        if adapter is None:
+            return None
+        rotation = getattr(adapter, "rotation", None)
+        return rotation.available_in() if rotation is not None else 0.0
+
+    # --- Debug capture ---
+
+    def enable_capture(self, size: int = 50, redact_fields: Iterable[str] = ()) -> CaptureBuffer:
+        """Keep the last `size` provider exchanges (keys and `redact_fields` masked) for debugging."""
+        self.capture = CaptureBuffer(size=size, redact_fields=redact_fields)
+        return self.capture
+
+    def disable_capture(self):
+        self.capture = None
+
+    # --- Retries ---
+
+    def set_retry_policy(
+        self, policy: Optional[RetryPolicy], sleep: Callable[[float], None] = time.sleep
+    ) -> Optional[Retrier]:
+        """Retry retryable ProviderErrors (after the adapter's own key rotation gave up); None turns it off."""
+        self.retrier = Retrier(policy, sleep=sleep) if policy is not None else None
+        return self.retrier
+
+    def _with_retries(self, request: CompletionRequest, call: Callable[[], object]):
+        retrier = self.retrier
+        if retrier is None:
+            return call()
+        return retrier.call(call, deadline=request_deadline(request))
 
     def complete(self, request: CompletionRequest) -> LLMResponse:
+        request = with_deadline(request)
+        provider = request.provider or self.default_provider
+        if provider not in self._providers:
+            raise ConfigError(f"Provider not registered: {provider}")
+        check_request(request)
+        capture = self.capture
+
+        def attempt() -> LLMResponse:
+            started = time.time()
+            try:
+                response = self._providers[provider].complete(request)
+            except Exception as exc:
+                if capture is not None:
+                    capture.record(provider, request, started, error=exc)
+                raise
+            if capture is not None:
+                capture.record(provider, request, started, response=response)
+            return response
+
+        response = self._with_retries(request, attempt)
+        self._maybe_shadow(request, provider, response)
+        return response
+
+    async def acomplete(self, request: CompletionRequest) -> LLMResponse:
+        """complete() for asyncio callers, with the same retries, capture and shadowing.
+
+        Adapters with their own `acomplete` are awaited; blocking ones run in a
+        worker thread so concurrent calls don't stall the event loop.
+        """
+        request = with_deadline(request)
         provider = request.provider or self.default_provider
+        adapter = self._providers.get(provider)
+        if adapter is None or isinstance(adapter, ChaosAdapter) or not hasattr(adapter, "acomplete"):
+            return await asyncio.to_thread(self.complete, request)
+        check_request(request)
+        capture = self.capture
+
+        async def attempt() -> LLMResponse:
+            started = time.time()
+            try:
+                response = await adapter.acomplete(request)
+            except Exception as exc:
+                if capture is not None:
+                    capture.record(provider, request, started, error=exc)
+                raise
+            if capture is not None:
+                capture.record(provider, request, started, response=response)
+            return response
+
+        retrier = self.retrier
+        if retrier is None:
+            response = await attempt()
+        else:
+            response = await retrier.acall(attempt, deadline=request_deadline(request))
+        self._maybe_shadow(request, provider, response)
+        return response
+
+    # --- Shadow testing ---
+
+    def set_shadow(
+        self,
+        provider: str,
+        model: Optional[str] = None,
+        rate: float = 0.1,
+        on_result: Optional[Callable[[ShadowResult], None]] = None,
+    ):
+        """Mirror a fraction of complete() calls to a candidate provider/model in the background."""
         if provider not in self._providers:
-            raise ValueError(f"Provider not registered: {provider}")
-        return self._providers[provider].complete(request)
+            raise ConfigError(f"Provider not registered: {provider}")
+        self.shadow = ShadowConfig(provider=provider, model=model, rate=rate, on_result=on_result)
+
+    def clear_shadow(self):
+        self.shadow = None
+
+    def flush_shadow(self, timeout: Optional[float] = None):
+        """Wait for in-flight shadow requests."""
+        with self._shadow_lock:
+            futures, self._shadow_futures = self._shadow_futures, []
+        concurrent.futures.wait(futures, timeout=timeout)
+
+    def _maybe_shadow(self, request: CompletionRequest, provider: str, response: LLMResponse):
+        shadow = self.shadow
+        if shadow and shadow.provider in self._providers and self._random.random() < shadow.rate:
+            self._submit_shadow(shadow, request, provider, response)
+
+    def _submit_shadow(self, shadow: ShadowConfig, request: CompletionRequest, provider: str, response: LLMResponse):
+        with self._shadow_lock:
+            if self._shadow_pool is None:
+                self._shadow_pool = concurrent.futures.ThreadPoolExecutor(max_workers=2, thread_name_prefix="shadow")
+            self._shadow_futures = [f for f in self._shadow_futures if not f.done()]
+            self._shadow_futures.append(
+                self._shadow_pool.submit(self._run_shadow, shadow, request, provider, response)
+            )
+
+    def _run_shadow(self, shadow: ShadowConfig, request: CompletionRequest, provider: str, response: LLMResponse):
+        # The shadow call gets a timeout window of its own
+        shadow_request = replace(request, provider=shadow.provider, model=shadow.model, deadline=None)
+        started = time.time()
+        content, error = None, None
+        try:
+            content = self._providers[shadow.provider].complete(shadow_request).content
+        except Exception as exc:
+            error = str(exc)
+        result = ShadowResult(
+            primary_provider=provider,
+            primary_content=response.content,
+            shadow_provider=shadow.provider,
+            shadow_model=shadow.model,
+            shadow_content=content,
+            latency_ms=(time.time() - started) * 1000,
+            error=error,
+        )
+        self.shadow_results.append(result)
+        if shadow.on_result:
+            try:
+                shadow.on_result(result)
+            except Exception:
+                pass
 
     def complete_stream(self, request: CompletionRequest) -> StreamIterator:
+        request = with_deadline(request)
         provider = request.provider or self.default_provider
         if provider not in self._providers:
-            raise ValueError(f"Provider not registered: {provider}")
+            raise ConfigError(f"Provider not registered: {provider}")
+        check_request(request)
         adapter = self._providers[provider]
+        capture = self.capture
         if hasattr(adapter, "complete_stream"):
-            return adapter.complete_stream(request)
+            # Only opening the stream is retried; chunks already passed on can't be taken back
+            stream = self._with_retries(request, lambda: adapter.complete_stream(request))
+            if capture is not None:
+                return self._captured_stream(capture, provider, request, stream)
+            return stream
+
         # Fallback: call complete() and yield a single chunk
-        response = adapter.complete(request)
-        return self._fallback_stream(response)
+        def attempt() -> LLMResponse:
+            started = time.time()
+            try:
+                response = adapter.complete(request)
+            except Exception as exc:
+                if capture is not None:
+                    capture.record(provider, request, started, error=exc)
+                raise
+            if capture is not None:
+                capture.record(provider, request, started, response=response)
+            return response
+
+        return self._fallback_stream(self._with_retries(request, attempt))
+
+    @staticmethod
+    def _captured_stream(
+        capture: CaptureBuffer, provider: str, request: CompletionRequest, stream: StreamIterator
+    ) -> StreamIterator:
+        started = time.time()
+        chunks: list[StreamChunk] = []
+        error: Optional[BaseException] = None
+        try:
+            for chunk in stream:
+                chunks.append(chunk)
+                yield chunk
+        except GeneratorExit:
+            # Caller cancelled the reply; close the provider stream too
+            error = RuntimeError("stream cancelled by caller")
+            if hasattr(stream, "close"):
+                stream.close()
+            raise
+        except Exception as exc:
+            error = exc
+            raise
+        finally:
+            response = accumulate_stream(iter(chunks))
+            capture.record(provider, request, started, response=response, error=error, stream=True)
 
     @staticmethod
     def _fallback_stream(response: LLMResponse) -> StreamIterator:
-        yield StreamChunk(delta=response.content, finish_reason="stop")
+        for index, call in enumerate(response.tool_calls or []):
+            yield StreamChunk(tool_call_delta=ToolCallDelta(
+                index=index, name=call.name, args_delta=json.dumps(call.args), id=call.id,
+            ))
+        yield StreamChunk(delta=response.content, finish_reason=response.finish_reason or "stop", usage=response.usage)
+
+
+def _unwrap(adapter: ProviderAdapter) -> ProviderAdapter:
+    return adapter.inner if isinstance(adapter, ChaosAdapter) else adapter
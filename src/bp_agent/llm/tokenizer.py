@@ -0,0 +1,85 @@
+"""Token counting per model family.
+
+OpenAI-family models use tiktoken when it is installed (exact); everything
+else falls back to a BPE-style approximation tuned per family.
+"""
+
+from __future__ import annotations
+
+import re
+from functools import lru_cache
+from typing import Iterable, Optional
+
+# Roughly the split regex used by OpenAI's BPE encoders, without \p{} classes
+_PRETOKEN = re.compile(
+    r"'(?:s|t|re|ve|m|ll|d)| ?[^\W\d_]+| ?\d{1,3}| ?[^\s\w]+|\s+(?!\S)|\s+",
+    re.UNICODE,
+)
+
+# Average characters per merged token inside a word
+_CHARS_PER_TOKEN = {
+    "openai": 4.0,
+    "gemini": 4.0,
+    "anthropic": 3.5,
+    "default": 4.0,
+}
+
+# Assistant/user framing overhead per message
+_MESSAGE_OVERHEAD = 4
+
+
+def model_family(model: Optional[str]) -> str:
+    name = (model or "").lower()
+    if name.startswith(("gpt", "o1", "o3", "o4", "text-embedding")) or "codex" in name:
+        return "openai"
+    if name.startswith("gemini"):
+        return "gemini"
+    if name.startswith(("claude", "opus")):
+        return "anthropic"
+    return "default"
+
+
+def count_tokens(model: Optional[str], text: str, exact: bool = True) -> int:
+    """Count tokens in text for the given model (exact where an encoder is available)."""
+    if not text:
+        return 0
+    family = model_family(model)
+    if exact and family == "openai":
+        encoder = _tiktoken_encoder(model or "")
+        if encoder is not None:
+            return len(encoder.encode(text, disallowed_special=()))
+    return _approximate(text, _CHARS_PER_TOKEN[family])
+
+
+def count_message_tokens(model: Optional[str], messages: Iterable, exact: bool = True) -> int:
+    """Count tokens for a list of Message-like objects (role + content)."""
+    total = 0
+    for msg in messages:
+        total += _MESSAGE_OVERHEAD + count_tokens(model, getattr(msg, "content", "") or "", exact=exact)
+    return total
+
+
+def _approximate(text: str, chars_per_token: float) -> int:
+    total = 0
+    for piece in _PRETOKEN.findall(text):
+        core = piece.strip()
+        if not core:
+            total += 1
+            continue
+        # CJK and other wide scripts rarely merge: about one token per character
+        wide = sum(1 for ch in core if ord(ch) >= 0x2E80)
+        narrow = len(core) - wide
+        total += wide + (max(1, round(narrow / chars_per_token)) if narrow else 0)
+    return total
+
+
+@lru_cache(maxsize=16)
+def _tiktoken_encoder(model: str):
+    try:
+        import tiktoken
+    except ImportError:
+        return None
+    try:
+        return tiktoken.encoding_for_model(model)
+    except KeyError:
+        return tiktoken.get_encoding("o200k_base")
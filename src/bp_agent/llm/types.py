@@ -2,21 +2,135 @@
 
 from __future__ import annotations
 
-import json
-from dataclasses import dataclass, field
+import math
+import time
+import uuid
+from dataclasses import dataclass, field, replace
 from typing import Any, Iterator, Optional
 
+from ..errors import BaseAgentError
+from .json_repair import parse_tool_args
+
 
 @dataclass
 class Message:
-    role: str
+    role: str  # system | developer | user | assistant | tool
     content: str
+    tool_calls: Optional[list[ToolCall]] = None  # assistant turn that called tools
+    tool_call_id: Optional[str] = None  # tool turn: the call it answers
+    name: Optional[str] = None  # tool turn: tool name
+
+    def to_dict(self) -> dict:
+        data: dict[str, Any] = {"role": self.role, "content": self.content}
+        if self.tool_calls:
+            data["tool_calls"] = [{"id": tc.id, "name": tc.name, "args": tc.args} for tc in self.tool_calls]
+        if self.tool_call_id is not None:
+            data["tool_call_id"] = self.tool_call_id
+        if self.name is not None:
+            data["name"] = self.name
+        return data
+
+    @classmethod
+    def from_dict(cls, data: dict) -> "Message":
+        calls = [ToolCall(name=tc["name"], args=tc.get("args", {}), id=tc.get("id")) for tc in data.get("tool_calls") or []]
+        return cls(
+            role=data["role"],
+            content=data.get("content") or "",
+            tool_calls=calls or None,
+            tool_call_id=data.get("tool_call_id"),
+            name=data.get("name"),
+        )
 
 
 @dataclass
 class ToolCall:
     name: str
     args: dict
+    id: Optional[str] = None  # provider call id, or one assigned by ensure_tool_call_ids
+    raw_args: Optional[str] = None  # original text when the args were not valid JSON
+
+
+def parse_tool_call(name: str, raw: Any, call_id: Optional[str] = None) -> ToolCall:
+    """Build a ToolCall from model-emitted args, repairing almost-JSON where possible."""
+    args, repaired = parse_tool_args(raw)
+    return ToolCall(name=name, args=args, id=call_id, raw_args=raw if repaired is not None else None)
+
+
+def ensure_tool_call_ids(tool_calls: list[ToolCall]) -> list[ToolCall]:
+    """Give calls without a provider id a local one so tool results can reference them."""
+    for call in tool_calls:
+        if not call.id:
+            call.id = f"call_{uuid.uuid4().hex[:12]}"
+    return tool_calls
+
+
+SYSTEM_ROLES = ("system", "developer")
+
+
+def system_text(messages: list[Message], roles: tuple[str, ...] = SYSTEM_ROLES) -> Optional[str]:
+    """All system-level messages joined in order (None if there are none)."""
+    parts = [m.content for m in messages if m.role in roles and m.content]
+    return "\n\n".join(parts) if parts else None
+
+
+def tool_message(call: ToolCall, content: str) -> Message:
+    return Message(role="tool", content=content, tool_call_id=call.id, name=call.name)
+
+
+@dataclass
+class Usage:
+    prompt_tokens: int = 0
+    completion_tokens: int = 0  # includes reasoning/thinking tokens where the provider reports them
+    total_tokens: int = 0
+
+    def add(self, other: Optional["Usage"]) -> None:
+        if other is not None:
+            self.prompt_tokens += other.prompt_tokens
+            self.completion_tokens += other.completion_tokens
+            self.total_tokens += other.total_tokens
+
+    def __add__(self, other: "Usage") -> "Usage":
+        total = Usage(self.prompt_tokens, self.completion_tokens, self.total_tokens)
+        total.add(other)
+        return total
+
+    def to_dict(self) -> dict[str, int]:
+        return {
+            "prompt_tokens": self.prompt_tokens,
+            "completion_tokens": self.completion_tokens,
+            "total_tokens": self.total_tokens,
+        }
+
+
+def _count(*values: Any) -> int:
+    return sum(value for value in values if isinstance(value, int))
+
+
+def usage_from_raw(response: Any) -> Optional[Usage]:
+    """Token counts of a raw body (Gemini usageMetadata, chat/completions, Messages, Responses, Ollama)."""
+    if not isinstance(response, dict):
+        return None
+    meta = response.get("usageMetadata")
+    if isinstance(meta, dict):
+        prompt = _count(meta.get("promptTokenCount"))
+        completion = _count(meta.get("candidatesTokenCount"), meta.get("thoughtsTokenCount"))
+        return Usage(prompt, completion, _count(meta.get("totalTokenCount")) or prompt + completion)
+    usage = response.get("usage")
+    if isinstance(usage, dict):
+        if "prompt_tokens" in usage or "completion_tokens" in usage:  # chat/completions
+            prompt, completion = _count(usage.get("prompt_tokens")), _count(usage.get("completion_tokens"))
+        else:  # Messages / Responses; cached prompt tokens are still prompt tokens
+            prompt = _count(
+                usage.get("input_tokens"),
+                usage.get("cache_creation_input_tokens"),
+                usage.get("cache_read_input_tokens"),
+            )
+            completion = _count(usage.get("output_tokens"))
+        return Usage(prompt, completion, _count(usage.get("total_tokens")) or prompt + completion)
+    if "prompt_eval_count" in response or "eval_count" in response:  # Ollama
+        prompt, completion = _count(response.get("prompt_eval_count")), _count(response.get("eval_count"))
+        return Usage(prompt, completion, prompt + completion)
+    return None
 
 
 @dataclass
@@ -24,6 +138,72 @@ class LLMResponse:
     content: str
     tool_calls: Optional[list[ToolCall]] = None
     raw: Optional[Any] = None
+    logprobs: Optional[list[float]] = None  # per output token, when requested and supported
+    finish_reason: Optional[str] = None  # stop | length | tool_calls | ...; read from raw when not given
+    usage: Optional[Usage] = None  # read from raw when not given; None if the provider sent no counts
+
+    def __post_init__(self):
+        if self.finish_reason is None and isinstance(self.raw, dict):
+            self.finish_reason = finish_reason_from_raw(self.raw)
+        else:
+            self.finish_reason = normalize_finish_reason(self.finish_reason)
+        if self.usage is None:
+            self.usage = usage_from_raw(self.raw)
+
+    @property
+    def confidence(self) -> Optional[float]:
+        return confidence_from_logprobs(self.logprobs)
+
+
+_LENGTH_REASONS = {"length", "max_tokens", "max_output_tokens", "model_length"}
+
+
+def normalize_finish_reason(reason: Optional[str]) -> Optional[str]:
+    """Provider stop reasons in one vocabulary; every "ran out of output tokens" becomes length."""
+    if not reason:
+        return None
+    reason = reason.lower()
+    if reason in _LENGTH_REASONS:
+        return "length"
+    if reason in ("end_turn", "stop_sequence", "completed"):
+        return "stop"
+    if reason == "tool_use":
+        return "tool_calls"
+    return reason
+
+
+def finish_reason_from_raw(response: dict) -> Optional[str]:
+    """Stop reason of a raw body (Gemini, chat/completions, Messages, Responses or Ollama)."""
+    candidates = response.get("candidates")
+    if candidates and isinstance(candidates, list):
+        return normalize_finish_reason(candidates[0].get("finishReason"))  # MAX_TOKENS, STOP, ...
+    choices = response.get("choices")
+    if choices and isinstance(choices, list):
+        return normalize_finish_reason(choices[0].get("finish_reason"))
+    if response.get("stop_reason"):
+        return normalize_finish_reason(response["stop_reason"])
+    if response.get("status") == "incomplete":
+        return normalize_finish_reason((response.get("incomplete_details") or {}).get("reason") or "length")
+    return normalize_finish_reason(response.get("done_reason") or response.get("status"))
+
+
+def responses_logprobs(response: dict) -> Optional[list[float]]:
+    """Token logprobs from a Responses-API style body (output[].content[].logprobs)."""
+    values = [
+        lp["logprob"]
+        for item in response.get("output") or []
+        for content in item.get("content") or []
+        for lp in content.get("logprobs") or []
+        if "logprob" in lp
+    ]
+    return values or None
+
+
+def confidence_from_logprobs(logprobs: Optional[list[float]]) -> Optional[float]:
+    """Geometric-mean token probability in [0, 1] (None without logprobs)."""
+    if not logprobs:
+        return None
+    return math.exp(sum(logprobs) / len(logprobs))
 
 
 @dataclass
@@ -34,6 +214,10 @@ class CompletionRequest:
     model: Optional[str] = None
     provider: Optional[str] = None
     metadata: Optional[dict] = None
+    timeout: Optional[float] = None  # seconds for the whole call, retries included
+    deadline: Optional[float] = None  # absolute (time.time()) end of timeout; fixed once at router entry
+    logprobs: bool = False  # ask for token logprobs where the provider exposes them
+    top_logprobs: Optional[int] = None
 
 
 @dataclass
@@ -41,6 +225,7 @@ class ToolCallDelta:
     index: int = 0
     name: Optional[str] = None
     args_delta: str = ""
+    id: Optional[str] = None
 
 
 @dataclass
@@ -49,6 +234,7 @@ class StreamChunk:
     tool_call_delta: Optional[ToolCallDelta] = None
     finish_reason: Optional[str] = None
     raw: Optional[Any] = None
+    usage: Optional[Usage] = None  # token counts so far; the last chunk that has them wins
 
 
 StreamIterator = Iterator[StreamChunk]
@@ -59,8 +245,15 @@ def accumulate_stream(stream: StreamIterator) -> LLMResponse:
     text_parts: list[str] = []
     # index -> (name, args_json_parts)
     tool_call_acc: dict[int, tuple[str, list[str]]] = {}
+    call_ids: dict[int, str] = {}
+    finish_reason: Optional[str] = None
+    usage: Optional[Usage] = None
 
     for chunk in stream:
+        if chunk.finish_reason:
+            finish_reason = chunk.finish_reason
+        if chunk.usage is not None:
+            usage = chunk.usage
         if chunk.delta:
             text_parts.append(chunk.delta)
         if chunk.tool_call_delta:
@@ -72,26 +265,50 @@ def accumulate_stream(stream: StreamIterator) -> LLMResponse:
                 tool_call_acc[tcd.index] = (tcd.name, entry[1])
             if tcd.args_delta:
                 entry[1].append(tcd.args_delta)
+            if tcd.id:
+                call_ids.setdefault(tcd.index, tcd.id)
 
     tool_calls: list[ToolCall] = []
     for idx in sorted(tool_call_acc):
         name, args_parts = tool_call_acc[idx]
-        args_str = "".join(args_parts)
-        try:
-            args = json.loads(args_str) if args_str else {}
-        except (json.JSONDecodeError, ValueError):
-            args = {}
-        tool_calls.append(ToolCall(name=name, args=args))
+        tool_calls.append(parse_tool_call(name, "".join(args_parts), call_ids.get(idx)))
 
     return LLMResponse(
         content="".join(text_parts),
         tool_calls=tool_calls if tool_calls else None,
+        finish_reason=finish_reason,
+        usage=usage,
     )
 
 
-class ProviderError(Exception):
+class ProviderError(BaseAgentError):
+    """Provider call failed; code is rate_limit, auth_error, server_error, network_error, ..."""
+
     def __init__(self, code: str, message: str, retryable: bool = False):
         super().__init__(message)
         self.code = code
         self.message = message
         self.retryable = retryable
+
+
+def with_deadline(request: CompletionRequest) -> CompletionRequest:
+    """The request with its timeout pinned to an absolute deadline, so every retry shares one window."""
+    if request.timeout is None or request.deadline is not None:
+        return request
+    return replace(request, deadline=time.time() + request.timeout)
+
+
+def request_deadline(request: CompletionRequest) -> Optional[float]:
+    if request.deadline is not None:
+        return request.deadline
+    return time.time() + request.timeout if request.timeout is not None else None
+
+
+def call_timeout(deadline: Optional[float], default: Optional[float]) -> Optional[float]:
+    """HTTP timeout for the next attempt: the time left before the deadline, capped by the default."""
+    if deadline is None:
+        return default
+    remaining = deadline - time.time()
+    if remaining <= 0:
+        raise ProviderError("deadline_exceeded", "request deadline exceeded", retryable=False)
+    return min(remaining, default) if default else remaining
@@ -0,0 +1,83 @@
+"""Content moderation for incoming instructions and outgoing answers."""
+
+from __future__ import annotations
+
+import re
+from dataclasses import dataclass, field
+from typing import Optional, Protocol
+
+from bp_agent.llm import CompletionRequest, Message
+
+MODERATION_POLICIES = ("off", "block", "flag", "annotate")
+
+MODEL_MODERATION_PROMPT = (
+    "You are a content moderation filter. Classify the user message. "
+    "Reply SAFE if it is acceptable, otherwise reply UNSAFE: followed by comma-separated categories "
+    "(e.g. UNSAFE: violence, self_harm)."
+)
+
+
+@dataclass
+class ModerationResult:
+    flagged: bool = False
+    categories: list[str] = field(default_factory=list)
+
+
+class Moderator(Protocol):
+    def check(self, text: str) -> ModerationResult:
+        ...
+
+
+class KeywordModerator:
+    """Flags text containing any configured keyword (whole-word, case-insensitive)."""
+
+    def __init__(self, keywords: dict[str, list[str]] | list[str]):
+        if isinstance(keywords, list):
+            keywords = {"keyword": keywords}
+        self._patterns = {
+            category: re.compile(r"\b(" + "|".join(re.escape(w) for w in words) + r")\b", re.IGNORECASE)
+            for category, words in keywords.items()
+            if words
+        }
+
+    def check(self, text: str) -> ModerationResult:
+        categories = [c for c, pattern in self._patterns.items() if pattern.search(text)]
+        return ModerationResult(flagged=bool(categories), categories=categories)
+
+
+class ModelModerator:
+    """Asks a (safety) model to classify the text."""
+
+    def __init__(self, llm, model: Optional[str] = None, provider: Optional[str] = None):
+        self.llm = llm
+        self.model = model
+        self.provider = provider
+
+    def check(self, text: str) -> ModerationResult:
+        request = CompletionRequest(
+            messages=[
+                Message(role="system", content=MODEL_MODERATION_PROMPT),
+                Message(role="user", content=text),
+            ],
+            temperature=0.0,
+            model=self.model,
+            provider=self.provider,
+        )
+        verdict = self.llm.complete(request).content.strip()
+        if not verdict.upper().startswith("UNSAFE"):
+            return ModerationResult()
+        _, _, rest = verdict.partition(":")
+        categories = [c.strip() for c in rest.split(",") if c.strip()] or ["unsafe"]
+        return ModerationResult(flagged=True, categories=categories)
+
+
+class CombinedModerator:
+    def __init__(self, moderators: list[Moderator]):
+        self.moderators = moderators
+
+    def check(self, text: str) -> ModerationResult:
+        categories: list[str] = []
+        for moderator in self.moderators:
+            result = moderator.check(text)
+            categories.extend(c for c in result.categories if c not in categories)
+        return ModerationResult(flagged=bool(categories), categories=categories)
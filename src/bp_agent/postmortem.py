@@ -0,0 +1,79 @@
+"""Failure post-mortems: a short, human-readable account of why a run failed."""
+
+from __future__ import annotations
+
+import json
+from collections import Counter
+from typing import Any, Optional
+
+from bp_agent.llm import CompletionRequest, Message
+
+POSTMORTEM_PROMPT = (
+    "An AI agent run failed. From the facts below, write a two or three sentence failure summary "
+    "for an operator: what went wrong, how often, and the most likely cause. Name tools and error "
+    "codes; do not speculate beyond the facts."
+)
+
+
+def failure_facts(error: str, trace: Optional[dict[str, Any]]) -> list[str]:
+    """Counted observations from a failed run's trace, most telling first."""
+    facts = [f"run stopped: {error}"]
+    trace = trace or {}
+
+    errors: Counter[tuple[str, str]] = Counter()
+    for result in trace.get("tool_results", []):
+        message = result.get("error")
+        output = result.get("output")
+        if not message and isinstance(output, str) and output.startswith("[error]"):
+            message = output[len("[error]"):].strip()  # builtins report failures in their output
+        if message:
+            errors[(result.get("name", "?"), message.splitlines()[0][:120])] += 1
+    for (tool, message), count in errors.most_common():
+        facts.append(f"tool {tool} failed {_times(count)}: {message}")
+
+    repeats = Counter(call.get("name", "?") for call in trace.get("duplicate_calls", []))
+    for tool, count in repeats.most_common():
+        facts.append(f"tool {tool} was called again with identical arguments {_times(count)}")
+
+    calls = Counter(call.get("name", "?") for call in trace.get("tool_calls", []))
+    if calls:
+        facts.append("tool calls: " + ", ".join(f"{name} x{count}" for name, count in calls.most_common()))
+    for escalation in trace.get("escalations", []):
+        facts.append(f"low confidence ({escalation['confidence']}) escalated {escalation['from']} -> {escalation['to']}")
+    if trace.get("injection_findings"):
+        facts.append(f"prompt injection flagged in {len(trace['injection_findings'])} tool output(s)")
+    return facts
+
+
+def summarize_failure(
+    error: str,
+    trace: Optional[dict[str, Any]],
+    llm=None,
+    model: Optional[str] = None,
+    provider: Optional[str] = None,
+    instruction: Optional[str] = None,
+) -> str:
+    """Post-mortem text; with a model it writes prose, otherwise (or if it fails) the facts joined."""
+    facts = failure_facts(error, trace)
+    fallback = "; ".join(facts)
+    if llm is None or model is None:
+        return fallback
+    body = {"instruction": (instruction or "")[:500], "facts": facts}
+    request = CompletionRequest(
+        messages=[
+            Message(role="system", content=POSTMORTEM_PROMPT),
+            Message(role="user", content=json.dumps(body, ensure_ascii=False)),
+        ],
+        temperature=0.0,
+        model=model,
+        provider=provider,
+    )
+    try:
+        summary = llm.complete(request).content.strip()
+    except Exception:
+        return fallback
+    return summary or fallback
+
+
+def _times(count: int) -> str:
+    return {1: "once", 2: "twice"}.get(count, f"{count} times")
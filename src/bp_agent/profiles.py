@@ -0,0 +1,99 @@
+"""Declarative agent profiles (agents.json / agents.toml)."""
+
+from __future__ import annotations
+
+import dataclasses
+import json
+from dataclasses import dataclass, field
+from pathlib import Path
+from typing import Optional
+
+from bp_agent.agent import Agent, AgentConfig
+from bp_agent.errors import ConfigError
+from bp_agent.routing import ProfileRoute, ProfileRouter
+
+_CONFIG_FIELDS = {f.name for f in dataclasses.fields(AgentConfig)}
+
+
+@dataclass
+class AgentProfile:
+    name: str
+    config: AgentConfig = field(default_factory=AgentConfig)
+    system_prompt: Optional[str] = None
+    toolsets: Optional[list[str]] = None  # None = keep every registered toolset
+    description: str = ""  # what the profile is for (shown to the routing model)
+    keywords: Optional[list[str]] = None  # routing hints; None = defaults for coding/research/data-extraction
+
+    @classmethod
+    def from_dict(cls, data: dict, base_dir: Path | None = None) -> "AgentProfile":
+        data = dict(data)
+        name = data.pop("name", None)
+        if not name:
+            raise ConfigError("Agent profile requires a name")
+
+        system_prompt = data.pop("system_prompt", None)
+        prompt_file = data.pop("system_prompt_file", None)
+        if prompt_file:
+            prompt_path = Path(prompt_file)
+            if base_dir and not prompt_path.is_absolute():
+                prompt_path = base_dir / prompt_path
+            system_prompt = prompt_path.read_text(encoding="utf-8")
+        toolsets = data.pop("toolsets", None)
+        description = data.pop("description", "")
+        keywords = data.pop("keywords", None)
+
+        unknown = set(data) - _CONFIG_FIELDS
+        if unknown:
+            raise ConfigError(f"Agent profile {name}: unknown fields {sorted(unknown)}")
+
+        return cls(
+            name=name,
+            config=AgentConfig(**data),
+            system_prompt=system_prompt,
+            toolsets=toolsets,
+            description=description,
+            keywords=keywords,
+        )
+
+    def build(self) -> Agent:
+        agent = Agent(self.name, config=self.config, system_prompt=self.system_prompt)
+        if self.toolsets is not None:
+            for tool in agent.tools.export():
+                if tool["toolset"] not in self.toolsets:
+                    agent.tools.unregister(tool["name"])
+        return agent
+
+
+def load_profiles(path: str) -> dict[str, AgentProfile]:
+    """Parse an agents file with a top-level `agents` list."""
+    file_path = Path(path)
+    text = file_path.read_text(encoding="utf-8")
+    if file_path.suffix == ".toml":
+        try:
+            import tomllib
+        except ImportError:  # Python < 3.11
+            raise ConfigError("TOML agent profiles require Python 3.11+ (use agents.json)")
+        data = tomllib.loads(text)
+    else:
+        data = json.loads(text)
+
+    profiles: dict[str, AgentProfile] = {}
+    for item in data.get("agents", []):
+        profile = AgentProfile.from_dict(item, base_dir=file_path.parent)
+        if profile.name in profiles:
+            raise ConfigError(f"Duplicate agent profile: {profile.name}")
+        profiles[profile.name] = profile
+    return profiles
+
+
+def build_agents(path: str) -> dict[str, Agent]:
+    """Materialize one Agent per profile."""
+    return {name: profile.build() for name, profile in load_profiles(path).items()}
+
+
+def build_router(path: str, default: Optional[str] = None, classifier_model: Optional[str] = None) -> ProfileRouter:
+    """Agents for every profile behind a ProfileRouter (front door for execute requests)."""
+    profiles = load_profiles(path)
+    routes = [ProfileRoute(name=p.name, description=p.description, keywords=p.keywords or []) for p in profiles.values()]
+    agents = {name: profile.build() for name, profile in profiles.items()}
+    return ProfileRouter(agents, routes=routes, default=default, classifier_model=classifier_model)
@@ -0,0 +1,137 @@
+"""Front-door routing: pick the agent profile best suited to an instruction."""
+
+from __future__ import annotations
+
+import re
+from dataclasses import dataclass, field, replace
+from typing import Any, Optional
+
+from bp_agent.agent import Agent, AgentResult
+from bp_agent.errors import ConfigError
+from bp_agent.llm import CompletionRequest, Message
+
+# Used for profiles with these names when they don't list keywords of their own
+DEFAULT_KEYWORDS: dict[str, list[str]] = {
+    "coding": [
+        "code", "bug", "function", "class", "refactor", "implement", "compile", "test", "script",
+        "python", "rust", "javascript", "repository", "stack trace", "exception", "api", "sql",
+    ],
+    "research": [
+        "research", "find out", "look up", "search", "compare", "sources", "latest", "investigate",
+        "summarize", "summarise", "explain", "overview", "what is", "who",
+    ],
+    "data-extraction": [
+        "extract", "parse", "fields", "json", "csv", "table", "invoice", "receipt", "scrape",
+        "structured", "columns", "entities",
+    ],
+}
+
+ROUTER_PROMPT = (
+    "Pick the agent best suited to the user's task. Agents:\n{profiles}\n"
+    "Answer with the agent name only."
+)
+
+
+@dataclass
+class ProfileRoute:
+    name: str
+    description: str = ""
+    keywords: list[str] = field(default_factory=list)  # words/phrases, matched case-insensitively
+
+
+@dataclass
+class RoutingDecision:
+    profile: str
+    method: str  # keyword | model | default
+    scores: dict[str, int] = field(default_factory=dict)  # keyword hits per profile
+    reason: Optional[str] = None
+
+    def to_dict(self) -> dict[str, Any]:
+        data: dict[str, Any] = {"profile": self.profile, "method": self.method}
+        if self.scores:
+            data["scores"] = dict(self.scores)
+        if self.reason:
+            data["reason"] = self.reason
+        return data
+
+
+class ProfileRouter:
+    """Routes instructions to one of several agents.
+
+    Keyword hits pick the profile; with classifier_model a small model decides
+    instead (keywords are the fallback). No match goes to `default`.
+    """
+
+    def __init__(
+        self,
+        agents: dict[str, Agent],
+        routes: Optional[list[ProfileRoute]] = None,
+        default: Optional[str] = None,
+        classifier_model: Optional[str] = None,
+        llm=None,
+    ):
+        if not agents:
+            raise ConfigError("ProfileRouter needs at least one agent")
+        self.agents = agents
+        given = {route.name: route for route in routes or []}
+        self.routes: list[ProfileRoute] = []
+        for name in agents:
+            route = given.get(name) or ProfileRoute(name=name)
+            self.routes.append(replace(route, keywords=list(route.keywords or DEFAULT_KEYWORDS.get(name, []))))
+        self.default = default or next(iter(agents))
+        if self.default not in agents:
+            raise ConfigError(f"Unknown default profile: {self.default}")
+        self.classifier_model = classifier_model
+        self.llm = llm or agents[self.default].llm
+        self._patterns = {
+            route.name: [re.compile(rf"\b{re.escape(word)}\b", re.IGNORECASE) for word in route.keywords]
+            for route in self.routes
+        }
+
+    def route(self, instruction: str) -> RoutingDecision:
+        scores = {
+            name: sum(len(pattern.findall(instruction)) for pattern in patterns)
+            for name, patterns in self._patterns.items()
+        }
+        scores = {name: score for name, score in scores.items() if score}
+        if self.classifier_model:
+            picked = self._ask_model(instruction)
+            if picked:
+                return RoutingDecision(profile=picked, method="model", scores=scores, reason=self.classifier_model)
+        if scores:
+            best = max(scores, key=lambda name: scores[name])  # ties: first profile wins
+            return RoutingDecision(profile=best, method="keyword", scores=scores)
+        return RoutingDecision(profile=self.default, method="default", reason="no keyword matched")
+
+    def execute(self, instruction: str, **kwargs) -> AgentResult:
+        """Route, then run on the chosen agent; the decision is stored on its task and the result."""
+        decision = self.route(instruction)
+        agent = self.agents[decision.profile]
+        result = agent.execute(instruction, **kwargs)
+        result.routing = decision.to_dict()
+        if agent.tasks and result.task_id:
+            agent.tasks.update(result.task_id, routing=result.routing)
+        return result
+
+    def _ask_model(self, instruction: str) -> Optional[str]:
+        profiles = "\n".join(
+            f"- {route.name}: {route.description or ', '.join(route.keywords[:8]) or 'general tasks'}"
+            for route in self.routes
+        )
+        request = CompletionRequest(
+            messages=[
+                Message(role="system", content=ROUTER_PROMPT.format(profiles=profiles)),
+                Message(role="user", content=instruction),
+            ],
+            temperature=0.0,
+            model=self.classifier_model,
+        )
+        try:
+            answer = self.llm.complete(request).content.strip().lower()
+        except Exception:
+            return None
+        # Longest name first so "data-extraction" is not read as a shorter profile name
+        for name in sorted(self.agents, key=len, reverse=True):
+            if name.lower() in answer:
+                return name
+        return None
@@ -5,8 +5,10 @@ from .runner import TaskRunner
 from .tui import TaskTUI
 from .chat import chat_repl
 from .cron import parse_cron, CronExpr
+from .scheduler import MaintenanceScheduler, MaintenanceJob, prune_queue_job
 
 __all__ = [
     "TaskQueue", "QueuedTask", "TaskRunner", "TaskTUI",
     "chat_repl", "parse_cron", "CronExpr",
+    "MaintenanceScheduler", "MaintenanceJob", "prune_queue_job",
 ]
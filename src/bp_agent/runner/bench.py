@@ -0,0 +1,127 @@
+"""Benchmark mode - provider latency, error rate and throughput."""
+
+from __future__ import annotations
+
+import concurrent.futures
+import math
+import sys
+import time
+from dataclasses import dataclass, field
+from typing import Optional
+
+DEFAULT_PROMPT = "Write one sentence about the ocean."
+
+
+@dataclass
+class BenchStats:
+    provider: str
+    latencies_ms: list[float] = field(default_factory=list)
+    errors: dict[str, int] = field(default_factory=dict)
+    output_chars: int = 0
+    output_tokens: int = 0
+    wall_seconds: float = 0.0
+
+    @property
+    def requests(self) -> int:
+        return len(self.latencies_ms) + sum(self.errors.values())
+
+    @property
+    def error_rate(self) -> float:
+        return sum(self.errors.values()) / self.requests if self.requests else 0.0
+
+    def percentile(self, pct: float) -> float:
+        if not self.latencies_ms:
+            return 0.0
+        ordered = sorted(self.latencies_ms)
+        # nearest-rank
+        idx = min(len(ordered) - 1, max(0, math.ceil(pct / 100 * len(ordered)) - 1))
+        return ordered[idx]
+
+    @property
+    def tokens_per_second(self) -> float:
+        return self.output_tokens / self.wall_seconds if self.wall_seconds else 0.0
+
+    def format(self) -> str:
+        errors = ", ".join(f"{k}={v}" for k, v in sorted(self.errors.items())) or "-"
+        return (
+            f"  {self.provider:10} n={self.requests:<4} p50={self.percentile(50):7.0f}ms "
+            f"p95={self.percentile(95):7.0f}ms err={self.error_rate:5.1%} "
+            f"tok/s={self.tokens_per_second:7.1f} errors: {errors}"
+        )
+
+
+def run_bench(
+    router,
+    providers: list[str],
+    requests: int = 10,
+    concurrency: int = 4,
+    prompt: str = DEFAULT_PROMPT,
+    model: Optional[str] = None,
+) -> list[BenchStats]:
+    """Fire `requests` completions per provider with `concurrency` in flight."""
+    from bp_agent.llm import CompletionRequest, Message, ProviderError, count_tokens
+
+    results: list[BenchStats] = []
+    for provider in providers:
+        stats = BenchStats(provider=provider)
+
+        def one_call():
+            request = CompletionRequest(
+                messages=[Message(role="user", content=prompt)],
+                model=model,
+                provider=provider,
+            )
+            started = time.time()
+            response = router.complete(request)
+            content = response.content or ""
+            return (time.time() - started) * 1000, len(content), count_tokens(model, content)
+
+        started = time.time()
+        with concurrent.futures.ThreadPoolExecutor(max_workers=max(1, concurrency)) as executor:
+            futures = [executor.submit(one_call) for _ in range(requests)]
+            for future in concurrent.futures.as_completed(futures):
+                try:
+                    latency_ms, chars, tokens = future.result()
+                except ProviderError as exc:
+                    stats.errors[exc.code] = stats.errors.get(exc.code, 0) + 1
+                except Exception as exc:
+                    name = type(exc).__name__
+                    stats.errors[name] = stats.errors.get(name, 0) + 1
+                else:
+                    stats.latencies_ms.append(latency_ms)
+                    stats.output_chars += chars
+                    stats.output_tokens += tokens
+        stats.wall_seconds = time.time() - started
+        results.append(stats)
+    return results
+
+
+def main(argv: Optional[list[str]] = None) -> int:
+    import argparse
+
+    parser = argparse.ArgumentParser(prog="bp-bench", description="Benchmark registered LLM providers")
+    parser.add_argument("--provider", "-p", action="append", help="Provider to bench (repeatable, default: all)")
+    parser.add_argument("--model", "-m", default=None, help="Model override (single-provider runs)")
+    parser.add_argument("--requests", "-n", type=int, default=10, help="Requests per provider")
+    parser.add_argument("--concurrency", "-c", type=int, default=4)
+    parser.add_argument("--prompt", default=DEFAULT_PROMPT)
+    args = parser.parse_args(argv)
+
+    from bp_agent.agent import AgentConfig, _build_llm_router
+
+    config = AgentConfig(provider=args.provider[0]) if args.provider else AgentConfig()
+    try:
+        router = _build_llm_router(config)
+    except ValueError as exc:
+        print(f"Error: {exc}", file=sys.stderr)
+        return 1
+
+    providers = args.provider or router.providers()
+    print(f"Benchmarking {', '.join(providers)}: {args.requests} requests, concurrency {args.concurrency}")
+    for stats in run_bench(router, providers, args.requests, args.concurrency, args.prompt, args.model):
+        print(stats.format())
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -2,13 +2,197 @@
 
 from __future__ import annotations
 
+import json
+import re
+import subprocess
 import sys
+from dataclasses import dataclass, field
+from pathlib import Path
+from typing import Callable, Optional
 
+from ..errors import ConfigError
+from .diffs import apply_patch, colorize_diff, extract_diff, parse_patch
+from .term import configure_output, supports_color, wrap_text
 
-def chat_repl(agent) -> None:
+_VAR_PATTERN = re.compile(r"\$(\w+)")
+DEFAULT_REPL_CONFIG = "~/.config/bp-agent/repl.json"
+_HISTORY_REF = re.compile(r"^!(!|-?\d+)")
+
+
+def invoke_tool(agent, line: str) -> str:
+    """Run a tool directly from the REPL: `tool <name> [json-args]`."""
+    from bp_agent.tools import GiveResultSignal
+
+    parts = line.split(maxsplit=1)
+    if not parts:
+        return "Usage: tool <name> [json-args]"
+    name = parts[0]
+    try:
+        args = json.loads(parts[1]) if len(parts) > 1 else {}
+    except json.JSONDecodeError as exc:
+        return f"[error] Invalid JSON args: {exc}"
+    if not isinstance(args, dict):
+        return "[error] Tool args must be a JSON object"
+
+    try:
+        result = agent.tools.execute(name, args)
+    except GiveResultSignal as sig:
+        return f"[result] {sig.result}"
+    if not result.success:
+        return f"[error] {result.error}"
+    return str(result.output)
+
+
+@dataclass
+class ReplState:
+    """Input history and prompt variables for the chat REPL."""
+
+    inputs: list[str] = field(default_factory=list)
+    variables: dict[str, str] = field(default_factory=dict)
+    workspace: str = "."  # /apply target directory
+    last_reply: Optional[str] = None
+    confirm: Callable[[str], bool] = lambda prompt: input(prompt).strip().lower() in ("y", "yes")
+    aliases: dict[str, str] = field(default_factory=dict)  # "/r" -> "/history"
+    macros: dict[str, list[str]] = field(default_factory=dict)  # "/review" -> lines run in order
+    system_prompt: Optional[str] = None  # set by /system, used when the chat (re)starts
+
+    def expand(self, line: str) -> str:
+        """Apply `!!` / `!N` / `!-N` history expansion, aliases, then `$name` substitution."""
+        match = _HISTORY_REF.match(line)
+        if match:
+            ref = match.group(1)
+            if not self.inputs:
+                raise ValueError("history is empty")
+            if ref == "!":
+                idx = len(self.inputs) - 1
+            else:
+                n = int(ref)
+                idx = len(self.inputs) + n if n < 0 else n - 1
+            if not 0 <= idx < len(self.inputs):
+                raise ValueError(f"{match.group(0)}: event not found")
+            line = self.inputs[idx] + line[match.end():]
+        head, _, rest = line.partition(" ")
+        if head in self.aliases:
+            line = f"{self.aliases[head]} {rest}".strip()
+        return self._substitute(line, {})
+
+    def _substitute(self, line: str, extra: dict[str, str]) -> str:
+        def value(m: re.Match) -> str:
+            name = m.group(1)
+            if name in extra:
+                return extra[name]
+            if name in self.variables:
+                return self.variables[name]
+            if name == "git_diff":
+                return self._git_diff()
+            return m.group(0)
+
+        return _VAR_PATTERN.sub(value, line)
+
+    def _git_diff(self) -> str:
+        try:
+            proc = subprocess.run(["git", "diff"], cwd=self.workspace, capture_output=True, text=True, timeout=10)
+        except (OSError, subprocess.SubprocessError):
+            return ""
+        return proc.stdout
+
+    def expand_macro(self, line: str) -> Optional[list[str]]:
+        """Lines a macro expands to ($1..$9 and $args are its arguments), or None."""
+        head, _, rest = line.partition(" ")
+        if head not in self.macros:
+            return None
+        args = rest.split()
+        extra = {"args": rest}
+        extra.update({str(i): arg for i, arg in enumerate(args, 1)})
+        return [self._substitute(step, extra) for step in self.macros[head]]
+
+    def record(self, line: str):
+        self.inputs.append(line)
+
+    def command(self, line: str) -> Optional[str]:
+        """Handle a slash command. Returns its output, or None if the line is not one."""
+        parts = line.split(maxsplit=2)
+        cmd = parts[0].lower() if parts else ""
+        if cmd == "/set":
+            if len(parts) < 3:
+                return "Usage: /set <name> <value>"
+            if not re.fullmatch(r"\w+", parts[1]):
+                return f"[error] Invalid variable name: {parts[1]}"
+            self.variables[parts[1]] = parts[2]
+            return f"${parts[1]} = {parts[2]}"
+        if cmd == "/unset":
+            if len(parts) < 2:
+                return "Usage: /unset <name>"
+            self.variables.pop(parts[1], None)
+            return f"${parts[1]} removed"
+        if cmd == "/vars":
+            return "\n".join(f"  ${k} = {v}" for k, v in sorted(self.variables.items())) or "  (no variables)"
+        if cmd == "/history":
+            return "\n".join(f"  {i:3}  {text}" for i, text in enumerate(self.inputs, 1)) or "  (no history)"
+        if cmd == "/apply":
+            return self._apply(parts[1] if len(parts) > 1 else self.workspace)
+        if cmd == "/doctor":
+            from .preflight import diagnose_keys, format_key_checks
+
+            print("Checking provider keys...")
+            return format_key_checks(diagnose_keys())
+        if cmd == "/aliases":
+            lines = [f"  {k} -> {v}" for k, v in sorted(self.aliases.items())]
+            lines += [f"  {k} -> macro ({len(v)} steps)" for k, v in sorted(self.macros.items())]
+            return "\n".join(lines) or "  (no aliases or macros)"
+        return None
+
+    def _apply(self, workspace: str) -> str:
+        diff = extract_diff(self.last_reply or "")
+        if not diff:
+            return "[error] Last reply has no diff to apply"
+        try:
+            files = [p.new_path if p.new_path != "/dev/null" else p.old_path for p in parse_patch(diff)]
+        except ValueError as exc:
+            return f"[error] {exc}"
+        if not self.confirm(f"Apply patch to {', '.join(files)} in {workspace}? [y/N] "):
+            return "(not applied)"
+        try:
+            written = apply_patch(diff, workspace)
+        except (ValueError, OSError) as exc:
+            return f"[error] Patch failed: {exc}"
+        return f"Applied: {', '.join(written)}"
+
+
+def load_repl_config(path: str) -> dict:
+    """Read aliases/macros from a JSON or TOML file (missing file = empty config)."""
+    file = Path(path).expanduser()
+    if not file.exists():
+        return {}
+    if file.suffix == ".toml":
+        try:
+            import tomllib
+        except ImportError:  # Python < 3.11
+            raise ConfigError("TOML REPL config requires Python 3.11+ (use a .json file)")
+        data = tomllib.loads(file.read_text(encoding="utf-8"))
+    else:
+        data = json.loads(file.read_text(encoding="utf-8"))
+    macros = {}
+    for name, steps in (data.get("macros") or {}).items():
+        macros[name] = [steps] if isinstance(steps, str) else list(steps)
+    return {"aliases": dict(data.get("aliases") or {}), "macros": macros}
+
+
+def _show_patch(state: ReplState):
+    diff = extract_diff(state.last_reply or "")
+    if not diff:
+        return
+    print("\n" + colorize_diff(diff, color=supports_color()))
+    print(f"(patch detected - /apply to write it into {state.workspace})")
+
+
+def chat_repl(agent, workspace: str = ".", config_path: Optional[str] = None) -> None:
     """Run a simple chat REPL with the given agent."""
-    print("bp-agent chat (type 'quit' to exit, 'reset' to clear history)")
+    print("bp-agent chat (type 'quit' to exit, 'reset' to clear history, 'tools' to list tools)")
+    print("  !! / !N repeat input, /set name value + $name variables, /vars, /history, /apply [dir], /save <file>")
+    print("  /continue starts a new session with a summary of this one")
     print("-" * 50)
+    state = ReplState(workspace=workspace, **load_repl_config(config_path or DEFAULT_REPL_CONFIG))
 
     while True:
         try:
@@ -20,37 +204,118 @@ def chat_repl(agent) -> None:
         if not user_input:
             continue
 
-        if user_input.lower() in ("quit", "exit", "q"):
-            break
-
-        if user_input.lower() == "reset":
-            agent.reset_chat()
-            print("(chat history cleared)")
+        try:
+            expanded = state.expand(user_input)
+        except ValueError as exc:
+            print(f"[error] {exc}")
             continue
+        if expanded != user_input:
+            print(f"  {expanded}")
+        state.record(expanded)
 
-        if user_input.lower() == "history":
-            for msg in agent.chat_history:
-                if msg.role == "system":
-                    continue
-                prefix = "you" if msg.role == "user" else "bot"
-                content = msg.content[:80] + "..." if len(msg.content) > 80 else msg.content
-                print(f"  [{prefix}] {content}")
-            continue
+        lines = state.expand_macro(expanded) or [expanded]
+        for line in lines:
+            if len(lines) > 1:
+                print(f"  > {line[:80]}")
+            if not _handle_line(agent, state, line):
+                return
+
+
+def _handle_line(agent, state: ReplState, user_input: str) -> bool:
+    """Run one REPL line. Returns False when the REPL should exit."""
+    if user_input.startswith("/"):
+        output = state.command(user_input)
+        if output is not None:
+            print(output)
+            return True
+
+    if user_input.lower() in ("quit", "exit", "q"):
+        return False
+
+    if user_input.lower().startswith("/system"):
+        state.system_prompt = user_input[len("/system"):].strip() or None
+        agent.reset_chat()
+        print("(system prompt set, chat history cleared)" if state.system_prompt else "(default system prompt restored)")
+        return True
+
+    if user_input.lower().startswith("/save"):
+        path = user_input[len("/save"):].strip()
+        if not path:
+            print("Usage: /save <file.json>")
+            return True
+        from .view import save_transcript
 
         try:
-            if hasattr(agent, "chat_stream"):
-                sys.stdout.write("\nbot> ")
-                sys.stdout.flush()
-                for delta in agent.chat_stream(user_input):
+            count = save_transcript(agent, path)
+        except OSError as exc:
+            print(f"[error] {exc}")
+        else:
+            print(f"Saved {count} turns to {path} (view with: bp-view {path})")
+        return True
+
+    if user_input.lower() == "/continue":
+        try:
+            summary = agent.continue_chat()
+        except Exception as exc:
+            print(f"[error] {exc}")
+            return True
+        print(f"(new session with summary carried over)\n{summary}" if summary else "(nothing to carry over)")
+        return True
+
+    if user_input.lower() == "reset":
+        agent.reset_chat()
+        print("(chat history cleared)")
+        return True
+
+    if user_input.lower().startswith("tool "):
+        print(invoke_tool(agent, user_input[5:].strip()))
+        return True
+
+    if user_input.lower() == "tools":
+        for tool in agent.tools.export():
+            print(f"  [{tool['toolset']}] {tool['name']}: {tool['description']}")
+        return True
+
+    if user_input.lower() == "history":
+        title = getattr(agent, "chat_metadata", {}).get("title")
+        if title:
+            print(f"  # {title}")
+        for msg in agent.chat_history:
+            if msg.role == "system":
+                continue
+            prefix = {"user": "you", "tool": "tool"}.get(msg.role, "bot")
+            content = msg.content[:80] + "..." if len(msg.content) > 80 else msg.content
+            print(f"  [{prefix}] {content}")
+        return True
+
+    try:
+        if hasattr(agent, "chat_stream"):
+            sys.stdout.write("\nbot> ")
+            sys.stdout.flush()
+            stream = agent.chat_stream(user_input, system_prompt=state.system_prompt)
+            reply_parts: list[str] = []
+            try:
+                for delta in stream:
+                    reply_parts.append(delta)
                     sys.stdout.write(delta)
                     sys.stdout.flush()
-                sys.stdout.write("\n")
-                sys.stdout.flush()
-            else:
-                response = agent.chat(user_input)
-                print(f"\nbot> {response}")
-        except Exception as exc:
-            print(f"\n[error] {exc}", file=sys.stderr)
+            except KeyboardInterrupt:
+                # Ctrl+C cancels the reply (and the provider request), not the REPL
+                stream.close()
+                sys.stdout.write(" [cancelled]")
+            sys.stdout.write("\n")
+            sys.stdout.flush()
+            state.last_reply = "".join(reply_parts)
+        else:
+            response = agent.chat(user_input, system_prompt=state.system_prompt)
+            print(f"\nbot> {wrap_text(response, indent='     ').lstrip()}")
+            state.last_reply = response
+        _show_patch(state)
+    except Exception as exc:
+        print(f"\n[error] {exc}", file=sys.stderr)
+        if getattr(exc, "code", None) == "session_budget_exceeded":
+            print("(use /continue to carry a summary into a new session)", file=sys.stderr)
+    return True
 
 
 def main():
@@ -59,19 +324,46 @@ def main():
     parser = argparse.ArgumentParser(description="bp-agent chat")
     parser.add_argument("--provider", "-p", default=None)
     parser.add_argument("--model", "-m", default=None)
+    parser.add_argument("--list-tools", action="store_true", help="Print tool schemas as JSON and exit")
+    parser.add_argument("--profile", default=None, help="Agent profile name from --profiles")
+    parser.add_argument("--profiles", default="agents.json", help="Agent profiles file (json/toml)")
+    parser.add_argument("--workspace", "-w", default=".", help="Directory /apply writes patches into")
+    parser.add_argument("--repl-config", default=None, help=f"Aliases/macros file (default: {DEFAULT_REPL_CONFIG})")
     args = parser.parse_args()
+    configure_output()
 
     from bp_agent.agent import Agent, AgentConfig, CHAT_SYSTEM_PROMPT
 
-    kwargs = {}
-    if args.provider:
-        kwargs["provider"] = args.provider
-    if args.model:
-        kwargs["model"] = args.model
+    if args.profile:
+        from bp_agent.profiles import load_profiles
+
+        profiles = load_profiles(args.profiles)
+        if args.profile not in profiles:
+            print(f"Unknown profile: {args.profile} (available: {', '.join(profiles) or 'none'})", file=sys.stderr)
+            raise SystemExit(1)
+        profile = profiles[args.profile]
+        if args.provider:
+            profile.config.provider = args.provider
+        if args.model:
+            profile.config.model = args.model
+        profile.system_prompt = profile.system_prompt or CHAT_SYSTEM_PROMPT
+        agent = profile.build()
+    else:
+        kwargs = {}
+        if args.provider:
+            kwargs["provider"] = args.provider
+        if args.model:
+            kwargs["model"] = args.model
 
-    config = AgentConfig(enable_task_store=False, **kwargs)
-    agent = Agent("chat", config=config, system_prompt=CHAT_SYSTEM_PROMPT)
-    chat_repl(agent)
+        config = AgentConfig(enable_task_store=False, **kwargs)
+        agent = Agent("chat", config=config, system_prompt=CHAT_SYSTEM_PROMPT)
+    if args.list_tools:
+        import json
+        print(json.dumps(agent.tools.export(), indent=2))
+        return
+    if agent.is_degraded:
+        print(f"Warning: {agent.health()['error']}", file=sys.stderr)
+    chat_repl(agent, workspace=args.workspace, config_path=args.repl_config)
 
 
 if __name__ == "__main__":
@@ -3,14 +3,43 @@
 from __future__ import annotations
 
 import argparse
+import fnmatch
 import os
+import re
 import sys
 import time
 from pathlib import Path
 from typing import Optional
 
+from .export import EXPORT_FORMATS, export_tasks, parse_since
 from .queue import TaskQueue, QueuedTask
 from .runner import TaskRunner
+from .scheduler import MaintenanceScheduler, prune_queue_job
+from .term import configure_output, symbol, terminal_width
+
+
+_ENV_REF = re.compile(r"\$\$|\$\{(\w+)\}")
+
+
+def expand_env(text: str, allowlist: list[str], env: Optional[dict[str, str]] = None) -> str:
+    """Expand `${VAR}` for variables matching an allowlist pattern (`$$` is a literal `$`).
+
+    Raises ValueError for variables that are not allowed or not set, rather than
+    sending an instruction with a hole in it.
+    """
+    env = os.environ if env is None else env
+
+    def sub(match: re.Match) -> str:
+        name = match.group(1)
+        if name is None:
+            return "$"
+        if not any(fnmatch.fnmatchcase(name, pattern) for pattern in allowlist):
+            raise ValueError(f"${{{name}}} is not in the --expand-env allowlist")
+        if name not in env:
+            raise ValueError(f"${{{name}}} is not set")
+        return env[name]
+
+    return _ENV_REF.sub(sub, text)
 
 
 def _format_time(ts: Optional[float]) -> str:
@@ -19,39 +48,56 @@ def _format_time(ts: Optional[float]) -> str:
     return time.strftime("%H:%M:%S", time.localtime(ts))
 
 
-def _format_task_line(task: QueuedTask, width: int = 50) -> str:
+def _format_task_line(task: QueuedTask, width: Optional[int] = None) -> str:
     status_icons = {
         "pending": "○",
         "running": "◐",
         "completed": "●",
         "failed": "✗",
     }
-    icon = status_icons.get(task.status, "?")
+    icon = symbol(status_icons.get(task.status, "?"))
+    width = width or max(20, terminal_width() - 30)
     instr = task.instruction[:width] + "..." if len(task.instruction) > width else task.instruction
     return f"  {icon} [{task.status:9}] {task.id}: {instr}"
 
 
+PAGE_SIZE = 20
+_STATUS_ORDER = {"running": 0, "pending": 1, "completed": 2, "failed": 3}
+
+
+def _sorted_tasks(queue: TaskQueue, status: Optional[str] = None) -> list[QueuedTask]:
+    # Sort: running first, then pending, then completed/failed
+    tasks = [t for t in queue.list_all() if status is None or t.status == status]
+    tasks.sort(key=lambda t: (_STATUS_ORDER.get(t.status, 9), t.seq))
+    return tasks
+
+
+def paginate(tasks: list, page: int, per_page: int = PAGE_SIZE) -> tuple[list, int, int]:
+    """Return (items on page, clamped page index, page count)."""
+    pages = max(1, (len(tasks) + per_page - 1) // per_page)
+    page = min(max(page, 0), pages - 1)
+    return tasks[page * per_page:(page + 1) * per_page], page, pages
+
+
 def _print_queue(queue: TaskQueue, show_all: bool = False):
-    tasks = queue.list_all()
+    tasks = _sorted_tasks(queue)
     if not tasks:
         print("  (empty queue)")
         return
 
-    # Sort: running first, then pending, then completed/failed
-    order = {"running": 0, "pending": 1, "completed": 2, "failed": 3}
-    tasks.sort(key=lambda t: (order.get(t.status, 9), t.created_at))
-
-    for task in tasks:
-        if not show_all and task.status in ("completed", "failed"):
-            continue
+    if not show_all:
+        tasks = [t for t in tasks if t.status not in ("completed", "failed")]
+    for task in tasks[:PAGE_SIZE]:
         print(_format_task_line(task))
+    if len(tasks) > PAGE_SIZE:
+        print(f"  ... {len(tasks) - PAGE_SIZE} more (use 'browse')")
 
     pending = queue.pending_count()
     if pending > 0:
         print(f"\n  {pending} task(s) pending")
 
 
-def _print_task_detail(task: QueuedTask):
+def _print_task_detail(task: QueuedTask, full: bool = False):
     print(f"  ID:          {task.id}")
     print(f"  Status:      {task.status}")
     print(f"  Instruction: {task.instruction}")
@@ -60,18 +106,38 @@ def _print_task_detail(task: QueuedTask):
     print(f"  Completed:   {_format_time(task.completed_at)}")
     if task.output:
         print(f"  Output:")
-        for line in task.output.split("\n")[:10]:
+        lines = task.output.split("\n")
+        for line in lines if full else lines[:10]:
             print(f"    {line}")
-        if task.output.count("\n") > 10:
+        if not full and len(lines) > 10:
             print("    ...")
     if task.error:
         print(f"  Error: {task.error}")
 
 
+def _print_jobs(scheduler: MaintenanceScheduler):
+    jobs = scheduler.status()
+    if not jobs:
+        print("  (no maintenance jobs)")
+        return
+    for job in jobs:
+        status = job["last_status"] or "never run"
+        detail = job["last_error"] or job["last_result"] or ""
+        print(f"  {job['name']:16} every {job['interval']:>6.0f}s  last: {_format_time(job['last_run'])} [{status}] {detail}")
+
+
 class TaskCLI:
-    def __init__(self, queue: TaskQueue, runner: Optional[TaskRunner] = None):
+    def __init__(
+        self,
+        queue: TaskQueue,
+        runner: Optional[TaskRunner] = None,
+        scheduler: Optional[MaintenanceScheduler] = None,
+        env_allowlist: Optional[list[str]] = None,
+    ):
         self.queue = queue
         self.runner = runner
+        self.scheduler = scheduler
+        self.env_allowlist = env_allowlist or []  # empty = no ${VAR} expansion
 
     def run_repl(self):
         print("Task Runner CLI")
@@ -100,6 +166,8 @@ class TaskCLI:
                 self._cmd_list(arg == "-a" or arg == "--all")
             elif cmd == "show":
                 self._cmd_show(arg)
+            elif cmd == "browse":
+                self._cmd_browse(arg or None)
             elif cmd == "run":
                 self._cmd_run()
             elif cmd == "start":
@@ -110,6 +178,8 @@ class TaskCLI:
                 self._cmd_status()
             elif cmd == "clear":
                 self._cmd_clear()
+            elif cmd == "jobs":
+                self._cmd_jobs()
             elif cmd == "help":
                 self._cmd_help()
             else:
@@ -119,6 +189,12 @@ class TaskCLI:
         if not instruction:
             print("Usage: new <instruction>")
             return
+        if self.env_allowlist:
+            try:
+                instruction = expand_env(instruction, self.env_allowlist)
+            except ValueError as exc:
+                print(f"Error: {exc}")
+                return
         task = self.queue.add(instruction)
         print(f"Added: {task.id}")
 
@@ -135,6 +211,34 @@ class TaskCLI:
             return
         _print_task_detail(task)
 
+    def _cmd_browse(self, status: Optional[str] = None):
+        """Interactive paginated task list: n/p to page, number to open, f <status> to filter."""
+        page = 0
+        while True:
+            tasks = _sorted_tasks(self.queue, status)
+            items, page, pages = paginate(tasks, page)
+            print(f"\n  Tasks{f' [{status}]' if status else ''} - page {page + 1}/{pages} ({len(tasks)} total)")
+            for idx, task in enumerate(items, 1):
+                print(f"  {idx:2}." + _format_task_line(task)[1:])
+            try:
+                choice = input("  [n]ext [p]rev [#] open  f <status>  [q]uit > ").strip()
+            except (EOFError, KeyboardInterrupt):
+                return
+            if choice in ("q", ""):
+                return
+            if choice == "n":
+                page += 1
+            elif choice == "p":
+                page -= 1
+            elif choice.startswith("f"):
+                status = choice[1:].strip() or None
+                page = 0
+            elif choice.isdigit() and 1 <= int(choice) <= len(items):
+                print()
+                _print_task_detail(items[int(choice) - 1], full=True)
+            else:
+                print(f"  Unknown choice: {choice}")
+
     def _cmd_run(self):
         if not self.runner:
             print("No runner configured")
@@ -142,6 +246,9 @@ class TaskCLI:
         print("Running next pending task...")
         if self.runner.run_once():
             print("Done")
+        elif self.runner.deferred:
+            wait = min(self.runner.deferred.values())
+            print(f"{len(self.runner.deferred)} task(s) waiting for rate limits (~{wait:.0f}s)")
         else:
             print("No pending tasks")
 
@@ -168,26 +275,40 @@ class TaskCLI:
     def _cmd_status(self):
         pending = self.queue.pending_count()
         running = "yes" if self.runner and self.runner.is_running else "no"
-        current = self.runner.current_task if self.runner else None
+        current = self.runner.current_tasks if self.runner else []
+        deferred = self.runner.deferred if self.runner else {}
         print(f"  Pending tasks: {pending}")
         print(f"  Runner active: {running}")
         if current:
-            print(f"  Current task:  {current}")
+            print(f"  Current task:  {', '.join(current)}")
+        if deferred:
+            print(f"  Rate limited:  {len(deferred)} task(s) deferred")
+        if self.runner:
+            health = self.runner.agent.health()
+            print(f"  Providers:     {', '.join(health['providers']) or '(none)'} [{health['status']}]")
 
     def _cmd_clear(self):
         count = self.queue.clear_completed()
         print(f"Cleared {count} completed/failed tasks")
 
+    def _cmd_jobs(self):
+        if not self.scheduler:
+            print("No maintenance scheduler configured")
+            return
+        _print_jobs(self.scheduler)
+
     def _cmd_help(self):
         print("Commands:")
         print("  new <instruction>  - Add new task")
         print("  list [-a]          - List tasks (-a for all)")
         print("  show <id>          - Show task details")
+        print("  browse [status]    - Page through tasks, open one for full output")
         print("  run                - Run next task (sync)")
         print("  start              - Start background runner")
         print("  stop               - Stop background runner")
         print("  status             - Show runner status")
         print("  clear              - Remove completed/failed tasks")
+        print("  jobs               - Show maintenance job status")
         print("  quit               - Exit")
 
 
@@ -195,43 +316,98 @@ def main(argv: Optional[list[str]] = None) -> int:
     parser = argparse.ArgumentParser(prog="task-runner", description="Task Runner CLI")
     parser.add_argument("--queue", "-q", default=".task_queue.json", help="Queue file path")
     parser.add_argument("--no-agent", action="store_true", help="Run without agent (queue only)")
+    parser.add_argument("--prune-after", type=float, default=None, metavar="HOURS",
+                        help="Periodically remove finished tasks older than HOURS")
+    parser.add_argument("--expand-env", default=os.getenv("BP_EXPAND_ENV", ""), metavar="VARS",
+                        help="Comma-separated variables (globs ok, e.g. BRANCH,CI_*) expanded as ${VAR} in instructions")
 
     subparsers = parser.add_subparsers(dest="command")
     subparsers.add_parser("repl", help="Interactive mode")
 
     add_p = subparsers.add_parser("add", help="Add task")
     add_p.add_argument("instruction", nargs="+")
+    add_p.add_argument("--provider", default=None, help="Run on this provider instead of the agent's")
 
     subparsers.add_parser("list", help="List tasks")
 
     run_p = subparsers.add_parser("run", help="Run tasks")
     run_p.add_argument("--once", action="store_true", help="Run single task")
     run_p.add_argument("--daemon", action="store_true", help="Run in background")
+    run_p.add_argument("--workers", type=int, default=1, help="Tasks run in parallel (daemon)")
+
+    export_p = subparsers.add_parser("export", help="Export tasks as JSONL/CSV")
+    export_p.add_argument("--format", "-f", choices=EXPORT_FORMATS, default="jsonl")
+    export_p.add_argument("--since", default=None, help="Only tasks created at/after (epoch or ISO date)")
+    export_p.add_argument("--output", "-o", default=None, help="Output file (default: stdout)")
+
+    view_p = subparsers.add_parser("view", help="Step through a saved transcript or trace offline")
+    view_p.add_argument("transcript")
+    view_p.add_argument("--all", "-a", action="store_true", help="Print every turn and exit")
 
     args = parser.parse_args(argv)
+    if args.command == "view":
+        from .view import main as view_main
+
+        return view_main([args.transcript] + (["--all"] if args.all else []))
+    configure_output()
 
     queue_path = Path(args.queue).expanduser()
     queue = TaskQueue(storage_path=queue_path)
 
+    if args.command == "export":
+        try:
+            since = parse_since(args.since)
+        except ValueError:
+            print(f"Invalid --since: {args.since}", file=sys.stderr)
+            return 1
+        tasks = queue.list_all()
+        if args.output:
+            with open(args.output, "w", encoding="utf-8", newline="") as handle:
+                count = export_tasks(tasks, handle, args.format, since)
+            print(f"Exported {count} task(s) to {args.output}", file=sys.stderr)
+        else:
+            export_tasks(tasks, sys.stdout, args.format, since)
+        return 0
+
     runner = None
     if not args.no_agent:
         try:
             from bp_agent.agent import Agent, AgentConfig
             config = AgentConfig(enable_task_store=False)  # We use our own queue
             agent = Agent("task-runner", config=config)
-            runner = TaskRunner(agent, queue)
+            if agent.is_degraded:
+                print(f"Warning: {agent.health()['error']}", file=sys.stderr)
+            runner = TaskRunner(
+                agent,
+                queue,
+                workers=getattr(args, "workers", 1),
+                agent_factory=lambda: Agent("task-runner", config=config),
+            )
         except Exception as exc:
             print(f"Warning: Could not create agent: {exc}", file=sys.stderr)
 
+    scheduler = MaintenanceScheduler()
+    if args.prune_after is not None:
+        scheduler.add_job("prune_tasks", prune_queue_job(queue, args.prune_after * 3600), interval=600, run_now=True)
+
+    env_allowlist = [v.strip() for v in args.expand_env.split(",") if v.strip()]
     command = args.command or "repl"
 
     if command == "repl":
-        TaskCLI(queue, runner).run_repl()
+        scheduler.start()
+        TaskCLI(queue, runner, scheduler, env_allowlist).run_repl()
+        scheduler.stop()
         return 0
 
     if command == "add":
         instruction = " ".join(args.instruction)
-        task = queue.add(instruction)
+        if env_allowlist:
+            try:
+                instruction = expand_env(instruction, env_allowlist)
+            except ValueError as exc:
+                print(f"Error: {exc}", file=sys.stderr)
+                return 1
+        task = queue.add(instruction, provider=args.provider)
         print(f"Added: {task.id}")
         return 0
 
@@ -245,12 +421,14 @@ def main(argv: Optional[list[str]] = None) -> int:
             return 1
         if args.daemon:
             runner.start()
+            scheduler.start()
             print("Runner started. Press Ctrl+C to stop.")
             try:
                 while runner.is_running:
                     time.sleep(1)
             except KeyboardInterrupt:
                 runner.stop()
+                scheduler.stop()
         elif args.once:
             runner.run_once()
         else:
@@ -0,0 +1,144 @@
+"""Unified diff detection, coloring and application for the chat REPL."""
+
+from __future__ import annotations
+
+import re
+from dataclasses import dataclass, field
+from pathlib import Path
+from typing import Optional
+
+_FENCE = re.compile(r"```(?:diff|patch)\s*\n(.*?)```", re.DOTALL)
+_HUNK = re.compile(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@")
+
+GREEN, RED, CYAN, BOLD, RESET = "\033[32m", "\033[31m", "\033[36m", "\033[1m", "\033[0m"
+
+
+@dataclass
+class Hunk:
+    old_start: int
+    lines: list[str] = field(default_factory=list)  # with their ' ', '-', '+' prefix
+
+
+@dataclass
+class FilePatch:
+    old_path: str
+    new_path: str
+    hunks: list[Hunk] = field(default_factory=list)
+
+
+def extract_diff(text: str) -> Optional[str]:
+    """Return the unified diff contained in a reply (fenced or bare), or None."""
+    fenced = [block for block in _FENCE.findall(text) if "@@" in block]
+    if fenced:
+        return "\n".join(block.rstrip("\n") for block in fenced) + "\n"
+    lines = text.splitlines()
+    for i in range(len(lines) - 2):
+        if lines[i].startswith("--- ") and lines[i + 1].startswith("+++ ") and lines[i + 2].startswith("@@"):
+            end = len(lines)
+            while end > i and not lines[end - 1][:1] in (" ", "+", "-", "@", "\\"):
+                end -= 1
+            return "\n".join(lines[i:end]) + "\n"
+    return None
+
+
+def colorize_diff(diff: str, color: bool = True) -> str:
+    if not color:
+        return diff.rstrip("\n")
+    out = []
+    for line in diff.splitlines():
+        if line.startswith(("+++ ", "--- ")):
+            out.append(f"{BOLD}{line}{RESET}")
+        elif line.startswith("@@"):
+            out.append(f"{CYAN}{line}{RESET}")
+        elif line.startswith("+"):
+            out.append(f"{GREEN}{line}{RESET}")
+        elif line.startswith("-"):
+            out.append(f"{RED}{line}{RESET}")
+        else:
+            out.append(line)
+    return "\n".join(out)
+
+
+def _strip_prefix(path: str) -> str:
+    path = path.split("\t")[0].strip()
+    if path.startswith(("a/", "b/")):
+        return path[2:]
+    return path
+
+
+def parse_patch(diff: str) -> list[FilePatch]:
+    patches: list[FilePatch] = []
+    lines = diff.splitlines()
+    i = 0
+    while i < len(lines):
+        line = lines[i]
+        if line.startswith("--- ") and i + 1 < len(lines) and lines[i + 1].startswith("+++ "):
+            patches.append(FilePatch(old_path=_strip_prefix(line[4:]), new_path=_strip_prefix(lines[i + 1][4:])))
+            i += 2
+            continue
+        match = _HUNK.match(line)
+        if match and patches:
+            patches[-1].hunks.append(Hunk(old_start=int(match.group(1))))
+        elif patches and patches[-1].hunks and line[:1] in (" ", "+", "-"):
+            patches[-1].hunks[-1].lines.append(line)
+        elif patches and patches[-1].hunks and line == "":
+            patches[-1].hunks[-1].lines.append(" ")  # blank context line with its space trimmed
+        i += 1
+    if not patches:
+        raise ValueError("No file headers (---/+++) in patch")
+    return patches
+
+
+def _resolve(root: Path, rel: str) -> Path:
+    target = (root / rel).resolve()
+    if root.resolve() not in target.parents and target != root.resolve():
+        raise ValueError(f"Patch path escapes workspace: {rel}")
+    return target
+
+
+def _apply_hunks(original: list[str], hunks: list[Hunk], path: str) -> list[str]:
+    result = list(original)
+    offset = 0
+    for hunk in hunks:
+        old = [l[1:] for l in hunk.lines if l[:1] in (" ", "-")]
+        new = [l[1:] for l in hunk.lines if l[:1] in (" ", "+")]
+        start = max(0, hunk.old_start - 1 + offset)
+        if result[start:start + len(old)] != old:
+            # Line numbers from a model are often off: look for the context elsewhere
+            candidates = [i for i in range(len(result) - len(old) + 1) if result[i:i + len(old)] == old]
+            if not candidates:
+                raise ValueError(f"Hunk at line {hunk.old_start} does not match {path}")
+            start = min(candidates, key=lambda i: abs(i - start))
+        result[start:start + len(old)] = new
+        offset += len(new) - len(old)
+    return result
+
+
+def apply_patch(diff: str, root: str | Path) -> list[str]:
+    """Apply a unified diff under root. All files are checked before any is written."""
+    root = Path(root)
+    planned: list[tuple[Path, Optional[str]]] = []
+    for patch in parse_patch(diff):
+        if patch.new_path == "/dev/null":
+            planned.append((_resolve(root, patch.old_path), None))
+            continue
+        target = _resolve(root, patch.new_path)
+        if patch.old_path == "/dev/null":
+            original: list[str] = []
+        else:
+            source = _resolve(root, patch.old_path)
+            if not source.exists():
+                raise ValueError(f"File not found: {patch.old_path}")
+            original = source.read_text(encoding="utf-8").splitlines()
+        updated = _apply_hunks(original, patch.hunks, patch.new_path)
+        planned.append((target, "\n".join(updated) + "\n" if updated else ""))
+
+    written = []
+    for target, content in planned:
+        if content is None:
+            target.unlink(missing_ok=True)
+        else:
+            target.parent.mkdir(parents=True, exist_ok=True)
+            target.write_text(content, encoding="utf-8")
+        written.append(str(target.relative_to(root.resolve())))
+    return written
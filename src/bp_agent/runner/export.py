@@ -0,0 +1,53 @@
+"""Task export - JSONL/CSV dumps for analysis."""
+
+from __future__ import annotations
+
+import csv
+import json
+from datetime import datetime
+from typing import Iterable, Optional, TextIO
+
+EXPORT_FORMATS = ("jsonl", "csv")
+CSV_FIELDS = ["id", "instruction", "status", "output", "error", "created_at", "started_at", "completed_at"]
+
+
+def parse_since(value: Optional[str]) -> Optional[float]:
+    """Accept an epoch timestamp or an ISO date/datetime."""
+    if not value:
+        return None
+    try:
+        return float(value)
+    except ValueError:
+        return datetime.fromisoformat(value).timestamp()
+
+
+def _timestamp(value) -> float:
+    if isinstance(value, (int, float)):
+        return float(value)
+    try:
+        return datetime.fromisoformat(value).timestamp()
+    except (TypeError, ValueError):
+        return 0.0
+
+
+def export_tasks(tasks: Iterable, out: TextIO, fmt: str = "jsonl", since: Optional[float] = None) -> int:
+    """Write tasks (anything with to_dict()) created at/after `since`. Returns the row count."""
+    if fmt not in EXPORT_FORMATS:
+        raise ValueError(f"Unknown export format: {fmt} (use {', '.join(EXPORT_FORMATS)})")
+
+    writer = None
+    if fmt == "csv":
+        writer = csv.DictWriter(out, fieldnames=CSV_FIELDS, extrasaction="ignore")
+        writer.writeheader()
+
+    count = 0
+    for task in tasks:
+        row = task.to_dict()
+        if since is not None and _timestamp(row.get("created_at")) < since:
+            continue
+        if writer:
+            writer.writerow(row)
+        else:
+            out.write(json.dumps(row, ensure_ascii=False) + "\n")
+        count += 1
+    return count
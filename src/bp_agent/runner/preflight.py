@@ -0,0 +1,219 @@
+"""Preflight checks - validate config, providers and tools before rollout."""
+
+from __future__ import annotations
+
+import sys
+import time
+from dataclasses import dataclass, field
+from typing import Callable, Optional
+
+
+@dataclass
+class CheckResult:
+    name: str
+    ok: bool
+    detail: str = ""
+
+
+@dataclass
+class PreflightReport:
+    checks: list[CheckResult] = field(default_factory=list)
+
+    @property
+    def ok(self) -> bool:
+        return all(c.ok for c in self.checks)
+
+    def add(self, name: str, ok: bool, detail: str = ""):
+        self.checks.append(CheckResult(name=name, ok=ok, detail=detail))
+
+    def format(self) -> str:
+        lines = []
+        for check in self.checks:
+            mark = "✓" if check.ok else "✗"
+            lines.append(f"  {mark} {check.name}" + (f": {check.detail}" if check.detail else ""))
+        lines.append("")
+        lines.append("PASS" if self.ok else "FAIL")
+        return "\n".join(lines)
+
+
+@dataclass
+class KeyCheck:
+    provider: str
+    key: str  # masked
+    status: str  # healthy | invalid | rate_limited | error
+    detail: str = ""
+
+
+def mask_key(key: str) -> str:
+    if len(key) <= 10:
+        return "*" * len(key)
+    return f"{key[:4]}…{key[-4:]}"
+
+
+def _probe_key(provider: str, key: str):
+    """Tiny completion through a single-key adapter with retries disabled."""
+    import os
+
+    from bp_agent.llm import (
+        CodexAdapter, CodexConfig, CompletionRequest, GeminiAdapter, GeminiConfig, Message,
+        OpenAIAdapter, OpenAIConfig, OpusAdapter, OpusConfig, RotationManager, RotationPolicy,
+    )
+
+    rotation = RotationManager(RotationPolicy(max_retries=0))
+    if provider == "gemini":
+        adapter = GeminiAdapter(GeminiConfig(api_keys=[key]), rotation)
+    elif provider == "codex":
+        adapter = CodexAdapter(CodexConfig(api_keys=[key]), rotation)
+    elif provider == "openai":
+        base_url = os.getenv("OPENAI_BASE_URL") or OpenAIConfig().base_url
+        adapter = OpenAIAdapter(OpenAIConfig(api_keys=[key], base_url=base_url.rstrip("/")), rotation)
+    else:
+        adapter = OpusAdapter(
+            OpusConfig(api_keys=[key], base_url=os.getenv("OPUS_BASE_URL", ""), endpoint=os.getenv("OPUS_ENDPOINT", "/messages")),
+            rotation,
+        )
+    adapter.complete(CompletionRequest(messages=[Message(role="user", content="Reply with OK.")], temperature=0.0, timeout=30))
+
+
+def configured_keys() -> dict[str, list[str]]:
+    import os
+
+    from bp_agent import agent as agent_module
+
+    try:
+        gemini = agent_module.load_gemini_keys()
+    except ValueError:
+        gemini = []
+    keys = {"gemini": gemini, "codex": agent_module.load_codex_keys(), "openai": agent_module.load_openai_keys()}
+    if os.getenv("OPUS_BASE_URL"):
+        keys["opus"] = agent_module.load_opus_keys()
+    return keys
+
+
+def diagnose_keys(
+    keys: Optional[dict[str, list[str]]] = None,
+    probe: Callable[[str, str], None] = _probe_key,
+) -> list[KeyCheck]:
+    """Check every configured provider key individually."""
+    from bp_agent.llm import ProviderError
+
+    checks: list[KeyCheck] = []
+    for provider, provider_keys in (configured_keys() if keys is None else keys).items():
+        for key in provider_keys:
+            started = time.time()
+            try:
+                probe(provider, key)
+            except ProviderError as exc:
+                if exc.code == "auth_error":
+                    status = "invalid"
+                elif exc.code in ("rate_limit", "quota"):
+                    status = "rate_limited"
+                else:
+                    status = "error"
+                checks.append(KeyCheck(provider, mask_key(key), status, f"{exc.code}: {exc.message[:120]}"))
+            except Exception as exc:
+                checks.append(KeyCheck(provider, mask_key(key), "error", str(exc)[:120]))
+            else:
+                checks.append(KeyCheck(provider, mask_key(key), "healthy", f"{(time.time() - started) * 1000:.0f} ms"))
+    return checks
+
+
+def format_key_checks(checks: list[KeyCheck]) -> str:
+    if not checks:
+        return "  (no provider keys configured)"
+    marks = {"healthy": "✓", "invalid": "✗", "rate_limited": "~", "error": "!"}
+    return "\n".join(
+        f"  {marks.get(c.status, '?')} {c.provider:7} {c.key:12} {c.status:12} {c.detail}" for c in checks
+    )
+
+
+def run_preflight(config, profiles_path: Optional[str] = None, completion: bool = True) -> PreflightReport:
+    """Run all checks for the given AgentConfig."""
+    from bp_agent import agent as agent_module
+    from bp_agent.llm import CompletionRequest, Message
+    from bp_agent.tools import ToolRegistry, register_builtins, load_tool_manifest
+
+    report = PreflightReport()
+
+    try:
+        router = agent_module._build_llm_router(config)
+    except ValueError as exc:
+        report.add("config", False, str(exc))
+        router = None
+    else:
+        report.add("config", True, f"providers: {', '.join(router.providers()) or '(none)'}")
+
+    if router is not None and completion:
+        for provider in router.providers():
+            model = config.model if provider == config.provider else None
+            request = CompletionRequest(
+                messages=[Message(role="user", content="Reply with OK.")],
+                temperature=0.0,
+                model=model,
+                provider=provider,
+            )
+            started = time.time()
+            try:
+                router.complete(request)
+            except Exception as exc:
+                report.add(f"provider:{provider}", False, str(exc)[:200])
+            else:
+                report.add(f"provider:{provider}", True, f"{(time.time() - started) * 1000:.0f} ms")
+
+    try:
+        registry = ToolRegistry()
+        if config.enable_builtin_tools:
+            register_builtins(registry)
+        if config.tools_manifest:
+            load_tool_manifest(registry, config.tools_manifest)
+    except Exception as exc:
+        report.add("tools", False, str(exc))
+    else:
+        report.add("tools", True, f"{registry.count()} registered")
+
+    if profiles_path:
+        try:
+            from bp_agent.profiles import load_profiles
+
+            profiles = load_profiles(profiles_path)
+        except Exception as exc:
+            report.add("profiles", False, str(exc))
+        else:
+            report.add("profiles", True, ", ".join(profiles) or "(none)")
+
+    return report
+
+
+def main(argv: Optional[list[str]] = None) -> int:
+    import argparse
+
+    parser = argparse.ArgumentParser(prog="bp-preflight", description="Validate bp-agent deployment config")
+    parser.add_argument("--provider", "-p", default=None)
+    parser.add_argument("--model", "-m", default=None)
+    parser.add_argument("--tools-manifest", default=None)
+    parser.add_argument("--profiles", default=None, help="Agent profiles file to validate")
+    parser.add_argument("--no-completion", action="store_true", help="Skip live test completions")
+    parser.add_argument("--keys", action="store_true", help="Check every provider key individually")
+    args = parser.parse_args(argv)
+
+    if args.keys:
+        checks = diagnose_keys()
+        print(format_key_checks(checks))
+        return 0 if checks and all(c.status == "healthy" for c in checks) else 1
+
+    from bp_agent.agent import AgentConfig
+
+    kwargs = {}
+    if args.provider:
+        kwargs["provider"] = args.provider
+    if args.model:
+        kwargs["model"] = args.model
+    config = AgentConfig(enable_task_store=False, tools_manifest=args.tools_manifest, **kwargs)
+
+    report = run_preflight(config, profiles_path=args.profiles, completion=not args.no_completion)
+    print(report.format())
+    return 0 if report.ok else 1
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -7,8 +7,9 @@ import time
 from dataclasses import dataclass, field
 from pathlib import Path
 from threading import Lock
-from typing import Optional
+from typing import Callable, Optional
 
+from ..task.backend import assign_seq
 from .cron import parse_cron
 
 
@@ -29,6 +30,8 @@ class QueuedTask:
     # Recurring
     cron: Optional[str] = None  # Cron expression for recurring tasks
     parent_id: Optional[str] = None  # ID of the cron parent that spawned this
+    provider: Optional[str] = None  # run on this provider (None = the agent's)
+    seq: int = 0  # creation order; list order doesn't depend on the wall clock
 
     def to_dict(self) -> dict:
         d = {
@@ -49,6 +52,10 @@ class QueuedTask:
             d["cron"] = self.cron
         if self.parent_id:
             d["parent_id"] = self.parent_id
+        if self.provider:
+            d["provider"] = self.provider
+        if self.seq:
+            d["seq"] = self.seq
         return d
 
     @classmethod
@@ -66,6 +73,8 @@ class QueuedTask:
             requires=data.get("requires", []),
             cron=data.get("cron"),
             parent_id=data.get("parent_id"),
+            provider=data.get("provider"),
+            seq=data.get("seq", 0),
         )
 
     @property
@@ -78,25 +87,67 @@ class QueuedTask:
         return True
 
 
+TaskEventListener = Callable[[dict], None]
+
+
 class TaskQueue:
     def __init__(self, storage_path: Optional[Path] = None):
         self.storage_path = storage_path
         self._tasks: dict[str, QueuedTask] = {}
         self._lock = Lock()
         self._counter = 0
+        self._listeners: list[TaskEventListener] = []
         if storage_path and storage_path.exists():
             self._load()
 
+    def subscribe(self, listener: TaskEventListener) -> Callable[[], None]:
+        """Receive lifecycle events (created, running, step, completed, failed). Returns an unsubscribe function.
+
+        A step event (one model or tool round of a running task) carries the round under "step".
+        """
+        self._listeners.append(listener)
+
+        def unsubscribe():
+            if listener in self._listeners:
+                self._listeners.remove(listener)
+
+        return unsubscribe
+
+    def step(self, task_id: str, detail: dict) -> None:
+        """Report progress of a running task to subscribers (the runner forwards the agent's step events)."""
+        with self._lock:
+            task = self._tasks.get(task_id)
+        if task is not None:
+            self._emit("step", task, detail)
+
+    def _emit(self, event_type: str, task: QueuedTask, step: Optional[dict] = None):
+        # Called outside the lock so listeners may query the queue
+        event = {"type": event_type, "task": task.to_dict(), "ts": time.time()}
+        if step is not None:
+            event["step"] = step
+        for listener in list(self._listeners):
+            try:
+                listener(event)
+            except Exception:
+                pass
+
     def _generate_id(self) -> str:
+        # The counter continues from the stored tasks, so ids stay unique even if the clock goes back
         self._counter += 1
         return f"task_{int(time.time())}_{self._counter:04d}"
 
+    def _new_task(self, **kwargs) -> QueuedTask:
+        task = QueuedTask(id=self._generate_id(), **kwargs)
+        task.seq = self._counter
+        return task
+
     def add(
         self,
         instruction: str,
         run_at: Optional[float] = None,
         requires: Optional[list[str]] = None,
         cron: Optional[str] = None,
+        provider: Optional[str] = None,
     ) -> QueuedTask:
         with self._lock:
             # Validate cron expression early
@@ -113,16 +164,17 @@ class TaskQueue:
             if cron and run_at is None:
                 run_at = parse_cron(cron).next_run()
 
-            task = QueuedTask(
-                id=self._generate_id(),
+            task = self._new_task(
                 instruction=instruction,
                 run_at=run_at,
                 requires=requires or [],
                 cron=cron,
+                provider=provider,
             )
             self._tasks[task.id] = task
             self._save()
-            return task
+        self._emit("created", task)
+        return task
 
     def get(self, task_id: str) -> Optional[QueuedTask]:
         return self._tasks.get(task_id)
@@ -138,6 +190,23 @@ class TaskQueue:
                 return task
             return None
 
+    def claim_next(self, accept: Optional[Callable[[QueuedTask], bool]] = None) -> Optional[QueuedTask]:
+        """Take the first ready task `accept` agrees to and mark it running, in one step,
+        so concurrent workers never get the same task. Skipped tasks stay pending."""
+        with self._lock:
+            claimed = None
+            for task in self._tasks.values():
+                if task.is_ready and self._deps_satisfied(task) and (accept is None or accept(task)):
+                    claimed = task
+                    break
+            if claimed is None:
+                return None
+            claimed.status = "running"
+            claimed.started_at = time.time()
+            self._save()
+        self._emit("running", claimed)
+        return claimed
+
     def _deps_satisfied(self, task: QueuedTask) -> bool:
         """Check if all required tasks are completed."""
         for req_id in task.requires:
@@ -153,6 +222,7 @@ class TaskQueue:
         output: Optional[str] = None,
         error: Optional[str] = None,
     ) -> Optional[QueuedTask]:
+        next_task = None
         with self._lock:
             task = self._tasks.get(task_id)
             if not task:
@@ -165,29 +235,35 @@ class TaskQueue:
                     task.completed_at = time.time()
                     # Auto-schedule next occurrence for cron tasks
                     if status == "completed" and task.cron:
-                        self._schedule_next_cron(task)
+                        next_task = self._schedule_next_cron(task)
             if output is not None:
                 task.output = output
             if error is not None:
                 task.error = error
             self._save()
-            return task
+        if status:
+            self._emit(status, task)
+        if next_task:
+            self._emit("created", next_task)
+        return task
 
-    def _schedule_next_cron(self, task: QueuedTask):
+    def _schedule_next_cron(self, task: QueuedTask) -> QueuedTask:
         """Create next occurrence of a recurring task. Called inside lock."""
         expr = parse_cron(task.cron)
         next_time = expr.next_run()
-        next_task = QueuedTask(
-            id=self._generate_id(),
+        next_task = self._new_task(
             instruction=task.instruction,
             run_at=next_time,
             cron=task.cron,
             parent_id=task.id,
+            provider=task.provider,
         )
         self._tasks[next_task.id] = next_task
+        return next_task
 
     def list_all(self) -> list[QueuedTask]:
-        return list(self._tasks.values())
+        """Oldest first, by seq."""
+        return sorted(self._tasks.values(), key=lambda t: t.seq)
 
     def list_by_status(self, status: str) -> list[QueuedTask]:
         return [t for t in self._tasks.values() if t.status == status]
@@ -210,6 +286,19 @@ class TaskQueue:
             self._save()
             return len(to_remove)
 
+    def prune(self, max_age: float) -> int:
+        """Remove completed/failed tasks that finished more than max_age seconds ago."""
+        cutoff = time.time() - max_age
+        with self._lock:
+            to_remove = [tid for tid, t in self._tasks.items()
+                         if t.status in ("completed", "failed") and not t.cron
+                         and (t.completed_at if t.completed_at is not None else t.created_at) < cutoff]
+            for tid in to_remove:
+                del self._tasks[tid]
+            if to_remove:
+                self._save()
+            return len(to_remove)
+
     def _save(self):
         if not self.storage_path:
             return
@@ -227,3 +316,4 @@ class TaskQueue:
                 self._tasks[task.id] = task
         except Exception:
             pass
+        self._counter = assign_seq(list(self._tasks.values()), lambda t: t.created_at)
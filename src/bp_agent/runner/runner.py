@@ -2,24 +2,51 @@
 
 from __future__ import annotations
 
-import time
-from threading import Event, Thread
-from typing import TYPE_CHECKING, Optional
+from threading import Event, Lock, Thread
+from typing import TYPE_CHECKING, Callable, Optional
 
-from .queue import TaskQueue
+from bp_agent.errors import ConfigError
+
+from .queue import QueuedTask, TaskQueue
 
 if TYPE_CHECKING:
     from bp_agent.agent import Agent
 
+IDLE_WAIT = 1.0  # seconds between polls of an empty (or fully deferred) queue
+
 
 class TaskRunner:
-    def __init__(self, agent: "Agent", queue: TaskQueue):
+    """Runs queued tasks on `workers` threads.
+
+    A task whose provider has every key cooling down after a rate limit is
+    deferred - left pending while tasks for providers with capacity go first -
+    instead of being started only to hit another 429.
+
+    An Agent keeps per-run state (its last trace, ...) and is never shared
+    between workers: `agent` serves the first one, and with workers > 1
+    `agent_factory` builds one Agent for each of the others.
+    """
+
+    def __init__(
+        self,
+        agent: "Agent",
+        queue: TaskQueue,
+        workers: int = 1,
+        agent_factory: Optional[Callable[[], "Agent"]] = None,
+    ):
+        if workers > 1 and agent_factory is None:
+            raise ConfigError("TaskRunner with workers > 1 needs agent_factory (one Agent per worker)")
         self.agent = agent
+        self.agent_factory = agent_factory
         self.queue = queue
+        self.workers = max(1, workers)
+        self._agents: dict[int, "Agent"] = {0: agent}  # worker index -> its agent
         self._running = False
-        self._thread: Optional[Thread] = None
+        self._threads: list[Thread] = []
         self._stop_event = Event()
-        self._current_task_id: Optional[str] = None
+        self._current: dict[int, str] = {}  # worker index -> task id
+        self._deferred: dict[str, float] = {}  # task id -> seconds until its provider has capacity
+        self._lock = Lock()
 
     @property
     def is_running(self) -> bool:
@@ -27,61 +54,113 @@ class TaskRunner:
 
     @property
     def current_task(self) -> Optional[str]:
-        return self._current_task_id
+        tasks = self.current_tasks
+        return tasks[0] if tasks else None
+
+    @property
+    def current_tasks(self) -> list[str]:
+        with self._lock:
+            return [self._current[index] for index in sorted(self._current)]
+
+    @property
+    def deferred(self) -> dict[str, float]:
+        """Tasks skipped on the last pick because their provider was rate limited."""
+        with self._lock:
+            return dict(self._deferred)
 
     def start(self):
         if self._running:
             return
+        for index in range(self.workers):  # build them up front so a failing factory raises here
+            self._agent(index)
         self._running = True
         self._stop_event.clear()
-        self._thread = Thread(target=self._run_loop, daemon=True)
-        self._thread.start()
+        self._threads = [Thread(target=self._run_loop, args=(index,), daemon=True) for index in range(self.workers)]
+        for thread in self._threads:
+            thread.start()
 
     def stop(self):
         self._running = False
         self._stop_event.set()
-        if self._thread:
-            self._thread.join(timeout=5)
-            self._thread = None
+        for thread in self._threads:
+            thread.join(timeout=5)
+        self._threads = []
+
+    def _agent(self, index: int) -> "Agent":
+        with self._lock:
+            if index not in self._agents:
+                self._agents[index] = self.agent_factory()
+            return self._agents[index]
 
-    def _run_loop(self):
+    def _run_loop(self, index: int = 0):
         while self._running and not self._stop_event.is_set():
-            task = self.queue.get_next_pending()
+            task = self._claim(self._agent(index))
             if not task:
-                self._stop_event.wait(timeout=1)
+                self._stop_event.wait(timeout=self._idle_wait())
                 continue
-
-            self._current_task_id = task.id
-            self.queue.update(task.id, status="running")
-
-            try:
-                result = self.agent.execute(task.instruction)
-                if result.success:
-                    self.queue.update(task.id, status="completed", output=result.output)
-                else:
-                    self.queue.update(task.id, status="failed", error=result.output or "Unknown error")
-            except Exception as exc:
-                self.queue.update(task.id, status="failed", error=str(exc))
-
-            self._current_task_id = None
+            self._process(task, index)
 
     def run_once(self) -> bool:
         """Run single task synchronously. Returns True if a task was processed."""
-        task = self.queue.get_next_pending()
+        task = self._claim(self.agent)
         if not task:
             return False
+        self._process(task, 0)
+        return True
 
-        self._current_task_id = task.id
-        self.queue.update(task.id, status="running")
+    def _claim(self, agent: "Agent") -> Optional[QueuedTask]:
+        deferred: dict[str, float] = {}
+        waits: dict[str, Optional[float]] = {}  # asked once per provider per pick
+        available_in = getattr(agent.llm, "available_in", None)
+
+        def has_capacity(task: QueuedTask) -> bool:
+            if available_in is None:
+                return True
+            provider = task.provider or agent.config.provider
+            if provider not in waits:
+                waits[provider] = available_in(provider)
+            wait = waits[provider]
+            if wait:  # None (unknown provider, all keys disabled) runs and fails with the real error
+                deferred[task.id] = wait
+                return False
+            return True
+
+        task = self.queue.claim_next(has_capacity)
+        with self._lock:
+            self._deferred = deferred
+        return task
+
+    def _idle_wait(self) -> float:
+        # Wake up when the first deferred provider comes off cooldown, if that is sooner
+        return min([IDLE_WAIT, *self.deferred.values()])
+
+    def _process(self, task: QueuedTask, index: int):
+        agent = self._agent(index)
+        with self._lock:
+            self._current[index] = task.id
+
+        def on_event(event: dict):
+            if event["type"] == "step":
+                self.queue.step(task.id, {key: value for key, value in event.items() if key != "type"})
 
         try:
-            result = self.agent.execute(task.instruction)
+            if task.provider:
+                from bp_agent.agent import ExecutionOptions
+
+                options = ExecutionOptions(provider=task.provider, on_event=on_event)
+                result = agent.execute_with(task.instruction, options)
+            else:
+                result = agent.execute(task.instruction, on_event=on_event)
             if result.success:
                 self.queue.update(task.id, status="completed", output=result.output)
             else:
-                self.queue.update(task.id, status="failed", error=result.output or "Unknown error")
+                # The partial text stays in output; error says why the run stopped
+                self.queue.update(
+                    task.id, status="failed", output=result.output or None, error=result.error or "Unknown error"
+                )
         except Exception as exc:
             self.queue.update(task.id, status="failed", error=str(exc))
+        finally:
+            with self._lock:
+                self._current.pop(index, None)
 
-        self._current_task_id = None
-        return True
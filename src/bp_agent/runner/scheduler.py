@@ -0,0 +1,116 @@
+"""Maintenance scheduler - periodic housekeeping jobs."""
+
+from __future__ import annotations
+
+import time
+from dataclasses import dataclass
+from threading import Event, Lock, Thread
+from typing import Callable, Optional
+
+from .queue import TaskQueue
+
+
+@dataclass
+class MaintenanceJob:
+    name: str
+    func: Callable[[], object]
+    interval: float  # seconds
+    next_run: float = 0.0
+    last_run: Optional[float] = None
+    last_status: Optional[str] = None  # ok | error
+    last_result: Optional[str] = None
+    last_error: Optional[str] = None
+    runs: int = 0
+
+    def to_dict(self) -> dict:
+        return {
+            "name": self.name,
+            "interval": self.interval,
+            "last_run": self.last_run,
+            "last_status": self.last_status,
+            "last_result": self.last_result,
+            "last_error": self.last_error,
+            "runs": self.runs,
+            "next_run": self.next_run,
+        }
+
+
+class MaintenanceScheduler:
+    def __init__(self, clock: Callable[[], float] = time.time):
+        self._jobs: dict[str, MaintenanceJob] = {}
+        self._lock = Lock()
+        self._clock = clock
+        self._thread: Optional[Thread] = None
+        self._stop_event = Event()
+
+    def add_job(self, name: str, func: Callable[[], object], interval: float, run_now: bool = False):
+        now = self._clock()
+        with self._lock:
+            self._jobs[name] = MaintenanceJob(
+                name=name, func=func, interval=interval, next_run=now if run_now else now + interval
+            )
+
+    def remove_job(self, name: str):
+        with self._lock:
+            self._jobs.pop(name, None)
+
+    def run_pending(self) -> int:
+        """Run every job that is due. Returns the number of jobs run."""
+        now = self._clock()
+        with self._lock:
+            due = [job for job in self._jobs.values() if job.next_run <= now]
+        for job in due:
+            self._run(job)
+        return len(due)
+
+    def run_job(self, name: str):
+        with self._lock:
+            job = self._jobs[name]
+        self._run(job)
+
+    def _run(self, job: MaintenanceJob):
+        started = self._clock()
+        try:
+            result = job.func()
+        except Exception as exc:
+            job.last_status = "error"
+            job.last_error = str(exc)
+            job.last_result = None
+        else:
+            job.last_status = "ok"
+            job.last_error = None
+            job.last_result = None if result is None else str(result)
+        job.last_run = started
+        job.runs += 1
+        job.next_run = started + job.interval
+
+    def status(self) -> list[dict]:
+        with self._lock:
+            return [job.to_dict() for job in self._jobs.values()]
+
+    @property
+    def is_running(self) -> bool:
+        return self._thread is not None
+
+    def start(self, tick: float = 1.0):
+        if self._thread:
+            return
+        self._stop_event.clear()
+        self._thread = Thread(target=self._loop, args=(tick,), daemon=True)
+        self._thread.start()
+
+    def stop(self):
+        self._stop_event.set()
+        if self._thread:
+            self._thread.join(timeout=5)
+            self._thread = None
+
+    def _loop(self, tick: float):
+        while not self._stop_event.is_set():
+            self.run_pending()
+            self._stop_event.wait(timeout=tick)
+
+
+def prune_queue_job(queue: TaskQueue, max_age: float) -> Callable[[], int]:
+    """Job removing finished (non-cron) tasks completed more than max_age seconds ago."""
+    return lambda: queue.prune(max_age)
@@ -0,0 +1,114 @@
+"""Terminal helpers - width, wrapping, color and encoding fallbacks (incl. Windows consoles)."""
+
+from __future__ import annotations
+
+import os
+import shutil
+import sys
+import textwrap
+import unicodedata
+from typing import Optional, TextIO
+
+UNICODE_BOX = {"h": "═", "v": "║", "tl": "╔", "tr": "╗", "bl": "╚", "br": "╝", "ml": "╠", "mr": "╣", "rule": "─"}
+ASCII_BOX = {"h": "=", "v": "|", "tl": "+", "tr": "+", "bl": "+", "br": "+", "ml": "+", "mr": "+", "rule": "-"}
+
+_ASCII_FALLBACKS = {"○": "o", "◐": "~", "●": "*", "✓": "+", "✗": "x", "→": "->", "…": "...", "💭": ">"}
+
+_vt_enabled: Optional[bool] = None
+
+
+def terminal_width(default: int = 80) -> int:
+    return shutil.get_terminal_size((default, 24)).columns or default
+
+
+def enable_vt_mode() -> bool:
+    """Turn on ANSI escape processing; a no-op everywhere but legacy Windows consoles."""
+    global _vt_enabled
+    if _vt_enabled is not None:
+        return _vt_enabled
+    if os.name != "nt":
+        _vt_enabled = True
+        return True
+    try:
+        import ctypes
+
+        kernel32 = ctypes.windll.kernel32
+        handle = kernel32.GetStdHandle(-11)  # STD_OUTPUT_HANDLE
+        mode = ctypes.c_uint32()
+        if not kernel32.GetConsoleMode(handle, ctypes.byref(mode)):
+            _vt_enabled = False
+        else:
+            # ENABLE_VIRTUAL_TERMINAL_PROCESSING
+            _vt_enabled = bool(kernel32.SetConsoleMode(handle, mode.value | 0x0004))
+    except (AttributeError, OSError):
+        _vt_enabled = False
+    return _vt_enabled
+
+
+def supports_color(stream: TextIO = sys.stdout) -> bool:
+    if os.getenv("NO_COLOR") or not getattr(stream, "isatty", lambda: False)():
+        return False
+    return enable_vt_mode()
+
+
+def supports_unicode(stream: TextIO = sys.stdout) -> bool:
+    encoding = getattr(stream, "encoding", None) or "ascii"
+    try:
+        "═║○●✓✗→".encode(encoding)
+    except (UnicodeEncodeError, LookupError):
+        return False
+    return True
+
+
+def box_chars(stream: TextIO = sys.stdout) -> dict[str, str]:
+    return UNICODE_BOX if supports_unicode(stream) else ASCII_BOX
+
+
+def symbol(char: str, stream: TextIO = sys.stdout) -> str:
+    """Status glyph, or its ASCII stand-in when the stream can't encode it."""
+    return char if supports_unicode(stream) else _ASCII_FALLBACKS.get(char, "?")
+
+
+def configure_output(stream: TextIO = sys.stdout) -> None:
+    """Never crash on characters the console codepage can't encode (cp437/cp1252)."""
+    if hasattr(stream, "reconfigure"):
+        try:
+            stream.reconfigure(errors="replace")
+        except (ValueError, OSError):
+            pass
+    enable_vt_mode()
+
+
+def display_width(text: str) -> int:
+    """Columns the text occupies: wide/fullwidth characters count 2, combining marks 0."""
+    width = 0
+    for ch in text:
+        if unicodedata.combining(ch):
+            continue
+        width += 2 if unicodedata.east_asian_width(ch) in ("W", "F") else 1
+    return width
+
+
+def wrap_text(text: str, width: Optional[int] = None, indent: str = "") -> str:
+    """Wrap prose to the terminal width; fenced code blocks and indented lines are left alone."""
+    width = max(20, (width or terminal_width()) - len(indent))
+    out: list[str] = []
+    in_fence = False
+    for line in text.split("\n"):
+        if line.lstrip().startswith("```"):
+            in_fence = not in_fence
+            out.append(indent + line)
+        elif in_fence or not line.strip() or line.startswith(("    ", "\t")) or len(line) <= width:
+            out.append(indent + line)
+        else:
+            lead = line[: len(line) - len(line.lstrip())]
+            # keep list markers hanging: "- item" continues under "item"
+            marker = lead + line.lstrip().split(" ", 1)[0] + " " if line.lstrip()[:2] in ("- ", "* ") else lead
+            out.extend(
+                indent + part
+                for part in textwrap.wrap(
+                    line, width, initial_indent="", subsequent_indent=" " * len(marker),
+                    break_long_words=True, break_on_hyphens=False,
+                )
+            )
+    return "\n".join(out)
@@ -10,6 +10,7 @@ from pathlib import Path
 from typing import Optional, Callable
 
 from .queue import TaskQueue, QueuedTask
+from .term import box_chars, configure_output, display_width, symbol
 
 
 def clear_screen():
@@ -38,6 +39,7 @@ class TaskTUI:
         self.running_output: list[str] = []
 
     def run(self):
+        configure_output()
         clear_screen()
         self._render()
 
@@ -132,7 +134,7 @@ class TaskTUI:
                 self._render()
                 try:
                     result = original_tool_execute(name, args)
-                    status = "✓" if result.success else "✗"
+                    status = symbol("✓" if result.success else "✗")
                     self.running_output.append(f"  {status} {_truncate(str(result.output), 60)}")
                     self._render()
                     return result
@@ -148,7 +150,7 @@ class TaskTUI:
                 self.queue.update(task.id, status="completed", output=result.output)
                 self.status_message = "Task completed"
                 self.running_output.append("")
-                self.running_output.append("─" * 40)
+                self.running_output.append(box_chars()["rule"] * 40)
                 self.running_output.append("OUTPUT:")
                 for line in result.output.split("\n")[:15]:
                     self.running_output.append(f"  {line}")
@@ -196,8 +198,7 @@ class TaskTUI:
         clear_screen()
 
         # Prepare task list (oldest at top, newest at bottom)
-        tasks = self.queue.list_all()
-        tasks.sort(key=lambda t: t.created_at)  # ascending - oldest first
+        tasks = self.queue.list_all()  # ascending - oldest first
         tasks = tasks[-6:]  # keep last 6 (most recent)
         task_lines = max(1, len(tasks))
 
@@ -209,7 +210,8 @@ class TaskTUI:
         }
 
         # Header
-        print(_separator(cols, "╔", "╗"))
+        box = box_chars()
+        print(_separator(cols, box["tl"], box["tr"]))
         title = " TASK RUNNER "
         padding = (cols - 2 - len(title)) // 2
         print(_pad_line(" " * padding + title, cols))
@@ -230,7 +232,7 @@ class TaskTUI:
 
         # Task list (bottom) - newest at bottom
         for task in tasks:
-            icon = status_chars.get(task.status, "?")
+            icon = symbol(status_chars.get(task.status, "?"))
             instr = _truncate(task.instruction, cols - 22)
             content = f" {icon} {task.status:9} | {instr}"
             print(_pad_line(content, cols))
@@ -244,7 +246,7 @@ class TaskTUI:
         status_content = f" {self.status_message}" if self.status_message else ""
         print(_pad_line(status_content, cols))
 
-        print(_separator(cols, "╚", "╝"))
+        print(_separator(cols, box["bl"], box["br"]))
 
         # Input prompt
         sys.stdout.write("> ")
@@ -260,17 +262,19 @@ def _truncate(s: str, maxlen: int) -> str:
 
 def _pad_line(content: str, width: int) -> str:
     """Create a box line with proper padding."""
-    # Strip existing box chars if any
     inner = content
-    # Calculate visible length (approximate - assumes most chars are width 1)
-    visible_len = len(inner)
-    padding = max(0, width - 2 - visible_len)
-    return "║" + inner + " " * padding + "║"
+    # Wide (CJK/emoji) characters take two columns
+    while inner and display_width(inner) > width - 2:
+        inner = inner[:-1]
+    padding = max(0, width - 2 - display_width(inner))
+    edge = box_chars()["v"]
+    return edge + inner + " " * padding + edge
 
 
-def _separator(width: int, left: str = "╠", right: str = "╣") -> str:
+def _separator(width: int, left: Optional[str] = None, right: Optional[str] = None) -> str:
     """Create a separator line."""
-    return left + "═" * (width - 2) + right
+    box = box_chars()
+    return (left or box["ml"]) + box["h"] * (width - 2) + (right or box["mr"])
 
 
 def main():
@@ -288,6 +292,9 @@ def main():
         from bp_agent.agent import Agent, AgentConfig
         config = AgentConfig(enable_task_store=False)
         agent = Agent("task-runner", config=config)
+        if agent.is_degraded:
+            print(f"Warning: {agent.health()['error']}", file=sys.stderr)
+            time.sleep(1)
     except Exception as exc:
         print(f"Warning: Agent not available: {exc}", file=sys.stderr)
         time.sleep(1)
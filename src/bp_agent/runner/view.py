@@ -0,0 +1,124 @@
+"""Offline transcript viewer - step through a saved chat or trace turn by turn."""
+
+from __future__ import annotations
+
+import json
+import sys
+from dataclasses import dataclass
+from typing import Any, Callable, Optional
+
+from .term import configure_output, wrap_text
+
+
+@dataclass
+class Turn:
+    role: str  # user | assistant | system | tool
+    content: str
+    label: str = ""  # tool name, error marker, ...
+
+
+def save_transcript(agent, path: str) -> int:
+    """Write the agent's chat history and metadata as a transcript file. Returns the turn count."""
+    turns = [{"role": m.role, "content": m.content} for m in agent.chat_history]
+    data = {"metadata": dict(getattr(agent, "chat_metadata", {}) or {}), "messages": turns}
+    with open(path, "w", encoding="utf-8") as handle:
+        json.dump(data, handle, indent=2, ensure_ascii=False, default=str)
+    return len(turns)
+
+
+def load_turns(data: dict[str, Any]) -> list[Turn]:
+    """Normalize a saved chat transcript ({"messages": [...]}) or an execution trace into turns."""
+    if "messages" in data:
+        return [Turn(role=m.get("role", "?"), content=m.get("content") or "") for m in data["messages"]]
+
+    from bp_agent.trace_export import _steps
+
+    turns: list[Turn] = []
+    for step in _steps(data):
+        if step["args"] is not None:
+            args = json.dumps(step["args"], ensure_ascii=False, default=str)
+            turns.append(Turn(role="assistant", content=f"{step['name']}({args})", label="call"))
+        result = step["result"]
+        if result is None:
+            turns.append(Turn(role="tool", content="(duplicate call skipped)", label=str(step["name"])))
+        elif result.get("error"):
+            turns.append(Turn(role="tool", content=str(result["error"]), label=f"{step['name']} error"))
+        else:
+            turns.append(Turn(role="tool", content=str(result.get("output")), label=str(step["name"])))
+    return turns
+
+
+def format_turn(turn: Turn, index: int, total: int, width: Optional[int] = None) -> str:
+    label = f" [{turn.label}]" if turn.label else ""
+    return f"--- {index + 1}/{total} {turn.role}{label} ---\n" + wrap_text(turn.content, width, indent="  ")
+
+
+def _header(data: dict[str, Any]) -> str:
+    meta = data.get("metadata") if "messages" in data else data
+    parts = [f"{key}: {meta[key]}" for key in ("title", "provider", "model") if isinstance(meta, dict) and meta.get(key)]
+    return " | ".join(parts)
+
+
+def view(data: dict[str, Any], read: Callable[[str], str] = input) -> None:
+    """Interactive stepper: enter/n next, p prev, <number> jump, a show all, q quit."""
+    turns = load_turns(data)
+    header = _header(data)
+    if header:
+        print(header)
+    if not turns:
+        print("(empty transcript)")
+        return
+    pos = 0
+    while True:
+        print(format_turn(turns[pos], pos, len(turns)))
+        try:
+            choice = read("[n]ext [p]rev [#] jump [a]ll [q]uit > ").strip().lower()
+        except (EOFError, KeyboardInterrupt):
+            return
+        if choice == "q":
+            return
+        if choice in ("", "n"):
+            if pos == len(turns) - 1:
+                print("(end of transcript)")
+                return
+            pos += 1
+        elif choice == "p":
+            pos = max(0, pos - 1)
+        elif choice == "a":
+            for i, turn in enumerate(turns):
+                print(format_turn(turn, i, len(turns)))
+            return
+        elif choice.isdigit() and 1 <= int(choice) <= len(turns):
+            pos = int(choice) - 1
+        else:
+            print(f"Unknown choice: {choice}")
+
+
+def main(argv: Optional[list[str]] = None) -> int:
+    import argparse
+
+    parser = argparse.ArgumentParser(prog="bp-view", description="Step through a saved transcript or trace offline")
+    parser.add_argument("transcript", help="Transcript or trace JSON file")
+    parser.add_argument("--all", "-a", action="store_true", help="Print every turn and exit")
+    args = parser.parse_args(argv)
+    configure_output()
+
+    try:
+        with open(args.transcript, "r", encoding="utf-8") as handle:
+            data = json.load(handle)
+    except (OSError, json.JSONDecodeError) as exc:
+        print(f"Error: {exc}", file=sys.stderr)
+        return 1
+    if not isinstance(data, dict):
+        print("Error: expected a JSON object", file=sys.stderr)
+        return 1
+    if args.all:
+        turns = load_turns(data)
+        print("\n".join([_header(data)] + [format_turn(t, i, len(turns)) for i, t in enumerate(turns)]).lstrip("\n"))
+    else:
+        view(data)
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,128 @@
+"""Watch mode - re-run an instruction whenever watched files change."""
+
+from __future__ import annotations
+
+import sys
+import time
+from pathlib import Path
+from typing import Callable, Iterable, Optional
+
+SKIP_DIRS = {".git", "__pycache__", ".venv", "venv", "node_modules", ".pytest_cache"}
+MAX_FILE_CHARS = 20_000
+
+
+def snapshot(paths: Iterable[str]) -> dict[str, float]:
+    """Map every file under the given paths to its mtime."""
+    mtimes: dict[str, float] = {}
+    for raw in paths:
+        root = Path(raw)
+        if root.is_file():
+            candidates = [root]
+        elif root.is_dir():
+            candidates = [p for p in root.rglob("*") if p.is_file() and not SKIP_DIRS.intersection(p.parts)]
+        else:
+            continue
+        for path in candidates:
+            try:
+                mtimes[str(path)] = path.stat().st_mtime
+            except OSError:
+                continue
+    return mtimes
+
+
+def diff_snapshots(old: dict[str, float], new: dict[str, float]) -> list[str]:
+    """Files added, modified or removed between two snapshots."""
+    changed = [p for p, mtime in new.items() if old.get(p) != mtime]
+    changed.extend(p for p in old if p not in new)
+    return sorted(changed)
+
+
+def build_instruction(instruction: str, changed: list[str], max_chars: int = MAX_FILE_CHARS) -> str:
+    parts = [instruction, "", "Changed files:"]
+    for path in changed:
+        file = Path(path)
+        if not file.exists():
+            parts.append(f"\n--- {path} (deleted)")
+            continue
+        try:
+            content = file.read_text(encoding="utf-8")
+        except (OSError, UnicodeDecodeError):
+            parts.append(f"\n--- {path} (binary or unreadable)")
+            continue
+        if len(content) > max_chars:
+            content = content[:max_chars] + f"\n... ({len(content) - max_chars} more chars)"
+        parts.append(f"\n--- {path}\n{content}")
+    return "\n".join(parts)
+
+
+def watch(
+    run: Callable[[str], str],
+    paths: list[str],
+    instruction: str,
+    interval: float = 1.0,
+    debounce: float = 0.5,
+    max_runs: Optional[int] = None,
+    on_output: Callable[[list[str], str], None] = lambda changed, output: print(output),
+) -> int:
+    """Poll paths and call run(instruction + changed files) on each change. Returns runs made."""
+    previous = snapshot(paths)
+    runs = 0
+    while max_runs is None or runs < max_runs:
+        time.sleep(interval)
+        current = snapshot(paths)
+        changed = diff_snapshots(previous, current)
+        if not changed:
+            continue
+        # Let editors finish writing (save = truncate + write, formatters, etc.)
+        time.sleep(debounce)
+        current = snapshot(paths)
+        changed = diff_snapshots(previous, current)
+        previous = current
+        if not changed:
+            continue
+        on_output(changed, run(build_instruction(instruction, changed)))
+        runs += 1
+    return runs
+
+
+def main(argv: Optional[list[str]] = None) -> int:
+    import argparse
+
+    parser = argparse.ArgumentParser(prog="bp-watch", description="Re-run an instruction when files change")
+    parser.add_argument("instruction", help="Instruction sent on every change")
+    parser.add_argument("--files", "-f", action="append", required=True, help="File or directory to watch (repeatable)")
+    parser.add_argument("--interval", type=float, default=1.0, help="Poll interval in seconds")
+    parser.add_argument("--provider", "-p", default=None)
+    parser.add_argument("--model", "-m", default=None)
+    args = parser.parse_args(argv)
+
+    from bp_agent.agent import Agent, AgentConfig
+
+    kwargs = {}
+    if args.provider:
+        kwargs["provider"] = args.provider
+    if args.model:
+        kwargs["model"] = args.model
+    agent = Agent("watch", config=AgentConfig(enable_task_store=False, **kwargs))
+    if agent.is_degraded:
+        print(f"Error: {agent.health()['error']}", file=sys.stderr)
+        return 1
+
+    def run(text: str) -> str:
+        result = agent.execute(text)
+        return result.output if result.success else f"[error] {result.error or result.output}"
+
+    def show(changed: list[str], output: str):
+        print(f"\n=== {time.strftime('%H:%M:%S')} changed: {', '.join(changed)}")
+        print(output)
+
+    print(f"Watching {', '.join(args.files)} (Ctrl+C to stop)")
+    try:
+        watch(run, args.files, args.instruction, interval=args.interval, on_output=show)
+    except KeyboardInterrupt:
+        pass
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
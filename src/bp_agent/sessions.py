@@ -0,0 +1,110 @@
+"""In-memory chat sessions with idle expiry, for serving many conversations from one agent."""
+
+from __future__ import annotations
+
+import threading
+import time
+from dataclasses import dataclass, field
+from typing import Any, Callable, Optional
+
+from bp_agent.errors import NotFoundError
+from bp_agent.llm.types import Message
+from bp_agent.task import generate_task_id
+
+
+class SessionNotFoundError(NotFoundError):
+    pass
+
+
+@dataclass
+class Session:
+    id: str
+    messages: list[Message] = field(default_factory=list)
+    metadata: dict[str, Any] = field(default_factory=dict)  # chat_metadata of the session
+    created_at: float = 0.0
+    updated_at: float = 0.0
+    system_prompt: Optional[str] = None
+
+    def to_dict(self) -> dict:
+        return {
+            "id": self.id,
+            "messages": [m.to_dict() for m in self.messages],
+            "metadata": self.metadata,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+            "system_prompt": self.system_prompt,
+        }
+
+
+class SessionStore:
+    def __init__(
+        self,
+        ttl: Optional[float] = 3600.0,
+        clock: Callable[[], float] = time.time,
+        id_factory: Optional[Callable[[], str]] = None,
+    ):
+        """ttl: seconds a session may sit idle before it expires (None = never)."""
+        self.ttl = ttl
+        self.clock = clock
+        self.id_factory = id_factory or (lambda: "sess_" + generate_task_id())
+        self._sessions: dict[str, Session] = {}
+        self._locks: dict[str, threading.Lock] = {}
+        self._lock = threading.Lock()
+
+    def create(self, system_prompt: Optional[str] = None, metadata: Optional[dict[str, Any]] = None) -> Session:
+        now = self.clock()
+        session = Session(
+            id=self.id_factory(),
+            metadata=dict(metadata or {}),
+            created_at=now,
+            updated_at=now,
+            system_prompt=system_prompt,
+        )
+        with self._lock:
+            self.purge_expired()
+            self._sessions[session.id] = session
+            self._locks[session.id] = threading.Lock()
+        return session
+
+    def get(self, id: str) -> Session:
+        """The live session; raises SessionNotFoundError when unknown or expired."""
+        with self._lock:
+            session = self._sessions.get(id)
+            if session is None or self._expired(session):
+                self._drop(id)
+                raise SessionNotFoundError(f"Session {id} not found or expired")
+            return session
+
+    def save(self, session: Session):
+        session.updated_at = self.clock()
+        with self._lock:
+            self._sessions[session.id] = session
+            self._locks.setdefault(session.id, threading.Lock())
+
+    def delete(self, id: str) -> bool:
+        with self._lock:
+            return self._drop(id)
+
+    def lock(self, id: str) -> threading.Lock:
+        """Per-session lock so two messages to one session don't interleave."""
+        with self._lock:
+            return self._locks.setdefault(id, threading.Lock())
+
+    def list(self) -> list[Session]:
+        with self._lock:
+            self.purge_expired()
+            return sorted(self._sessions.values(), key=lambda s: s.updated_at, reverse=True)
+
+    def purge_expired(self) -> int:
+        """Drop idle sessions past the TTL. Caller may hold self._lock."""
+        expired = [id for id, session in self._sessions.items() if self._expired(session)]
+        for id in expired:
+            self._drop(id)
+        return len(expired)
+
+    def _expired(self, session: Session) -> bool:
+        return self.ttl is not None and self.clock() - session.updated_at > self.ttl
+
+    def _drop(self, id: str) -> bool:
+        self._locks.pop(id, None)
+        return self._sessions.pop(id, None) is not None
@@ -1,5 +1,25 @@
 """Task store exports."""
 
-from .store import TaskStatus, Task, TaskStore, TaskNotFoundError
+from .store import Clock, IdFactory, TaskStatus, Task, TaskStore, TaskNotFoundError, TaskPage, generate_task_id, sequential_ids
+from .backend import TaskBackend, TaskFilter, JsonBackend, SqliteBackend
+from .checkpoint import Checkpoint, CheckpointStore
+from .journal import ExecutionJournal
 
-__all__ = ["TaskStatus", "Task", "TaskStore", "TaskNotFoundError"]
+__all__ = [
+    "Clock",
+    "IdFactory",
+    "TaskStatus",
+    "Task",
+    "TaskStore",
+    "TaskNotFoundError",
+    "TaskPage",
+    "generate_task_id",
+    "sequential_ids",
+    "TaskBackend",
+    "TaskFilter",
+    "JsonBackend",
+    "SqliteBackend",
+    "Checkpoint",
+    "CheckpointStore",
+    "ExecutionJournal",
+]
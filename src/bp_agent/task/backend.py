@@ -0,0 +1,328 @@
+"""Task storage backends: in-memory/JSON file and SQLite."""
+
+from __future__ import annotations
+
+import gzip
+import json
+import os
+import sqlite3
+import threading
+from dataclasses import dataclass
+from datetime import datetime
+from pathlib import Path
+from typing import TYPE_CHECKING, Callable, Iterator, Optional, Protocol
+
+from ..errors import ConfigError, StoreError
+
+if TYPE_CHECKING:
+    from .store import Task
+
+_ZSTD_MAGIC = b"\x28\xb5\x2f\xfd"
+_GZIP_MAGIC = b"\x1f\x8b"
+
+
+@dataclass(frozen=True)
+class TaskFilter:
+    """Which tasks a listing covers; since/until are epoch seconds against created_at."""
+
+    status: Optional[str] = None
+    since: Optional[float] = None  # inclusive
+    until: Optional[float] = None  # exclusive
+    before: Optional[int] = None  # seq cursor
+
+    def matches(self, task: "Task") -> bool:
+        if self.status is not None and task.status.value != self.status:
+            return False
+        if self.before is not None and task.seq >= self.before:
+            return False
+        if self.since is not None or self.until is not None:
+            created = created_timestamp(task.created_at)
+            if self.since is not None and created < self.since:
+                return False
+            if self.until is not None and created >= self.until:
+                return False
+        return True
+
+
+class TaskBackend(Protocol):
+    """Where a TaskStore keeps its tasks.
+
+    put() gets the task and the record to persist (task.to_dict() with spilled
+    fields truncated); get()/list() may return either, so callers never rely on
+    identity between the two.
+    """
+
+    def get(self, id: str) -> Optional["Task"]: ...
+
+    def put(self, task: "Task", record: dict) -> None: ...
+
+    def insert(self, task: "Task", record: dict) -> None:
+        """Store a new task, numbering it (task.seq and record["seq"]) in the same atomic step."""
+        ...
+
+    def list(self, where: TaskFilter, limit: int, offset: int = 0) -> list["Task"]:
+        """Matching tasks, newest (highest seq) first."""
+        ...
+
+    def count(self, where: TaskFilter) -> int: ...
+
+    def all(self) -> Iterator["Task"]: ...
+
+    def max_seq(self) -> int: ...
+
+
+class JsonBackend:
+    """Tasks in a dict; with a path, the whole set is rewritten to a JSON file on every put.
+
+    Safe to share between threads; the file must not be shared between processes (use sqlite).
+    """
+
+    def __init__(self, path: str | Path | None = None, compression: Optional[str] = None):
+        if compression not in (None, "gzip", "zstd"):
+            raise ConfigError(f"Unknown compression: {compression}")
+        self.path = Path(path) if path else None
+        self.compression = compression
+        self._tasks: dict[str, Task] = {}
+        self._records: dict[str, dict] = {}
+        self._seq = 0
+        self._lock = threading.RLock()
+        if self.path is not None:
+            self._load()
+
+    def get(self, id: str) -> Optional["Task"]:
+        return self._tasks.get(id)
+
+    def put(self, task: "Task", record: dict) -> None:
+        with self._lock:
+            self._tasks[task.id] = task
+            self._records[task.id] = record
+            self._seq = max(self._seq, task.seq)
+            self._save()
+
+    def insert(self, task: "Task", record: dict) -> None:
+        with self._lock:
+            task.seq = record["seq"] = self._seq + 1
+            self.put(task, record)
+
+    def list(self, where: TaskFilter, limit: int, offset: int = 0) -> list["Task"]:
+        with self._lock:
+            tasks = list(self._tasks.values())
+        tasks = sorted((t for t in tasks if where.matches(t)), key=lambda t: t.seq, reverse=True)
+        return tasks[offset: offset + limit]
+
+    def count(self, where: TaskFilter) -> int:
+        with self._lock:
+            tasks = list(self._tasks.values())
+        return sum(1 for t in tasks if where.matches(t))
+
+    def all(self) -> Iterator["Task"]:
+        with self._lock:
+            return iter(list(self._tasks.values()))
+
+    def max_seq(self) -> int:
+        return self._seq
+
+    def _save(self):
+        # Callers hold self._lock; a crash mid-write leaves the previous file intact
+        if self.path is None:
+            return
+
+        if self.path.parent:
+            os.makedirs(self.path.parent, exist_ok=True)
+
+        data = list(self._records.values())
+        tmp = self.path.with_suffix(self.path.suffix + ".tmp")
+        if self.compression is None:
+            with tmp.open("w", encoding="utf-8") as handle:
+                json.dump(data, handle, indent=2)
+        else:
+            payload = json.dumps(data).encode("utf-8")
+            if self.compression == "zstd":
+                import zstandard
+
+                payload = zstandard.ZstdCompressor().compress(payload)
+            else:
+                payload = gzip.compress(payload)
+            tmp.write_bytes(payload)
+        os.replace(tmp, self.path)
+
+    def _load(self):
+        from .store import Task
+
+        if not self.path.exists():
+            return
+
+        raw = self.path.read_bytes()
+        if raw.startswith(_ZSTD_MAGIC):
+            import zstandard
+
+            raw = zstandard.ZstdDecompressor().decompress(raw)
+        elif raw.startswith(_GZIP_MAGIC):
+            raw = gzip.decompress(raw)
+        data = json.loads(raw.decode("utf-8"))
+
+        for item in data:
+            task = Task.from_dict(item)
+            self._tasks[task.id] = task
+        self._seq = assign_seq(list(self._tasks.values()), _created_key)
+        self._records = {task.id: task.to_dict() for task in self._tasks.values()}
+
+
+_SCHEMA = """
+CREATE TABLE IF NOT EXISTS tasks (
+    id TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    created_ts REAL NOT NULL DEFAULT 0,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS tasks_seq ON tasks (seq);
+CREATE INDEX IF NOT EXISTS tasks_status_seq ON tasks (status, seq);
+"""
+_INDEXES = """
+CREATE INDEX IF NOT EXISTS tasks_created ON tasks (created_ts);
+"""
+
+
+class SqliteBackend:
+    """Tasks in a SQLite table, one row per task, indexed by seq, status and creation time.
+
+    A put writes one row instead of the whole set, so large stores stay cheap
+    to update. The connection is shared across threads behind a lock; several
+    processes may share the file, since insert() numbers tasks inside its write
+    transaction.
+    """
+
+    def __init__(self, path: str | Path = "tasks.db"):
+        self.path = Path(path)
+        if str(path) != ":memory:" and self.path.parent:
+            os.makedirs(self.path.parent, exist_ok=True)
+        self._lock = threading.Lock()
+        try:
+            self._db = sqlite3.connect(str(path), check_same_thread=False)
+            self._db.executescript(_SCHEMA)
+            self._migrate()
+            self._db.executescript(_INDEXES)
+        except sqlite3.Error as exc:
+            raise StoreError(f"Cannot open task database {path}: {exc}") from exc
+
+    def get(self, id: str) -> Optional["Task"]:
+        rows = self._query("SELECT data FROM tasks WHERE id = ?", (id,))
+        return _task(rows[0][0]) if rows else None
+
+    def put(self, task: "Task", record: dict) -> None:
+        with self._lock:
+            try:
+                with self._db:
+                    self._write("INSERT OR REPLACE", task, record)
+            except sqlite3.Error as exc:
+                raise StoreError(f"Cannot write task {task.id}: {exc}") from exc
+
+    def insert(self, task: "Task", record: dict) -> None:
+        with self._lock:
+            try:
+                with self._db:
+                    # IMMEDIATE takes the write lock first, so no other process can read the same MAX(seq)
+                    self._db.execute("BEGIN IMMEDIATE")
+                    seq = self._db.execute("SELECT COALESCE(MAX(seq), 0) + 1 FROM tasks").fetchone()[0]
+                    task.seq = record["seq"] = seq
+                    self._write("INSERT", task, record)
+            except sqlite3.Error as exc:
+                raise StoreError(f"Cannot write task {task.id}: {exc}") from exc
+
+    def _write(self, verb: str, task: "Task", record: dict) -> None:
+        self._db.execute(
+            f"{verb} INTO tasks (id, seq, status, created_at, created_ts, data) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                task.id,
+                task.seq,
+                task.status.value,
+                task.created_at,
+                created_timestamp(task.created_at),
+                json.dumps(record),
+            ),
+        )
+
+    def list(self, where: TaskFilter, limit: int, offset: int = 0) -> list["Task"]:
+        sql, params = _where(where)
+        rows = self._query(f"SELECT data FROM tasks{sql} ORDER BY seq DESC LIMIT ? OFFSET ?", (*params, limit, offset))
+        return [_task(data) for (data,) in rows]
+
+    def count(self, where: TaskFilter) -> int:
+        sql, params = _where(where)
+        return self._query(f"SELECT COUNT(*) FROM tasks{sql}", params)[0][0]
+
+    def all(self) -> Iterator["Task"]:
+        return (_task(data) for (data,) in self._query("SELECT data FROM tasks ORDER BY seq"))
+
+    def max_seq(self) -> int:
+        return self._query("SELECT COALESCE(MAX(seq), 0) FROM tasks")[0][0]
+
+    def close(self) -> None:
+        with self._lock:
+            self._db.close()
+
+    def _migrate(self):
+        # Databases created before the created_ts column: add it and fill it from created_at
+        columns = {row[1] for row in self._db.execute("PRAGMA table_info(tasks)")}
+        if "created_ts" in columns:
+            return
+        with self._db:
+            self._db.execute("ALTER TABLE tasks ADD COLUMN created_ts REAL NOT NULL DEFAULT 0")
+            rows = self._db.execute("SELECT id, created_at FROM tasks").fetchall()
+            self._db.executemany(
+                "UPDATE tasks SET created_ts = ? WHERE id = ?",
+                [(created_timestamp(created_at), id) for id, created_at in rows],
+            )
+
+    def _query(self, sql: str, params: tuple = ()) -> list[tuple]:
+        with self._lock:
+            try:
+                return self._db.execute(sql, params).fetchall()
+            except sqlite3.Error as exc:
+                raise StoreError(f"Task database query failed: {exc}") from exc
+
+
+def _where(where: TaskFilter) -> tuple[str, tuple]:
+    clauses, params = [], []
+    for clause, value in (
+        ("status = ?", where.status),
+        ("seq < ?", where.before),
+        ("created_ts >= ?", where.since),
+        ("created_ts < ?", where.until),
+    ):
+        if value is not None:
+            clauses.append(clause)
+            params.append(value)
+    return (f" WHERE {' AND '.join(clauses)}" if clauses else ""), tuple(params)
+
+
+def created_timestamp(created_at: str) -> float:
+    """Epoch seconds of an ISO created_at (naive = local time); 0 if it doesn't parse."""
+    try:
+        return datetime.fromisoformat(created_at).timestamp()
+    except (TypeError, ValueError):
+        return 0.0
+
+
+def _task(data: str) -> "Task":
+    from .store import Task
+
+    return Task.from_dict(json.loads(data))
+
+
+def _created_key(task: "Task"):
+    try:
+        return datetime.fromisoformat(task.created_at)
+    except ValueError:
+        return datetime.min
+
+
+def assign_seq(tasks: list, created_key: Callable) -> int:
+    """Number tasks stored before seq existed (in created_at order); returns the highest seq."""
+    highest = max((t.seq for t in tasks), default=0)
+    for task in sorted((t for t in tasks if not t.seq), key=created_key):
+        highest += 1
+        task.seq = highest
+    return highest
@@ -0,0 +1,95 @@
+"""Execution checkpoints for resuming interrupted agent runs."""
+
+from __future__ import annotations
+
+import json
+import os
+from dataclasses import dataclass, field
+from datetime import datetime
+from pathlib import Path
+from typing import Callable, Optional
+
+
+@dataclass
+class Checkpoint:
+    id: str
+    instruction: str
+    iteration: int
+    messages: list[dict]
+    pending_tool_calls: list[dict] = field(default_factory=list)  # asked for and not yet run
+    updated_at: Optional[str] = None
+    model: Optional[str] = None  # after tier selection / escalation
+    tier: Optional[str] = None
+    deadline: Optional[float] = None  # epoch seconds (execute timeout)
+
+    def to_dict(self) -> dict:
+        return {
+            "id": self.id,
+            "instruction": self.instruction,
+            "iteration": self.iteration,
+            "messages": self.messages,
+            "pending_tool_calls": self.pending_tool_calls,
+            "updated_at": self.updated_at,
+            "model": self.model,
+            "tier": self.tier,
+            "deadline": self.deadline,
+        }
+
+    @classmethod
+    def from_dict(cls, data: dict) -> "Checkpoint":
+        return cls(
+            id=data["id"],
+            instruction=data["instruction"],
+            iteration=data.get("iteration", 0),
+            messages=data.get("messages", []),
+            pending_tool_calls=data.get("pending_tool_calls", []),
+            updated_at=data.get("updated_at"),
+            model=data.get("model"),
+            tier=data.get("tier"),
+            deadline=data.get("deadline"),
+        )
+
+
+class CheckpointStore:
+    """One JSON file per in-flight execution, removed once the run finishes."""
+
+    def __init__(self, path: str | None = None, clock: Optional[Callable[[], datetime]] = None):
+        self.path = Path(path or ".checkpoints")
+        self.clock = clock or datetime.now
+
+    def save(self, checkpoint: Checkpoint) -> None:
+        os.makedirs(self.path, exist_ok=True)
+        checkpoint.updated_at = self.clock().isoformat()
+        target = self._file(checkpoint.id)
+        tmp = target.with_suffix(".json.tmp")
+        with tmp.open("w", encoding="utf-8") as handle:
+            json.dump(checkpoint.to_dict(), handle, indent=2)
+        os.replace(tmp, target)
+
+    def load(self, id: str) -> Checkpoint | None:
+        target = self._file(id)
+        if not target.exists():
+            return None
+        with target.open("r", encoding="utf-8") as handle:
+            return Checkpoint.from_dict(json.load(handle))
+
+    def delete(self, id: str) -> None:
+        try:
+            self._file(id).unlink()
+        except FileNotFoundError:
+            pass
+
+    def list(self) -> list[Checkpoint]:
+        if not self.path.exists():
+            return []
+        checkpoints = []
+        for item in sorted(self.path.glob("*.json")):
+            try:
+                with item.open("r", encoding="utf-8") as handle:
+                    checkpoints.append(Checkpoint.from_dict(json.load(handle)))
+            except (OSError, ValueError, KeyError):
+                continue
+        return checkpoints
+
+    def _file(self, id: str) -> Path:
+        return self.path / f"{id}.json"
@@ -0,0 +1,105 @@
+"""Execution journal: an append-only record of run phases for post-crash diagnostics."""
+
+from __future__ import annotations
+
+import json
+import os
+import threading
+from datetime import datetime
+from pathlib import Path
+from typing import TYPE_CHECKING, Any, Callable, Optional
+
+if TYPE_CHECKING:
+    from .checkpoint import CheckpointStore
+    from .store import TaskStore
+
+TERMINAL_PHASES = ("finished", "reconciled")
+
+
+class ExecutionJournal:
+    """One JSON line per phase change: {"run", "task_id", "phase", "at", ...detail}.
+
+    Phases: started, llm_call, tool_call, finished (success/error) and
+    reconciled. Every line is flushed as it is written, so after a crash the
+    runs whose last line is not terminal are exactly the ones that were in
+    flight, and their last phase says where they stopped.
+    """
+
+    def __init__(self, path: str | None = None, clock: Optional[Callable[[], datetime]] = None):
+        self.path = Path(path or "journal.jsonl")
+        self.clock = clock or datetime.now
+        self._lock = threading.Lock()
+
+    def record(self, run: str, phase: str, task_id: Optional[str] = None, **detail: Any) -> None:
+        entry = {"run": run, "task_id": task_id, "phase": phase, "at": self.clock().isoformat(), **detail}
+        line = json.dumps(entry, default=str) + "\n"
+        with self._lock:
+            if self.path.parent:
+                os.makedirs(self.path.parent, exist_ok=True)
+            with self.path.open("a", encoding="utf-8") as handle:
+                handle.write(line)
+                handle.flush()
+
+    def entries(self) -> list[dict]:
+        """All entries, oldest first; a line cut short by a crash is skipped."""
+        if not self.path.exists():
+            return []
+        entries = []
+        with self._lock, self.path.open("r", encoding="utf-8") as handle:
+            for line in handle:
+                try:
+                    entries.append(json.loads(line))
+                except ValueError:
+                    continue
+        return entries
+
+    def in_flight(self) -> list[dict]:
+        """Last entry of every run that never reached a terminal phase, oldest run first."""
+        last: dict[str, dict] = {}
+        for entry in self.entries():
+            last.pop(entry["run"], None)  # re-insert so order follows the latest activity
+            last[entry["run"]] = entry
+        return [entry for entry in last.values() if entry["phase"] not in TERMINAL_PHASES]
+
+    def reconcile(
+        self,
+        store: Optional["TaskStore"] = None,
+        checkpoints: Optional["CheckpointStore"] = None,
+    ) -> list[dict]:
+        """Close out runs interrupted by a crash; returns their last entries.
+
+        Their tasks still pending/running in `store` are marked failed with an
+        "interrupted: ..." error. Each returned entry gets "resumable": True when
+        `checkpoints` still holds the run's checkpoint (Agent.resume() picks it up).
+        Only call this once no run of this journal can still be alive. The
+        journal is then compacted to the entries of runs that stayed open.
+        """
+        interrupted = self.in_flight()
+        for entry in interrupted:
+            entry["resumable"] = bool(checkpoints and checkpoints.load(entry["run"]) is not None)
+            task = store.get(entry["task_id"]) if store and entry.get("task_id") else None
+            if task is not None and task.status.value in ("pending", "running"):
+                store.update(task.id, status="failed", error=f"interrupted: process stopped during {entry['phase']}")
+            self.record(entry["run"], "reconciled", task_id=entry.get("task_id"), last_phase=entry["phase"])
+        self.compact()
+        return interrupted
+
+    def compact(self) -> int:
+        """Drop the entries of finished runs; returns how many were removed."""
+        with self._lock:
+            if not self.path.exists():
+                return 0
+            lines = self.path.read_text(encoding="utf-8").splitlines(keepends=True)
+            entries = []
+            for line in lines:
+                try:
+                    entries.append((json.loads(line), line))
+                except ValueError:
+                    continue
+            last_phase = {entry["run"]: entry["phase"] for entry, _ in entries}
+            closed = {run for run, phase in last_phase.items() if phase in TERMINAL_PHASES}
+            kept = [line for entry, line in entries if entry["run"] not in closed]
+            tmp = self.path.with_suffix(self.path.suffix + ".tmp")
+            tmp.write_text("".join(kept), encoding="utf-8")
+            os.replace(tmp, self.path)
+            return len(lines) - len(kept)
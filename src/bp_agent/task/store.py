@@ -2,15 +2,22 @@
 
 from __future__ import annotations
 
-import json
 import os
 import random
 import string
-from dataclasses import dataclass
+import threading
+from dataclasses import dataclass, field
 from datetime import datetime
 from enum import Enum
 from pathlib import Path
-from typing import Optional
+from typing import Callable, Optional
+
+from ..errors import ConfigError, NotFoundError
+from .backend import JsonBackend, SqliteBackend, TaskBackend, TaskFilter
+
+Clock = Callable[[], datetime]  # returns "now"; inject a fixed one in tests
+IdFactory = Callable[[], str]
+TimeBound = datetime | str | float  # datetime, ISO string or epoch seconds
 
 
 class TaskStatus(Enum):
@@ -29,9 +36,14 @@ class Task:
     output: Optional[str] = None
     error: Optional[str] = None
     completed_at: Optional[str] = None
+    artifacts: dict[str, str] = field(default_factory=dict)  # spilled field -> artifact file
+    variant: Optional[str] = None  # prompt variant (A/B tests)
+    seq: int = 0  # creation order assigned by the store; unlike created_at, immune to clock changes
+    routing: Optional[dict] = None  # front-door routing decision (profile, method, ...)
+    postmortem: Optional[str] = None  # failure summary for operators (AgentConfig.postmortem)
 
     def to_dict(self) -> dict:
-        return {
+        data = {
             "id": self.id,
             "instruction": self.instruction,
             "status": self.status.value,
@@ -40,6 +52,17 @@ class Task:
             "created_at": self.created_at,
             "completed_at": self.completed_at,
         }
+        if self.artifacts:
+            data["artifacts"] = dict(self.artifacts)
+        if self.variant:
+            data["variant"] = self.variant
+        if self.seq:
+            data["seq"] = self.seq
+        if self.routing:
+            data["routing"] = dict(self.routing)
+        if self.postmortem:
+            data["postmortem"] = self.postmortem
+        return data
 
     @classmethod
     def from_dict(cls, data: dict) -> "Task":
@@ -51,32 +74,95 @@ class Task:
             error=data.get("error"),
             created_at=data["created_at"],
             completed_at=data.get("completed_at"),
+            artifacts=data.get("artifacts") or {},
+            variant=data.get("variant"),
+            seq=data.get("seq", 0),
+            routing=data.get("routing"),
+            postmortem=data.get("postmortem"),
         )
 
 
-class TaskNotFoundError(Exception):
+class TaskNotFoundError(NotFoundError):
     pass
 
 
-class TaskStore:
-    def __init__(self, persist: bool = False, path: str | None = None):
-        self.persist = persist
-        self.path = Path(path or "tasks.json")
-        self._tasks: dict[str, Task] = {}
+@dataclass
+class TaskPage:
+    tasks: list[Task]
+    total: int  # tasks matching the filters, across all pages
+    offset: int
+    limit: int
 
-        if self.persist:
-            self._load()
+    @property
+    def has_more(self) -> bool:
+        return self.offset + len(self.tasks) < self.total
 
-    def create(self, instruction: str) -> Task:
+
+SPILL_FIELDS = ("output", "error")
+BACKENDS = ("memory", "json", "sqlite")
+
+
+class TaskStore:
+    def __init__(
+        self,
+        persist: bool = False,
+        path: str | None = None,
+        compression: Optional[str] = None,
+        max_field_chars: Optional[int] = None,
+        artifact_dir: str | None = None,
+        id_factory: Optional[IdFactory] = None,
+        clock: Optional[Clock] = None,
+        backend: str | TaskBackend | None = None,
+    ):
+        """compression: None | "gzip" | "zstd" (needs zstandard). Reading detects the format.
+
+        backend: "memory" | "json" | "sqlite" or a TaskBackend; default "json" when
+        persist, else "memory". path defaults to tasks.json / tasks.db.
+        id_factory/clock replace generate_task_id and datetime.now (deterministic tests).
+        """
+        if backend is None:
+            backend = "json" if persist else "memory"
+        if isinstance(backend, str):
+            if backend not in BACKENDS:
+                raise ConfigError(f"Unknown task backend: {backend}")
+            if compression is not None and backend == "sqlite":
+                raise ConfigError("compression is not supported by the sqlite task backend")
+            default_path = "tasks.db" if backend == "sqlite" else "tasks.json"
+            self.path = Path(path or default_path)
+            if backend == "sqlite":
+                backend = SqliteBackend(self.path)
+            else:
+                backend = JsonBackend(self.path if backend == "json" else None, compression)
+        else:
+            self.path = Path(path or getattr(backend, "path", None) or "tasks.json")
+        self.backend: TaskBackend = backend
+        self.persist = not (isinstance(backend, JsonBackend) and backend.path is None)
+        self.compression = compression
+        # Fields longer than this are stored in artifact files, truncated in the store
+        self.max_field_chars = max_field_chars
+        self.artifact_dir = Path(artifact_dir) if artifact_dir else self.path.with_name(self.path.name + ".artifacts")
+        self.clock: Clock = clock or datetime.now
+        self.id_factory: IdFactory = id_factory or (lambda: generate_task_id(self.clock()))
+        # create()/update() are called from submit threads, runner workers and aexecute
+        self._lock = threading.RLock()
+        self._seq = self.backend.max_seq()  # only for custom backends without insert()
+
+    def create(self, instruction: str, variant: Optional[str] = None) -> Task:
         task = Task(
-            id=generate_task_id(),
+            id=self.id_factory(),
             instruction=instruction,
             status=TaskStatus.PENDING,
-            created_at=datetime.now().isoformat(),
+            created_at=self.clock().isoformat(),
+            variant=variant,
         )
-
-        self._tasks[task.id] = task
-        self._save_if_persist()
+        with self._lock:
+            insert = getattr(self.backend, "insert", None)
+            if insert is not None:
+                insert(task, self._record(task))
+            else:
+                self._seq = max(self._seq, self.backend.max_seq()) + 1
+                task.seq = self._seq
+                self._put(task)
         return task
 
     def update(
@@ -85,64 +171,164 @@ class TaskStore:
         status: str | TaskStatus | None = None,
         output: Optional[str] = None,
         error: Optional[str] = None,
+        routing: Optional[dict] = None,
+        postmortem: Optional[str] = None,
     ) -> Task:
-        if id not in self._tasks:
-            raise TaskNotFoundError(f"Task {id} not found")
+        with self._lock:
+            return self._update(id, status, output, error, routing, postmortem)
 
-        task = self._tasks[id]
+    def _update(
+        self,
+        id: str,
+        status: str | TaskStatus | None,
+        output: Optional[str],
+        error: Optional[str],
+        routing: Optional[dict],
+        postmortem: Optional[str],
+    ) -> Task:
+        task = self.backend.get(id)
+        if task is None:
+            raise TaskNotFoundError(f"Task {id} not found")
 
         if status is not None:
             task.status = TaskStatus(status) if isinstance(status, str) else status
 
         if output is not None:
             task.output = output
+            task.artifacts.pop("output", None)
 
         if error is not None:
             task.error = error
+            task.artifacts.pop("error", None)
 
-        if task.status in (TaskStatus.COMPLETED, TaskStatus.FAILED):
-            task.completed_at = datetime.now().isoformat()
+        if routing is not None:
+            task.routing = dict(routing)
 
-        self._save_if_persist()
-        return task
+        if postmortem is not None:
+            task.postmortem = postmortem
 
-    def get(self, id: str) -> Task | None:
-        return self._tasks.get(id)
-
-    def list(self, limit: int = 10) -> list[Task]:
-        def sort_key(t: Task):
-            try:
-                return datetime.fromisoformat(t.created_at)
-            except ValueError:
-                return datetime.min
-
-        tasks = sorted(self._tasks.values(), key=sort_key, reverse=True)
-        return tasks[:limit]
-
-    def _save_if_persist(self):
-        if not self.persist:
-            return
+        if status is not None and task.status in (TaskStatus.COMPLETED, TaskStatus.FAILED):
+            task.completed_at = self.clock().isoformat()
 
-        if self.path.parent:
-            os.makedirs(self.path.parent, exist_ok=True)
-
-        data = [t.to_dict() for t in self._tasks.values()]
-        with self.path.open("w", encoding="utf-8") as handle:
-            json.dump(data, handle, indent=2)
-
-    def _load(self):
-        if not self.path.exists():
-            return
+        self._put(task)
+        return task
 
-        with self.path.open("r", encoding="utf-8") as handle:
-            data = json.load(handle)
+    def get(self, id: str) -> Task | None:
+        return self.backend.get(id)
 
-        for item in data:
-            task = Task.from_dict(item)
-            self._tasks[task.id] = task
+    def list(
+        self,
+        limit: int = 10,
+        before: Optional[int] = None,
+        status: str | TaskStatus | None = None,
+        since: Optional[TimeBound] = None,
+        until: Optional[TimeBound] = None,
+        offset: int = 0,
+    ) -> list[Task]:
+        """Newest first, by seq. Pass the last task's seq as `before` to get the next page.
 
+        since (inclusive) / until (exclusive) bound created_at.
+        """
+        return self.backend.list(_task_filter(status, since, until, before), limit, offset)
 
-def generate_task_id() -> str:
-    timestamp = datetime.now().strftime("%Y%m%d_%H%M%S")
+    def query(
+        self,
+        status: str | TaskStatus | None = None,
+        since: Optional[TimeBound] = None,
+        until: Optional[TimeBound] = None,
+        offset: int = 0,
+        limit: int = 10,
+    ) -> TaskPage:
+        """list() with offset paging and the total number of matching tasks."""
+        if offset < 0 or limit < 0:
+            raise ConfigError("offset and limit must not be negative")
+        where = _task_filter(status, since, until)
+        return TaskPage(self.backend.list(where, limit, offset), self.backend.count(where), offset, limit)
+
+    def variant_stats(self) -> dict[str, dict]:
+        """Finished task counts and success rate per prompt variant."""
+        stats: dict[str, dict] = {}
+        for task in self.backend.all():
+            if not task.variant or task.status not in (TaskStatus.COMPLETED, TaskStatus.FAILED):
+                continue
+            entry = stats.setdefault(task.variant, {"total": 0, "completed": 0, "failed": 0})
+            entry["total"] += 1
+            entry[task.status.value] += 1
+        for entry in stats.values():
+            entry["success_rate"] = entry["completed"] / entry["total"]
+        return stats
+
+    def read_field(self, id: str, name: str) -> Optional[str]:
+        """Full value of a field, reading it back from its artifact file if it was spilled."""
+        task = self.backend.get(id)
+        if task is None:
+            raise TaskNotFoundError(f"Task {id} not found")
+        artifact = task.artifacts.get(name)
+        if artifact and Path(artifact).exists():
+            return Path(artifact).read_text(encoding="utf-8")
+        return getattr(task, name)
+
+    def _put(self, task: Task):
+        self.backend.put(task, self._record(task))
+
+    def _record(self, task: Task) -> dict:
+        return self._spill(task) if self.persist else task.to_dict()
+
+    def _spill(self, task: Task) -> dict:
+        data = task.to_dict()
+        if not self.max_field_chars:
+            return data
+        for name in SPILL_FIELDS:
+            value = getattr(task, name)
+            if not value or len(value) <= self.max_field_chars:
+                continue
+            if name not in task.artifacts:
+                os.makedirs(self.artifact_dir, exist_ok=True)
+                artifact = self.artifact_dir / f"{task.id}.{name}.txt"
+                artifact.write_text(value, encoding="utf-8")
+                task.artifacts[name] = str(artifact)
+            data[name] = value[: self.max_field_chars]
+            data["artifacts"] = dict(task.artifacts)
+        return data
+
+
+def _task_filter(
+    status: str | TaskStatus | None,
+    since: Optional[TimeBound],
+    until: Optional[TimeBound],
+    before: Optional[int] = None,
+) -> TaskFilter:
+    if isinstance(status, str):
+        try:
+            status = TaskStatus(status)
+        except ValueError:
+            raise ConfigError(f"Unknown task status: {status}") from None
+    return TaskFilter(
+        status=status.value if status else None,
+        since=_epoch(since),
+        until=_epoch(until),
+        before=before,
+    )
+
+
+def _epoch(value: Optional[TimeBound]) -> Optional[float]:
+    if value is None or isinstance(value, (int, float)):
+        return value
+    if isinstance(value, str):
+        try:
+            value = datetime.fromisoformat(value)
+        except ValueError:
+            raise ConfigError(f"Invalid date: {value!r}") from None
+    return value.timestamp()
+
+
+def generate_task_id(now: Optional[datetime] = None) -> str:
+    timestamp = (now or datetime.now()).strftime("%Y%m%d_%H%M%S")
     suffix = "".join(random.choices(string.ascii_lowercase + string.digits, k=4))
     return f"{timestamp}_{suffix}"
+
+
+def sequential_ids(prefix: str = "task", start: int = 1) -> IdFactory:
+    """Id factory yielding prefix_0001, prefix_0002, ... (for tests and reproducible runs)."""
+    counter = iter(range(start, 1 << 62))
+    return lambda: f"{prefix}_{next(counter):04d}"
@@ -0,0 +1,48 @@
+"""Prompt templating - `{{ name }}` placeholders rendered per request."""
+
+from __future__ import annotations
+
+import re
+import time
+from typing import Any, Callable, Mapping, Optional
+
+_PLACEHOLDER = re.compile(r"\{\{\s*([\w.]+)\s*(?:\|\s*([^}]*?)\s*)?\}\}")
+
+
+def builtin_values(clock: Optional[Callable[[], float]] = None) -> dict[str, Any]:
+    """Date/time values available to every template, computed at render time (UTC)."""
+    now = time.gmtime((clock or time.time)())
+    return {
+        "now": time.strftime("%Y-%m-%dT%H:%M:%SZ", now),
+        "date": time.strftime("%Y-%m-%d", now),
+        "time": time.strftime("%H:%M", now),
+        "weekday": time.strftime("%A", now),
+    }
+
+
+def _lookup(values: Mapping[str, Any], name: str) -> Optional[Any]:
+    value: Any = values
+    for part in name.split("."):
+        if isinstance(value, Mapping) and part in value:
+            value = value[part]
+        else:
+            return None
+    return value() if callable(value) else value
+
+
+def render_template(text: str, values: Mapping[str, Any]) -> str:
+    """Replace `{{ name }}`, `{{ user.name }}` and `{{ name | fallback }}`.
+
+    Callable values are called on each render. Unknown names without a
+    fallback are left untouched, so literal braces in prompts survive.
+    """
+    if "{{" not in text:
+        return text
+
+    def sub(match: re.Match) -> str:
+        value = _lookup(values, match.group(1))
+        if value is None:
+            return match.group(2) if match.group(2) is not None else match.group(0)
+        return str(value)
+
+    return _PLACEHOLDER.sub(sub, text)
@@ -0,0 +1,45 @@
+"""Provider-specific tool-use guidance appended to the system prompt (AgentConfig.tool_guidance)."""
+
+from __future__ import annotations
+
+from typing import Mapping, Optional
+
+# Keyed by router provider name; phrasing follows each vendor's own tool-use docs
+TOOL_GUIDANCE: dict[str, str] = {
+    "gemini": (
+        "Use tools only through function calls; never write a call out as text or code. "
+        "Make independent calls together in one turn, and wait for their results before calls that depend on them. "
+        "Give every argument the type its schema declares (numbers as numbers, not strings)."
+    ),
+    "opus": (
+        "Use tools through tool_use blocks, never by describing a call in prose. "
+        "Before calling, decide which tool fits; make independent calls in the same turn. "
+        "Do not guess a result a tool could give you - call the tool."
+    ),
+    "openai": (
+        "Make every tool call with function calling. Arguments must be valid JSON matching the tool's schema, "
+        "with no extra fields. Do not announce a call before making it; call independent tools in parallel."
+    ),
+    "codex": (
+        "Make every tool call with function calling. Arguments must be valid JSON matching the tool's schema, "
+        "with no extra fields. Do not announce a call before making it; call independent tools in parallel."
+    ),
+    "ollama": (
+        "Only the tools listed to you exist; never invent tool names or arguments. "
+        "To use a tool, emit a tool call - do not write text that describes one. "
+        "Call one tool at a time and read its result before deciding the next step."
+    ),
+}
+
+
+def register_tool_guidance(provider: str, text: str) -> None:
+    TOOL_GUIDANCE[provider] = text
+
+
+def tool_guidance(provider: Optional[str], overrides: Optional[Mapping[str, str]] = None) -> Optional[str]:
+    """Guidance for a provider; overrides win, and an empty override turns it off."""
+    if not provider:
+        return None
+    if overrides and provider in overrides:
+        return overrides[provider] or None
+    return TOOL_GUIDANCE.get(provider)
@@ -1,6 +1,9 @@
 """Tooling exports."""
 
 from .registry import ToolSchema, ToolResult, ToolEntry, ToolRegistry, build_schema, GiveResultSignal
-from .builtins import register_builtins
+from .builtins import register_builtins, shared_builtin_registry
+from .manifest import load_tool_manifest
+from .shell import ShellConfig, register_builtin_shell
+from .http import HttpConfig, register_builtin_http
 
-__all__ = ["ToolSchema", "ToolResult", "ToolEntry", "ToolRegistry", "build_schema", "register_builtins", "GiveResultSignal"]
+__all__ = ["ToolSchema", "ToolResult", "ToolEntry", "ToolRegistry", "build_schema", "register_builtins", "shared_builtin_registry", "GiveResultSignal", "load_tool_manifest", "ShellConfig", "register_builtin_shell", "HttpConfig", "register_builtin_http"]
@@ -4,9 +4,12 @@ from __future__ import annotations
 
 import os
 import subprocess
+import threading
+from datetime import datetime, timezone as dt_timezone
 from pathlib import Path
 from typing import Optional
 
+from .calculator import calculate
 from .registry import ToolRegistry, ToolSchema, build_schema, GiveResultSignal
 
 
@@ -76,6 +79,28 @@ def _list_dir_handler(path: str = ".") -> str:
         return f"[error] {exc}"
 
 
+def _current_time_handler(timezone: str = "UTC") -> str:
+    """Current date and time in the given IANA timezone."""
+    try:
+        from zoneinfo import ZoneInfo, ZoneInfoNotFoundError
+    except ImportError:  # pragma: no cover - py<3.9
+        return "[error] zoneinfo not available"
+    try:
+        tz = dt_timezone.utc if timezone.upper() == "UTC" else ZoneInfo(timezone)
+    except (ZoneInfoNotFoundError, ValueError):
+        return f"[error] Unknown timezone: {timezone}"
+    now = datetime.now(tz)
+    return f"{now.isoformat(timespec='seconds')} ({now.strftime('%A')}, {timezone}, unix {int(now.timestamp())})"
+
+
+def _calculate_handler(expression: str, precision: int = 50) -> str:
+    """Evaluate arithmetic exactly (or convert units) so the model doesn't have to."""
+    try:
+        return calculate(expression, precision)
+    except ValueError as exc:
+        return f"[error] {exc}"
+
+
 BASH_SCHEMA = build_schema(
     "bash",
     "Execute a bash command and return output",
@@ -105,6 +130,24 @@ LIST_DIR_SCHEMA = build_schema(
     path={"type": "string", "description": "Path to the directory (default: current)"},
 )
 
+CURRENT_TIME_SCHEMA = build_schema(
+    "current_time",
+    "Get the current date and time. Use this instead of guessing today's date",
+    timezone={"type": "string", "description": "IANA timezone, e.g. Europe/Istanbul (default UTC)"},
+)
+
+CALCULATE_SCHEMA = build_schema(
+    "calculate",
+    "Evaluate a math expression exactly, or convert units (e.g. '5 km to mi', '100 F to C'). "
+    "Use this instead of doing arithmetic yourself",
+    expression={
+        "type": "string",
+        "description": "Expression with + - * / // % ** (or ^), sqrt, ln, log, sin, factorial, ...",
+        "required": True,
+    },
+    precision={"type": "integer", "description": "Significant digits for non-exact results (default 50)"},
+)
+
 GIVE_RESULT_SCHEMA = build_schema(
     "give_result",
     "REQUIRED: Call this tool to deliver your final answer to the user. The task is NOT complete until you call this tool.",
@@ -119,8 +162,25 @@ def _give_result_handler(result: str) -> str:
 
 def register_builtins(registry: ToolRegistry) -> None:
     """Register all built-in tools."""
-    registry.register("bash", _bash_handler, BASH_SCHEMA)
-    registry.register("read_file", _read_file_handler, READ_FILE_SCHEMA)
-    registry.register("write_file", _write_file_handler, WRITE_FILE_SCHEMA)
-    registry.register("list_dir", _list_dir_handler, LIST_DIR_SCHEMA)
-    registry.register("give_result", _give_result_handler, GIVE_RESULT_SCHEMA)
+    registry.register("bash", _bash_handler, BASH_SCHEMA, toolset="builtin", requires_approval=True)
+    registry.register("read_file", _read_file_handler, READ_FILE_SCHEMA, toolset="builtin")
+    registry.register("write_file", _write_file_handler, WRITE_FILE_SCHEMA, toolset="builtin", requires_approval=True)
+    registry.register("list_dir", _list_dir_handler, LIST_DIR_SCHEMA, toolset="builtin")
+    registry.register("current_time", _current_time_handler, CURRENT_TIME_SCHEMA, toolset="builtin")
+    registry.register("calculate", _calculate_handler, CALCULATE_SCHEMA, toolset="builtin")
+    registry.register("give_result", _give_result_handler, GIVE_RESULT_SCHEMA, toolset="builtin")
+
+
+_shared_registry: Optional[ToolRegistry] = None
+_shared_lock = threading.Lock()
+
+
+def shared_builtin_registry() -> ToolRegistry:
+    """Process-wide registry holding the built-in tools once, for agents to overlay."""
+    global _shared_registry
+    with _shared_lock:
+        if _shared_registry is None:
+            registry = ToolRegistry()
+            register_builtins(registry)
+            _shared_registry = registry
+        return _shared_registry
@@ -0,0 +1,215 @@
+"""Safe expression evaluation and unit conversion for the `calculate` builtin."""
+
+from __future__ import annotations
+
+import ast
+import math
+import re
+from decimal import Decimal, InvalidOperation, localcontext
+from typing import Union
+
+Number = Union[int, Decimal]
+
+MAX_EXPONENT = 10_000  # keeps 2**n-style inputs from eating the process
+MAX_INT_DIGITS = 10_000  # also bounds the integer part of decimal results
+MAX_OUTPUT_CHARS = 10_000  # longer decimals are shown in scientific notation
+
+_CONSTANTS = {"pi": Decimal(str(math.pi)), "e": Decimal(str(math.e)), "tau": Decimal(str(math.tau))}
+
+# unit -> (dimension, factor to the base unit)
+UNITS: dict[str, tuple[str, Decimal]] = {}
+
+
+def _units(dimension: str, table: dict[str, str], aliases: dict[str, str] | None = None):
+    for name, factor in table.items():
+        UNITS[name] = (dimension, Decimal(factor))
+    for alias, name in (aliases or {}).items():
+        UNITS[alias] = UNITS[name]
+
+
+_units("length", {"m": "1", "km": "1000", "cm": "0.01", "mm": "0.001", "mi": "1609.344", "yd": "0.9144",
+                  "ft": "0.3048", "in": "0.0254", "nmi": "1852"},
+       {"meter": "m", "meters": "m", "mile": "mi", "miles": "mi", "feet": "ft", "foot": "ft", "inch": "in", "inches": "in"})
+_units("mass", {"kg": "1", "g": "0.001", "mg": "0.000001", "t": "1000", "lb": "0.45359237", "oz": "0.028349523125"},
+       {"lbs": "lb", "pound": "lb", "pounds": "lb", "gram": "g", "grams": "g"})
+_units("time", {"s": "1", "ms": "0.001", "min": "60", "h": "3600", "day": "86400", "week": "604800", "year": "31557600"},
+       {"sec": "s", "hr": "h", "hour": "h", "hours": "h", "days": "day", "weeks": "week", "years": "year"})
+_units("volume", {"l": "1", "ml": "0.001", "m3": "1000", "gal": "3.785411784", "qt": "0.946352946", "floz": "0.0295735295625"},
+       {"L": "l", "liter": "l", "liters": "l"})
+_units("data", {"B": "1", "KB": "1000", "MB": "1000000", "GB": "1000000000", "TB": "1000000000000",
+                "KiB": "1024", "MiB": "1048576", "GiB": "1073741824", "TiB": "1099511627776"})
+_units("speed", {"m/s": "1", "km/h": "0.2777777777777777777777777778", "mph": "0.44704", "kn": "0.5144444444444444444444444444"},
+       {"kph": "km/h", "knots": "kn"})
+
+_TEMPERATURES = {"C", "F", "K", "°C", "°F"}
+_CONVERSION = re.compile(r"^\s*(.+?)\s*([A-Za-z°][\w/°]*)\s+(?:to|in|as)\s+([A-Za-z°][\w/°]*)\s*$")
+
+
+def calculate(expression: str, precision: int = 50) -> str:
+    """Evaluate an arithmetic expression or a unit conversion ("5 km to mi")."""
+    with localcontext() as ctx:
+        ctx.prec = max(1, min(precision, 1000))
+        match = _CONVERSION.match(expression)
+        if match and (match.group(2) in UNITS or match.group(2) in _TEMPERATURES):
+            result = convert(evaluate(match.group(1)), match.group(2), match.group(3))
+            with localcontext() as display:
+                display.prec = min(ctx.prec, 12)  # unit factors are not exact past this
+                result = +result
+            return f"{format_number(result)} {match.group(3)}"
+        return format_number(evaluate(expression))
+
+
+def evaluate(expression: str) -> Number:
+    source = expression.replace("^", "**")
+    try:
+        tree = ast.parse(source, mode="eval")
+    except SyntaxError as exc:
+        raise ValueError(f"Invalid expression: {exc.msg}") from None
+    return _eval(tree.body, source)
+
+
+def convert(value: Number, source: str, target: str) -> Decimal:
+    value = Decimal(value)
+    if source in _TEMPERATURES or target in _TEMPERATURES:
+        src, dst = source.lstrip("°"), target.lstrip("°")
+        if src not in ("C", "F", "K") or dst not in ("C", "F", "K"):
+            raise ValueError(f"Cannot convert {source} to {target}")
+        kelvin = {"C": value + Decimal("273.15"), "F": (value - 32) * 5 / 9 + Decimal("273.15"), "K": value}[src]
+        return {"C": kelvin - Decimal("273.15"), "F": (kelvin - Decimal("273.15")) * 9 / 5 + 32, "K": kelvin}[dst]
+    if source not in UNITS or target not in UNITS:
+        raise ValueError(f"Unknown unit: {source if source not in UNITS else target}")
+    (src_dim, src_factor), (dst_dim, dst_factor) = UNITS[source], UNITS[target]
+    if src_dim != dst_dim:
+        raise ValueError(f"Cannot convert {src_dim} ({source}) to {dst_dim} ({target})")
+    return value * src_factor / dst_factor
+
+
+def format_number(value: Number) -> str:
+    if isinstance(value, int):
+        return format(Decimal(value), "f")  # str() refuses ints past sys.get_int_max_str_digits()
+    _checked(value)
+    if value == value.to_integral_value():
+        try:
+            return str(value.quantize(Decimal(1)))
+        except InvalidOperation:  # more digits than the precision
+            text = format(value, "f")
+    else:
+        text = format(value.normalize(), "f")
+    return text if len(text) <= MAX_OUTPUT_CHARS else format(value.normalize(), "E")
+
+
+def _checked(value: Number) -> Number:
+    """Reject decimals whose integer part has more than MAX_INT_DIGITS digits."""
+    if isinstance(value, Decimal) and value.is_finite() and value.adjusted() >= MAX_INT_DIGITS:
+        _too_big()
+    return value
+
+
+def _to_decimal(value: Number) -> Decimal:
+    return value if isinstance(value, Decimal) else Decimal(value)
+
+
+def _maybe_int(value: Decimal) -> Number:
+    return int(value) if value == value.to_integral_value() and abs(value) < Decimal(10) ** 28 else value
+
+
+def _call_float(fn, *args):
+    return Decimal(repr(fn(*(float(a) for a in args))))
+
+
+_FUNCTIONS = {
+    "sqrt": lambda x: _to_decimal(x).sqrt(),
+    "exp": lambda x: _to_decimal(x).exp(),
+    "ln": lambda x: _to_decimal(x).ln(),
+    "log10": lambda x: _to_decimal(x).log10(),
+    "log": lambda x, base=None: _to_decimal(x).ln() if base is None else _to_decimal(x).ln() / _to_decimal(base).ln(),
+    "abs": abs,
+    "round": lambda x, n=0: round(_to_decimal(x), int(n)),
+    "floor": lambda x: math.floor(_checked(x)),
+    "ceil": lambda x: math.ceil(_checked(x)),
+    "factorial": lambda n: math.factorial(int(n)) if int(_checked(n)) <= 5000 else _too_big(),
+    "gcd": lambda a, b: math.gcd(int(_checked(a)), int(_checked(b))),
+    "min": min,
+    "max": max,
+    "sin": lambda x: _call_float(math.sin, x),
+    "cos": lambda x: _call_float(math.cos, x),
+    "tan": lambda x: _call_float(math.tan, x),
+    "asin": lambda x: _call_float(math.asin, x),
+    "acos": lambda x: _call_float(math.acos, x),
+    "atan": lambda x: _call_float(math.atan, x),
+    "radians": lambda x: _call_float(math.radians, x),
+    "degrees": lambda x: _call_float(math.degrees, x),
+}
+
+
+def _too_big():
+    raise ValueError("Result too large")
+
+
+def _eval(node: ast.AST, source: str) -> Number:
+    if isinstance(node, ast.Constant) and isinstance(node.value, (int, float)) and not isinstance(node.value, bool):
+        if isinstance(node.value, int):
+            return node.value
+        # Use the literal text so 0.1 stays exactly 0.1
+        literal = ast.get_source_segment(source, node) or repr(node.value)
+        return Decimal(literal.replace("_", ""))
+    if isinstance(node, ast.Name):
+        if node.id in _CONSTANTS:
+            return +_CONSTANTS[node.id]
+        raise ValueError(f"Unknown name: {node.id}")
+    if isinstance(node, ast.UnaryOp) and isinstance(node.op, (ast.UAdd, ast.USub)):
+        value = _eval(node.operand, source)
+        return -value if isinstance(node.op, ast.USub) else +value
+    if isinstance(node, ast.BinOp):
+        left, right = _eval(node.left, source), _eval(node.right, source)
+        return _binop(node.op, left, right)
+    if isinstance(node, ast.Call) and isinstance(node.func, ast.Name) and not node.keywords:
+        fn = _FUNCTIONS.get(node.func.id)
+        if fn is None:
+            raise ValueError(f"Unknown function: {node.func.id}")
+        args = [_eval(arg, source) for arg in node.args]
+        try:
+            result = fn(*args)
+        except (InvalidOperation, ValueError, TypeError, OverflowError) as exc:
+            raise ValueError(f"{node.func.id}: {exc or 'invalid argument'}") from None
+        return _maybe_int(result) if isinstance(result, Decimal) else result
+    raise ValueError(f"Unsupported expression: {ast.dump(node)[:60]}")
+
+
+def _binop(op: ast.operator, left: Number, right: Number) -> Number:
+    both_int = isinstance(left, int) and isinstance(right, int)
+    try:
+        if isinstance(op, ast.Add):
+            return left + right
+        if isinstance(op, ast.Sub):
+            return left - right
+        if isinstance(op, ast.Mult):
+            result = left * right
+        elif isinstance(op, ast.Div):
+            if right == 0:
+                raise ValueError("Division by zero")
+            if both_int and left % right == 0:
+                return left // right
+            return _to_decimal(left) / _to_decimal(right)
+        elif isinstance(op, ast.FloorDiv):
+            if right == 0:
+                raise ValueError("Division by zero")
+            return left // right
+        elif isinstance(op, ast.Mod):
+            if right == 0:
+                raise ValueError("Division by zero")
+            return left % right
+        elif isinstance(op, ast.Pow):
+            if abs(right) > MAX_EXPONENT:
+                raise ValueError("Exponent too large")
+            if both_int and right >= 0:
+                result = left ** right
+            else:
+                return _to_decimal(left) ** _to_decimal(right)
+        else:
+            raise ValueError(f"Unsupported operator: {type(op).__name__}")
+    except InvalidOperation:
+        raise ValueError("Invalid operation") from None
+    if isinstance(result, int) and result.bit_length() > MAX_INT_DIGITS * 3.33:
+        raise ValueError("Result too large")
+    return result
@@ -0,0 +1,148 @@
+"""Built-in HTTP tool (http_request) - GET/POST limited to allowlisted URLs."""
+
+from __future__ import annotations
+
+from dataclasses import dataclass, field, fields
+from typing import Any, Optional
+from urllib.parse import urljoin, urlsplit
+
+import requests as http_requests
+
+from ..errors import ConfigError
+from ..llm.egress import EgressPolicy, egress_allowed, is_local_host, url_matches
+from .registry import ToolRegistry, ToolSchema, build_schema
+
+_REDIRECTS = (301, 302, 303, 307, 308)
+_TEXT_TYPES = ("text/", "json", "xml", "javascript", "x-www-form-urlencoded")
+_CREDENTIAL_HEADERS = ("authorization", "cookie", "proxy-authorization")
+
+
+@dataclass
+class HttpConfig:
+    # Host globs ("*.example.com") or URL prefixes ("https://api.github.com/repos/"); None = any public URL.
+    # Loopback/link-local addresses need an entry without wildcards ("localhost", "http://127.0.0.1:8080").
+    allow: Optional[list[str]] = None
+    timeout: float = 15.0  # default seconds per request
+    max_timeout: float = 60.0  # cap on the timeout the model may ask for
+    max_body_bytes: int = 200_000  # response body is cut off here
+    max_redirects: int = 5  # every hop is checked against the allowlist
+    headers: dict[str, str] = field(default_factory=dict)  # auth etc.; not sent on redirects to another origin
+
+    @classmethod
+    def from_dict(cls, data: dict[str, Any]) -> "HttpConfig":
+        unknown = set(data) - {f.name for f in fields(cls)}
+        if unknown:
+            raise ConfigError(f"Unknown http tool options: {', '.join(sorted(unknown))}")
+        return cls(**data)
+
+
+def url_allowed(url: str, allow: Optional[list[str]]) -> bool:
+    if is_local_host(urlsplit(url).hostname):
+        explicit = [pattern for pattern in allow or [] if not any(c in pattern for c in "*?[")]
+        return url_matches(url, explicit)
+    if allow is None:
+        return url_matches(url, ["*"])
+    return url_matches(url, allow)
+
+
+def make_http_request(config: HttpConfig, egress: Optional[EgressPolicy] = None):
+    """http_request handler bound to config (and to an agent's egress policy, if given)."""
+
+    def http_request(
+        url: str,
+        method: str = "GET",
+        body: Optional[str] = None,
+        headers: Optional[dict[str, str]] = None,
+        timeout: Optional[float] = None,
+    ) -> str:
+        method = method.upper()
+        if method not in ("GET", "POST"):
+            return f"[error] Unsupported method: {method} (GET or POST)"
+        limit = min(float(timeout or config.timeout), config.max_timeout)
+        send_headers = {**(headers or {}), **config.headers}
+        # Credentials only go to the origin the call was made to, not to hosts it redirects to
+        cross_origin_headers = {
+            name: value for name, value in (headers or {}).items() if name.lower() not in _CREDENTIAL_HEADERS
+        }
+        start = _origin(url)
+
+        for _ in range(config.max_redirects + 1):
+            if not url_allowed(url, config.allow):
+                return f"[error] URL not allowed: {url}"
+            if not egress_allowed(url, egress):
+                return f"[error] URL blocked by egress policy: {url}"
+            try:
+                resp = http_requests.request(
+                    method, url, data=body.encode("utf-8") if body is not None else None,
+                    headers=send_headers if _origin(url) == start else cross_origin_headers, timeout=limit, stream=True, allow_redirects=False,
+                )
+            except http_requests.RequestException as exc:
+                return f"[error] {exc}"
+            try:
+                location = resp.headers.get("Location")
+                if resp.status_code in _REDIRECTS and location:
+                    url = urljoin(url, location)
+                    if resp.status_code == 303 or (resp.status_code in (301, 302) and method == "POST"):
+                        method, body = "GET", None
+                    continue
+                return _format_response(resp, config.max_body_bytes)
+            finally:
+                resp.close()
+        return f"[error] Too many redirects (max {config.max_redirects})"
+
+    return http_request
+
+
+def _origin(url: str) -> tuple:
+    parts = urlsplit(url)
+    try:
+        port = parts.port
+    except ValueError:
+        port = None
+    return parts.scheme, parts.hostname, port or {"http": 80, "https": 443}.get(parts.scheme)
+
+
+def _format_response(resp, max_bytes: int) -> str:
+    data = b""
+    truncated = False
+    for chunk in resp.iter_content(chunk_size=8192):
+        data += chunk
+        if len(data) > max_bytes:
+            data, truncated = data[:max_bytes], True
+            break
+    content_type = resp.headers.get("Content-Type", "")
+    head = f"HTTP {resp.status_code} {resp.reason or ''}".rstrip()
+    if content_type:
+        head += f"\ncontent-type: {content_type}"
+    if data and content_type and not any(kind in content_type for kind in _TEXT_TYPES):
+        return f"{head}\n\n[binary body, {len(data)} bytes{'+' if truncated else ''}]"
+    text = data.decode(resp.encoding or "utf-8", errors="replace")
+    if truncated:
+        text += f"\n[truncated at {max_bytes} bytes]"
+    return f"{head}\n\n{text}" if text else head
+
+
+def http_request_schema(config: HttpConfig) -> ToolSchema:
+    description = "Fetch a URL over HTTP (GET or POST) and return status, content type and body"
+    if config.allow is not None:
+        description += f". Allowed: {', '.join(config.allow)}"
+    return build_schema(
+        "http_request",
+        description,
+        url={"type": "string", "description": "http(s) URL", "required": True},
+        method={"type": "string", "description": "GET (default) or POST"},
+        body={"type": "string", "description": "Request body for POST"},
+        headers={"type": "object", "description": "Extra request headers"},
+        timeout={
+            "type": "number",
+            "description": f"Timeout in seconds (default {config.timeout:g}, max {config.max_timeout:g})",
+        },
+    )
+
+
+def register_builtin_http(
+    registry: ToolRegistry, config: Optional[HttpConfig] = None, egress: Optional[EgressPolicy] = None
+) -> None:
+    """Register http_request with the given limits (default: any http(s) URL, 200 KB body)."""
+    config = config or HttpConfig()
+    registry.register("http_request", make_http_request(config, egress), http_request_schema(config), toolset="builtin")
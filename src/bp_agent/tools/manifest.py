@@ -0,0 +1,122 @@
+"""Load external tool definitions from a tools.json / tools.toml manifest.
+
+Example (JSON):
+
+    {"tools": [
+      {"name": "grep_logs", "description": "Search service logs",
+       "parameters": {"type": "object", "properties": {"pattern": {"type": "string"}}},
+       "command": ["./scripts/grep_logs.sh"], "timeout": 20},
+      {"name": "lookup_user", "description": "Fetch a user record",
+       "http": {"url": "http://users.internal/lookup", "method": "POST"}}
+    ]}
+
+Command tools receive the tool args as JSON on stdin and return stdout.
+HTTP tools send the args as a JSON body (or query string for GET).
+"""
+
+from __future__ import annotations
+
+import json
+import subprocess
+from pathlib import Path
+from typing import Callable, Optional
+
+from ..errors import ToolError
+from ..llm.egress import EgressPolicy, egress_allowed
+from .registry import ToolRegistry, ToolSchema
+
+
+def load_tool_manifest(registry: ToolRegistry, path: str, egress: Optional[EgressPolicy] = None) -> list[str]:
+    """Register every tool declared in the manifest. Returns registered names.
+
+    HTTP tools are checked against `egress` (an agent's policy) as well as any process-wide one.
+    """
+    data = _read_manifest(Path(path))
+    names: list[str] = []
+    for spec in data.get("tools", []):
+        name = spec.get("name")
+        if not name:
+            raise ToolError(f"Tool manifest entry without name in {path}")
+        schema = ToolSchema(
+            name=name,
+            description=spec.get("description", ""),
+            parameters=spec.get("parameters"),
+        )
+        registry.register(
+            name,
+            _build_handler(name, spec, egress),
+            schema,
+            toolset="manifest",
+            requires_approval=bool(spec.get("requires_approval", False)),
+        )
+        names.append(name)
+    return names
+
+
+def _read_manifest(path: Path) -> dict:
+    text = path.read_text(encoding="utf-8")
+    if path.suffix == ".toml":
+        try:
+            import tomllib
+        except ImportError:  # Python < 3.11
+            raise ToolError("TOML tool manifests require Python 3.11+ (use tools.json)")
+        return tomllib.loads(text)
+    return json.loads(text)
+
+
+def _build_handler(name: str, spec: dict, egress: Optional[EgressPolicy] = None) -> Callable:
+    timeout = spec.get("timeout", 30)
+    if "command" in spec:
+        return _command_handler(spec["command"], timeout, spec.get("cwd"))
+    if "http" in spec:
+        return _http_handler(spec["http"], timeout, egress)
+    if "mcp" in spec:
+        raise ToolError(f"Tool {name}: MCP servers are not supported yet")
+    raise ToolError(f"Tool {name}: manifest entry needs 'command' or 'http'")
+
+
+def _command_handler(command: str | list[str], timeout: int, cwd: str | None) -> Callable:
+    def handler(**args) -> str:
+        try:
+            result = subprocess.run(
+                command,
+                shell=isinstance(command, str),
+                input=json.dumps(args),
+                capture_output=True,
+                text=True,
+                timeout=timeout,
+                cwd=cwd,
+            )
+        except subprocess.TimeoutExpired:
+            return f"[error] Command timed out after {timeout}s"
+        output = result.stdout
+        if result.returncode != 0:
+            output += f"\n[stderr]\n{result.stderr}" if result.stderr else ""
+            output += f"\n[exit code: {result.returncode}]"
+        return output.strip() or "(no output)"
+
+    return handler
+
+
+def _http_handler(http: dict, timeout: int, egress: Optional[EgressPolicy]) -> Callable:
+    url = http["url"]
+    method = http.get("method", "POST").upper()
+    headers = http.get("headers", {})
+
+    def handler(**args) -> str:
+        import requests
+
+        if not egress_allowed(url, egress):
+            return f"[error] URL blocked by egress policy: {url}"
+        try:
+            if method == "GET":
+                resp = requests.get(url, params=args, headers=headers, timeout=timeout)
+            else:
+                resp = requests.request(method, url, json=args, headers=headers, timeout=timeout)
+        except requests.RequestException as exc:
+            return f"[error] {exc}"
+        if resp.status_code >= 400:
+            return f"[error] HTTP {resp.status_code}: {resp.text}"
+        return resp.text
+
+    return handler
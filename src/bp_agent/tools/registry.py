@@ -2,8 +2,17 @@
 
 from __future__ import annotations
 
+import threading
 from dataclasses import dataclass
-from typing import Any, Callable, Optional
+from typing import TYPE_CHECKING, Any, Callable, Optional
+
+from ..errors import ConfigError, ToolError
+from ..validation import validate
+
+if TYPE_CHECKING:
+    from ..llm.egress import EgressPolicy
+    from .http import HttpConfig
+    from .shell import ShellConfig
 
 
 @dataclass
@@ -20,11 +29,15 @@ class ToolSchema:
         }
 
 
+VALIDATION_MODES = ("strict", "advisory", "off")
+
+
 @dataclass
 class ToolResult:
     success: bool
     output: Any
     error: Optional[str] = None
+    warnings: Optional[list[str]] = None  # argument schema violations (advisory validation)
 
 
 @dataclass
@@ -32,6 +45,8 @@ class ToolEntry:
     name: str
     handler: Callable
     schema: ToolSchema
+    toolset: str = "custom"
+    requires_approval: bool = False  # has side effects a client should confirm before running it
 
 
 class GiveResultSignal(Exception):
@@ -42,45 +57,132 @@ class GiveResultSignal(Exception):
 
 
 class ToolRegistry:
-    def __init__(self):
+    def __init__(self, parent: Optional["ToolRegistry"] = None, validation: str = "advisory"):
+        """parent: shared registry looked up behind this one (overlay). Its tools
+        can be hidden per overlay with mask(); the parent itself is never modified.
+
+        validation: check args against the tool's parameters schema before calling it.
+        strict refuses invalid calls, advisory (default) runs them and reports warnings, off skips it.
+        """
+        if validation not in VALIDATION_MODES:
+            raise ConfigError(f"Unknown tool validation mode: {validation}")
+        self.parent = parent
+        self.validation = validation
         self._tools: dict[str, ToolEntry] = {}
-
-    def register(self, name: str, handler: Callable, schema: ToolSchema):
-        if name in self._tools:
-            raise ValueError(f"Tool {name} already registered")
-
-        if not schema.name:
-            schema.name = name
-        elif schema.name != name:
-            raise ValueError(f"Tool schema name mismatch: {schema.name} != {name}")
-
-        self._tools[name] = ToolEntry(name=name, handler=handler, schema=schema)
+        self._masked: set[str] = set()
+        self._lock = threading.RLock()  # registries may be shared across agents/threads
+
+    def register(
+        self, name: str, handler: Callable, schema: ToolSchema, toolset: str = "custom", requires_approval: bool = False
+    ):
+        with self._lock:
+            if self.has(name):
+                raise ToolError(f"Tool {name} already registered")
+
+            if not schema.name:
+                schema.name = name
+            elif schema.name != name:
+                raise ToolError(f"Tool schema name mismatch: {schema.name} != {name}")
+
+            self._tools[name] = ToolEntry(
+                name=name, handler=handler, schema=schema, toolset=toolset, requires_approval=requires_approval
+            )
+
+    def unregister(self, name: str) -> bool:
+        """Remove a tool; on an overlay, a parent tool is masked instead."""
+        with self._lock:
+            if self._tools.pop(name, None) is not None:
+                return True
+            return self.mask(name)
+
+    def mask(self, name: str) -> bool:
+        """Hide a parent tool from this overlay. Returns False if there is nothing to hide."""
+        with self._lock:
+            if self.parent is None or name in self._masked or self.parent.get(name) is None:
+                return False
+            self._masked.add(name)
+            return True
+
+    def unmask(self, name: str) -> bool:
+        with self._lock:
+            if name not in self._masked:
+                return False
+            self._masked.discard(name)
+            return True
+
+    def register_builtin_shell(self, config: Optional["ShellConfig"] = None):
+        """Register the sandboxed run_command tool (limits in tools.shell.ShellConfig)."""
+        from .shell import register_builtin_shell
+
+        register_builtin_shell(self, config)
+
+    def register_builtin_http(self, config: Optional["HttpConfig"] = None, egress: Optional["EgressPolicy"] = None):
+        """Register the http_request tool (limits in tools.http.HttpConfig)."""
+        from .http import register_builtin_http
+
+        register_builtin_http(self, config, egress)
+
+    def get(self, name: str) -> Optional[ToolEntry]:
+        entry = self._tools.get(name)
+        if entry is None and self.parent is not None and name not in self._masked:
+            entry = self.parent.get(name)
+        return entry
+
+    def entries(self) -> list[ToolEntry]:
+        """Visible tools: the parent's (minus masked) first, then this registry's own."""
+        with self._lock:
+            own = list(self._tools.values())
+            hidden = self._masked | set(self._tools)
+        inherited = self.parent.entries() if self.parent is not None else []
+        return [e for e in inherited if e.name not in hidden] + own
 
     def execute(self, name: str, args: dict) -> ToolResult:
-        if name not in self._tools:
+        tool = self.get(name)
+        if tool is None:
             return ToolResult(success=False, output=None, error=f"Tool {name} not found")
 
-        tool = self._tools[name]
+        problems = self.validate_args(name, args) if self.validation != "off" else []
+        if problems and self.validation == "strict":
+            return ToolResult(
+                success=False, output=None, error=f"Invalid arguments for {name}: {'; '.join(problems)}",
+            )
 
         try:
             output = tool.handler(**args)
-            return ToolResult(success=True, output=output, error=None)
+            return ToolResult(success=True, output=output, error=None, warnings=problems or None)
         except GiveResultSignal:
             raise
         except Exception as exc:
-            return ToolResult(success=False, output=None, error=str(exc))
+            error = str(exc)
+            if problems:
+                error += f" (invalid arguments: {'; '.join(problems)})"
+            return ToolResult(success=False, output=None, error=error, warnings=problems or None)
+
+    def validate_args(self, name: str, args: Any) -> list[str]:
+        """Schema violations in args for tool `name` (empty = valid or nothing to check)."""
+        tool = self.get(name)
+        if tool is None or not tool.schema.parameters:
+            return []
+        return validate(args, tool.schema.parameters, path="args")
 
     def get_schemas(self) -> list[ToolSchema]:
-        return [entry.schema for entry in self._tools.values()]
+        return [entry.schema for entry in self.entries()]
+
+    def export(self) -> list[dict]:
+        """Serializable tool catalog (schema, toolset, approval requirement) for clients and docs."""
+        return [
+            {**entry.schema.to_dict(), "toolset": entry.toolset, "requires_approval": entry.requires_approval}
+            for entry in self.entries()
+        ]
 
     def has(self, name: str) -> bool:
-        return name in self._tools
+        return self.get(name) is not None
 
     def count(self) -> int:
-        return len(self._tools)
+        return len(self.entries())
 
     def list_names(self) -> list[str]:
-        return list(self._tools.keys())
+        return [entry.name for entry in self.entries()]
 
 
 def build_schema(name: str, description: str, **params: dict) -> ToolSchema:
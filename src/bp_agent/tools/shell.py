@@ -0,0 +1,173 @@
+"""Sandboxed shell tool (run_command) - timeouts, allow/deny lists, output cap.
+
+The lists match command names (the first word of every command in a pipeline
+or chain, including inside $(...) and backticks). They are a guard rail, not
+a security boundary: allowing an interpreter (bash, python, ...) allows
+whatever it runs.
+"""
+
+from __future__ import annotations
+
+import os
+import shlex
+import signal
+import subprocess
+from dataclasses import dataclass, field, fields
+from pathlib import Path
+from typing import Any, Optional
+
+from ..errors import ConfigError
+from .registry import ToolRegistry, ToolSchema, build_schema
+
+DEFAULT_DENY = ("sudo", "su", "doas", "shutdown", "reboot", "halt", "poweroff", "mkfs")
+
+# Words that run the next word as a command
+_WRAPPERS = {"env", "nohup", "time", "exec", "command", "nice", "timeout", "xargs", "builtin"}
+_OPERATOR_CHARS = set(";&|()")
+
+
+@dataclass
+class ShellConfig:
+    timeout: float = 30.0  # default seconds per command
+    max_timeout: float = 300.0  # cap on the timeout the model may ask for
+    cwd: Optional[str] = None  # working directory; a requested cwd must stay inside it
+    allow: Optional[list[str]] = None  # command names that may run; None = anything not denied
+    deny: list[str] = field(default_factory=lambda: list(DEFAULT_DENY))
+    max_output_chars: int = 20_000  # longer output keeps its head and tail
+    env: Optional[dict[str, str]] = None  # added to the inherited environment
+
+    @classmethod
+    def from_dict(cls, data: dict[str, Any]) -> "ShellConfig":
+        unknown = set(data) - {f.name for f in fields(cls)}
+        if unknown:
+            raise ConfigError(f"Unknown shell tool options: {', '.join(sorted(unknown))}")
+        return cls(**data)
+
+
+def command_names(command: str) -> list[str]:
+    """Names of the commands a shell line would run. Raises ValueError on unbalanced quotes."""
+    lexer = shlex.shlex(command.replace("\n", ";"), posix=True, punctuation_chars=True)
+    lexer.whitespace_split = True
+    names: list[str] = []
+    expect_command = True
+    skip_next = False
+    for token in lexer:
+        if token and set(token) <= _OPERATOR_CHARS:
+            expect_command = expect_command or token != ")"  # `$(a) b`: b is an argument
+            continue
+        if token and set(token) <= set("<>&"):  # redirection; the next word is a file
+            skip_next = True
+            continue
+        if skip_next:
+            skip_next = False
+            continue
+        if "$(" in token or "`" in token:
+            names.extend(command_names(token.replace("$(", ";").replace("`", ";").replace(")", ";")))
+            expect_command = False
+            continue
+        if not expect_command or token == "$":
+            continue
+        if "=" in token and not token.startswith("="):  # FOO=bar cmd
+            continue
+        if (token.startswith("-") or token.replace(".", "").isdigit()) and names and names[-1] in _WRAPPERS:
+            # nice -n 5 cmd, timeout 10 cmd
+            continue
+        name = os.path.basename(token)
+        names.append(name)
+        expect_command = name in _WRAPPERS
+    return names
+
+
+def check_command(command: str, config: ShellConfig) -> Optional[str]:
+    """Why the command may not run, or None if it may."""
+    try:
+        names = command_names(command)
+    except ValueError as exc:
+        return f"Cannot parse command: {exc}"
+    for name in names:
+        if any(name == denied or name.startswith(denied + ".") for denied in config.deny):
+            return f"Command not allowed: {name}"
+        if config.allow is not None and name not in config.allow and name not in _WRAPPERS:
+            return f"Command not in allowlist: {name}"
+    return None
+
+
+def _resolve_cwd(cwd: Optional[str], config: ShellConfig) -> Path:
+    root = Path(config.cwd or os.getcwd()).expanduser().resolve()
+    if not cwd:
+        return root
+    path = (root / Path(cwd).expanduser()).resolve()
+    if config.cwd and path != root and root not in path.parents:
+        raise ValueError(f"Working directory outside {root}: {cwd}")
+    return path
+
+
+def _truncate(output: str, limit: int) -> str:
+    if limit <= 0 or len(output) <= limit:
+        return output
+    half = limit // 2
+    return f"{output[:half]}\n[... {len(output) - 2 * half} chars truncated ...]\n{output[-half:]}"
+
+
+def make_run_command(config: ShellConfig):
+    """run_command handler bound to config."""
+
+    def run_command(command: str, timeout: Optional[float] = None, cwd: Optional[str] = None) -> str:
+        reason = check_command(command, config)
+        if reason:
+            return f"[error] {reason}"
+        try:
+            workdir = _resolve_cwd(cwd, config)
+        except ValueError as exc:
+            return f"[error] {exc}"
+        limit = min(float(timeout or config.timeout), config.max_timeout)
+        env = {**os.environ, **(config.env or {})}
+        try:
+            # Own process group, so a timeout also kills whatever the shell started
+            proc = subprocess.Popen(
+                command, shell=True, cwd=workdir, env=env, text=True,
+                stdout=subprocess.PIPE, stderr=subprocess.PIPE, start_new_session=True,
+            )
+        except OSError as exc:
+            return f"[error] {exc}"
+        try:
+            stdout, stderr = proc.communicate(timeout=limit)
+        except subprocess.TimeoutExpired:
+            try:
+                os.killpg(proc.pid, signal.SIGKILL)
+            except OSError:
+                proc.kill()
+            proc.communicate()
+            return f"[error] Command timed out after {limit:g}s"
+        output = stdout
+        if stderr:
+            output += f"\n[stderr]\n{stderr}"
+        if proc.returncode != 0:
+            output += f"\n[exit code: {proc.returncode}]"
+        return _truncate(output.strip(), config.max_output_chars) or "(no output)"
+
+    return run_command
+
+
+def run_command_schema(config: ShellConfig) -> ToolSchema:
+    description = "Run a shell command and return its output (stdout, stderr and exit code)"
+    if config.allow is not None:
+        description += f". Allowed commands: {', '.join(config.allow)}"
+    return build_schema(
+        "run_command",
+        description,
+        command={"type": "string", "description": "The command to run", "required": True},
+        timeout={
+            "type": "number",
+            "description": f"Timeout in seconds (default {config.timeout:g}, max {config.max_timeout:g})",
+        },
+        cwd={"type": "string", "description": "Working directory, relative to the workspace"},
+    )
+
+
+def register_builtin_shell(registry: ToolRegistry, config: Optional[ShellConfig] = None) -> None:
+    """Register run_command with the given limits (defaults: 30s, common deny list)."""
+    config = config or ShellConfig()
+    registry.register(
+        "run_command", make_run_command(config), run_command_schema(config), toolset="builtin", requires_approval=True
+    )
@@ -0,0 +1,111 @@
+"""Render execution traces as Mermaid sequence diagrams or Graphviz DOT."""
+
+from __future__ import annotations
+
+import json
+import sys
+from typing import Any, Optional
+
+EXCERPT_CHARS = 60
+
+
+def _excerpt(value: Any, limit: int = EXCERPT_CHARS) -> str:
+    text = value if isinstance(value, str) else json.dumps(value, ensure_ascii=False, default=str)
+    text = " ".join(str(text).split())
+    return text[:limit] + "..." if len(text) > limit else text
+
+
+def _steps(trace: dict[str, Any]) -> list[dict[str, Any]]:
+    """Pair tool calls with their results in order (duplicates have no result)."""
+    results = list(trace.get("tool_results") or [])
+    steps = []
+    for call in trace.get("tool_calls") or []:
+        result = None
+        if results and results[0].get("name") == call.get("name"):
+            result = results.pop(0)
+        steps.append({"name": call.get("name"), "args": call.get("args"), "result": result})
+    for result in results:  # give_result and results of resumed calls
+        steps.append({"name": result.get("name"), "args": None, "result": result})
+    return steps
+
+
+def _mermaid_text(text: str) -> str:
+    return text.replace(";", ",").replace("#", "").replace('"', "'")
+
+
+def trace_to_mermaid(trace: dict[str, Any]) -> str:
+    model = trace.get("model") or "model"
+    lines = [
+        "sequenceDiagram",
+        "    participant A as Agent",
+        f"    participant L as {_mermaid_text(str(trace.get('provider') or 'LLM'))}",
+        "    participant T as Tools",
+    ]
+    for step in _steps(trace):
+        name = _mermaid_text(str(step["name"]))
+        lines.append(f"    A->>L: complete ({_mermaid_text(model)})")
+        if step["args"] is not None:
+            lines.append(f"    L-->>A: call {name}({_mermaid_text(_excerpt(step['args']))})")
+        result = step["result"]
+        if result is None:
+            lines.append(f"    Note over A: duplicate {name} skipped")
+            continue
+        lines.append(f"    A->>T: {name}")
+        if result.get("error"):
+            lines.append(f"    T-->>A: error: {_mermaid_text(_excerpt(result['error']))}")
+        else:
+            lines.append(f"    T-->>A: {_mermaid_text(_excerpt(result.get('output')))}")
+    return "\n".join(lines) + "\n"
+
+
+def _dot_label(text: str) -> str:
+    return text.replace("\\", "\\\\").replace('"', '\\"')
+
+
+def trace_to_dot(trace: dict[str, Any]) -> str:
+    lines = [
+        "digraph trace {",
+        "    rankdir=TB;",
+        "    node [shape=box, fontname=monospace];",
+        f'    start [label="{_dot_label(str(trace.get("provider") or "LLM"))} / {_dot_label(str(trace.get("model") or "model"))}", shape=oval];',
+    ]
+    previous = "start"
+    for idx, step in enumerate(_steps(trace), 1):
+        node = f"step{idx}"
+        title = str(step["name"])
+        if step["args"] is not None:
+            title += f"({_excerpt(step['args'], 40)})"
+        result = step["result"]
+        if result is None:
+            detail, style = "duplicate, skipped", ", style=dashed"
+        elif result.get("error"):
+            detail, style = f"error: {_excerpt(result['error'])}", ", color=red"
+        else:
+            detail, style = _excerpt(result.get("output")), ""
+        lines.append(f'    {node} [label="{_dot_label(title)}\\n{_dot_label(detail)}"{style}];')
+        lines.append(f"    {previous} -> {node};")
+        previous = node
+    lines.append("}")
+    return "\n".join(lines) + "\n"
+
+
+def main(argv: Optional[list[str]] = None) -> int:
+    import argparse
+
+    parser = argparse.ArgumentParser(prog="bp-trace", description="Render a saved trace as Mermaid or DOT")
+    parser.add_argument("trace", help="Trace JSON file ('-' for stdin)")
+    parser.add_argument("--format", "-f", choices=("mermaid", "dot"), default="mermaid")
+    args = parser.parse_args(argv)
+
+    if args.trace == "-":
+        trace = json.load(sys.stdin)
+    else:
+        with open(args.trace, "r", encoding="utf-8") as handle:
+            trace = json.load(handle)
+    render = trace_to_mermaid if args.format == "mermaid" else trace_to_dot
+    sys.stdout.write(render(trace))
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
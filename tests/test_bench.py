@@ -0,0 +1,30 @@
+from bp_agent.llm import LLMRouter, LLMResponse, ProviderError
+from bp_agent.runner.bench import BenchStats, run_bench
+
+
+def test_bench_collects_latency_and_errors():
+    class FlakyAdapter:
+        def __init__(self):
+            self.calls = 0
+
+        def complete(self, request):
+            self.calls += 1
+            if self.calls % 4 == 0:
+                raise ProviderError("rate_limit", "slow down", retryable=True)
+            return LLMResponse(content="x" * 40)
+
+    router = LLMRouter(default_provider="fake")
+    router.register_provider("fake", FlakyAdapter())
+
+    [stats] = run_bench(router, ["fake"], requests=8, concurrency=1)
+    assert stats.requests == 8
+    assert stats.errors == {"rate_limit": 2}
+    assert stats.error_rate == 0.25
+    assert stats.output_chars == 6 * 40
+    assert "p95=" in stats.format()
+
+
+def test_bench_percentiles():
+    stats = BenchStats(provider="p", latencies_ms=[float(i) for i in range(1, 101)])
+    assert stats.percentile(50) == 50.0
+    assert stats.percentile(95) == 95.0
@@ -0,0 +1,131 @@
+import json
+import sys
+
+import pytest
+
+import bp_agent.agent as agent
+from bp_agent.agent import Agent
+from bp_agent.runner.chat import invoke_tool
+from bp_agent.llm import LLMRouter
+from bp_agent.tools import build_schema
+
+
+def _agent(monkeypatch):
+    monkeypatch.setattr(agent, "_build_llm_router", lambda config: LLMRouter())
+    inst = Agent("test")
+    inst.add_tool("add", lambda a, b: a + b, build_schema(
+        "add", "Add",
+        a={"type": "integer", "required": True},
+        b={"type": "integer", "required": True},
+    ))
+    return inst
+
+
+def test_invoke_tool_directly(monkeypatch):
+    inst = _agent(monkeypatch)
+
+    assert invoke_tool(inst, 'add {"a": 2, "b": 3}') == "5"
+    assert invoke_tool(inst, 'give_result {"result": "done"}') == "[result] done"
+    assert invoke_tool(inst, "missing").startswith("[error] Tool missing not found")
+    assert invoke_tool(inst, "add {bad").startswith("[error] Invalid JSON")
+
+
+def test_repl_history_expansion_and_variables():
+    from bp_agent.runner.chat import ReplState
+
+    state = ReplState()
+    assert state.command("/set lang Turkish") == "$lang = Turkish"
+    assert state.expand("Translate to $lang: $text") == "Translate to Turkish: $text"
+
+    state.record("first prompt")
+    state.record("second prompt")
+    assert state.expand("!!") == "second prompt"
+    assert state.expand("!1 in $lang") == "first prompt in Turkish"
+    assert state.expand("!-2") == "first prompt"
+    try:
+        state.expand("!9")
+        assert False, "Expected ValueError"
+    except ValueError as exc:
+        assert "event not found" in str(exc)
+
+    assert "  2  second prompt" in state.command("/history")
+    assert state.command("hello") is None
+
+
+def test_repl_apply_patch_from_reply(tmp_path):
+    from bp_agent.runner.chat import ReplState
+    from bp_agent.runner.diffs import colorize_diff, extract_diff
+
+    (tmp_path / "app.py").write_text("def main():\n    print('hi')\n    return 0\n")
+    reply = (
+        "Here is the fix:\n\n```diff\n"
+        "--- a/app.py\n+++ b/app.py\n@@ -1,3 +1,3 @@\n def main():\n-    print('hi')\n+    print('hello')\n     return 0\n"
+        "--- /dev/null\n+++ b/notes.txt\n@@ -0,0 +1 @@\n+todo\n"
+        "```\nDone."
+    )
+    diff = extract_diff(reply)
+    assert diff.startswith("--- a/app.py")
+    assert "\033[32m+    print('hello')\033[0m" in colorize_diff(diff)
+
+    prompts = []
+    state = ReplState(workspace=str(tmp_path), last_reply=reply, confirm=lambda p: prompts.append(p) or True)
+    assert state.command("/apply") == "Applied: app.py, notes.txt"
+    assert "app.py, notes.txt" in prompts[0]
+    assert (tmp_path / "app.py").read_text() == "def main():\n    print('hello')\n    return 0\n"
+    assert (tmp_path / "notes.txt").read_text() == "todo\n"
+
+    state.last_reply = "--- a/../evil\n+++ b/../evil\n@@ -0,0 +1 @@\n+x\n"
+    assert "escapes workspace" in state.command("/apply")
+
+
+def test_repl_aliases_and_macros(tmp_path):
+    from bp_agent.runner.chat import ReplState, load_repl_config
+
+    config_file = tmp_path / "repl.json"
+    config_file.write_text(json.dumps({
+        "aliases": {"/h": "/history"},
+        "macros": {"/review": ["/system You are a strict reviewer", "Review $1 for $focus: $args"]},
+    }))
+    state = ReplState(**load_repl_config(str(config_file)))
+    state.variables["focus"] = "bugs"
+
+    assert state.expand("/h") == "/history"
+    assert state.expand_macro("/review app.py quickly") == [
+        "/system You are a strict reviewer",
+        "Review app.py for bugs: app.py quickly",
+    ]
+    assert state.expand_macro("hello") is None
+    assert load_repl_config(str(tmp_path / "missing.json")) == {}
+
+
+def test_repl_toml_config_without_tomllib_is_a_config_error(monkeypatch, tmp_path):
+    from bp_agent.errors import ConfigError
+    from bp_agent.runner.chat import load_repl_config
+
+    config_file = tmp_path / "repl.toml"
+    config_file.write_text('[aliases]\n"/h" = "/history"\n')
+    monkeypatch.setitem(sys.modules, "tomllib", None)  # what Python 3.10 looks like
+    with pytest.raises(ConfigError, match="Python 3.11"):
+        load_repl_config(str(config_file))
+
+
+def test_transcript_save_and_offline_view(monkeypatch, tmp_path):
+    from bp_agent.llm import Message
+    from bp_agent.runner.view import format_turn, load_turns, save_transcript, view
+
+    inst = _agent(monkeypatch)
+    inst._chat_messages = [Message(role="user", content="hi"), Message(role="assistant", content="hello there")]
+    inst.chat_metadata = {"title": "Greeting"}
+    path = tmp_path / "chat.json"
+    assert save_transcript(inst, str(path)) == 2
+
+    data = json.loads(path.read_text(encoding="utf-8"))
+    turns = load_turns(data)
+    assert [t.role for t in turns] == ["user", "assistant"]
+    assert format_turn(turns[1], 1, 2).startswith("--- 2/2 assistant ---")
+
+    answers = iter(["n", "p", "q"])
+    view(data, read=lambda prompt: next(answers))
+
+    trace = {"tool_calls": [{"name": "add", "args": {"a": 1}}], "tool_results": [{"name": "add", "output": 2, "error": None}]}
+    assert [(t.role, t.content) for t in load_turns(trace)] == [("assistant", 'add({"a": 1})'), ("tool", "2")]
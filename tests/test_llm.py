@@ -1,10 +1,12 @@
 import json
+import time
 
-from bp_agent.llm import LLMRouter, CompletionRequest, Message, LLMResponse
+from bp_agent.llm import LLMRouter, CompletionRequest, Message, LLMResponse, ChaosConfig, ProviderError
 from bp_agent.llm.rotation import RotationManager, RotationPolicy, RotationSlot
 from bp_agent.llm.gemini_adapter import GeminiAdapter, GeminiConfig
 from bp_agent.llm.opus_adapter import OpusAdapter, OpusConfig
-from bp_agent.llm.types import StreamChunk, ToolCallDelta, accumulate_stream
+from bp_agent.llm.types import StreamChunk, ToolCall, ToolCallDelta, accumulate_stream, tool_message
+from bp_agent.tools import ToolSchema
 
 
 def test_router_requires_provider():
@@ -28,10 +30,76 @@ def test_rotation_select_round_robin():
     assert slot2.id in ["a", "b"]
 
 
+def test_rotation_cools_down_failing_keys_and_reports_stats():
+    now = [1000.0]
+    policy = RotationPolicy(cooldown_seconds=10, max_cooldown_seconds=15, failure_threshold=2)
+    mgr = RotationManager(policy=policy, clock=lambda: now[0])
+    for name in ("a", "b", "c"):
+        mgr.add_slot(RotationSlot(id=name, fingerprint=name))
+
+    mgr.report_error("a", "rate_limit", "429")
+    mgr.report_error("b", "server_error", "502")
+    mgr.report_error("c", "auth_error")
+    mgr.report_error("b", "api_error", "bad request")  # not the key's fault
+    assert {mgr.select_slot().id for _ in range(3)} == {"b"}
+    mgr.report_error("b", "network_error", "reset")  # second in a row
+    try:
+        mgr.select_slot()
+        assert False, "Expected ProviderError"
+    except ProviderError as exc:
+        assert (exc.code, exc.retryable) == ("rate_limit", True) and "10s" in exc.message
+
+    now[0] += 10
+    assert mgr.select_slot().id in ("a", "b")  # both back; c stays disabled
+    mgr.report_rate_limit("a")
+    stats = {entry["fingerprint"]: entry for entry in mgr.stats()}
+    assert stats["a"]["state"] == "cooldown" and stats["a"]["cooldown_remaining"] == 15  # doubled, capped
+    assert stats["a"]["rate_limits"] == 2 and stats["a"]["consecutive_failures"] == 2
+    assert stats["b"]["failures"] == 2 and stats["a"]["requests"] + stats["b"]["requests"] == 4
+    assert stats["c"]["state"] == "disabled" and stats["c"]["auth_failures"] == 1
+
+    mgr.report_success("b")
+    stats = {entry["fingerprint"]: entry for entry in mgr.stats()}
+    assert stats["b"]["consecutive_failures"] == 0 and stats["b"]["last_success"] == now[0]
+    assert "secret" not in stats["a"]
+
+
+def test_router_rotates_keys_live():
+    from bp_agent.llm import key_fingerprint
+
+    router = LLMRouter(default_provider="opus")
+    adapter = OpusAdapter(OpusConfig(api_keys=["old-key"], base_url="https://proxy", endpoint="/responses"))
+    router.register_provider("opus", adapter)
+    used = []
+
+    def fake_send(payload, api_key, timeout=None):
+        used.append(api_key)
+        if len(used) == 1:
+            # removed while this request is in flight
+            assert router.remove_key("opus", key_fingerprint("old-key"))
+        return {"output_text": "ok"}
+
+    adapter._send_request = fake_send
+    new = router.add_key("opus", "new-key")
+    request = CompletionRequest(messages=[Message(role="user", content="Hi")])
+    for _ in range(4):
+        assert router.complete(request).content == "ok"
+
+    assert used == ["old-key", "new-key", "new-key", "new-key"]
+    assert router.keys("opus") == [{"fingerprint": new, "state": "healthy", "last_error": None}]
+    assert not router.remove_key("opus", "unknown")
+    router.register_provider("local", object())
+    try:
+        router.add_key("local", "k")
+        assert False, "Expected ValueError"
+    except ValueError:
+        pass
+
+
 def test_gemini_adapter_response_parsing():
     adapter = GeminiAdapter(GeminiConfig(api_keys=["k1"]))
 
-    def fake_send_request(payload, model, api_key):
+    def fake_send_request(payload, model, api_key, timeout=None):
         assert api_key == "k1"
         return {
             "candidates": [
@@ -55,7 +123,7 @@ def _make_opus_adapter():
 def test_opus_text_only_response():
     adapter = _make_opus_adapter()
 
-    def fake_send(payload, api_key):
+    def fake_send(payload, api_key, timeout=None):
         return {"output_text": "Hello from Opus!"}
 
     adapter._send_request = fake_send
@@ -69,7 +137,7 @@ def test_opus_text_only_response():
 def test_opus_tool_call_response():
     adapter = _make_opus_adapter()
 
-    def fake_send(payload, api_key):
+    def fake_send(payload, api_key, timeout=None):
         return {
             "output": [
                 {
@@ -93,7 +161,7 @@ def test_opus_tool_call_response():
 def test_opus_mixed_text_and_tool_call():
     adapter = _make_opus_adapter()
 
-    def fake_send(payload, api_key):
+    def fake_send(payload, api_key, timeout=None):
         return {
             "output": [
                 {
@@ -117,7 +185,7 @@ def test_opus_mixed_text_and_tool_call():
 def test_opus_arguments_as_json_string():
     adapter = _make_opus_adapter()
 
-    def fake_send(payload, api_key):
+    def fake_send(payload, api_key, timeout=None):
         return {
             "output": [
                 {
@@ -139,7 +207,7 @@ def test_opus_arguments_as_json_string():
 def test_opus_fallback_text_field():
     adapter = _make_opus_adapter()
 
-    def fake_send(payload, api_key):
+    def fake_send(payload, api_key, timeout=None):
         return {"text": "fallback text"}
 
     adapter._send_request = fake_send
@@ -213,3 +281,738 @@ def test_router_complete_stream_fallback():
     assert len(chunks) == 1
     assert chunks[0].delta == "fallback response"
     assert chunks[0].finish_reason == "stop"
+
+
+def test_router_shadow_mirrors_requests():
+    class EchoAdapter:
+        def __init__(self, name):
+            self.name = name
+            self.requests = []
+
+        def complete(self, request):
+            self.requests.append(request)
+            return LLMResponse(content=f"{self.name}:{request.model}")
+
+    primary, candidate = EchoAdapter("primary"), EchoAdapter("candidate")
+    router = LLMRouter(default_provider="primary")
+    router.register_provider("primary", primary)
+    router.register_provider("candidate", candidate)
+    seen = []
+    router.set_shadow("candidate", model="new-model", rate=1.0, on_result=seen.append)
+
+    response = router.complete(CompletionRequest(messages=[Message(role="user", content="Hi")], model="old"))
+    router.flush_shadow(timeout=5)
+
+    assert response.content == "primary:old"
+    assert candidate.requests[0].model == "new-model"
+    assert seen[0].shadow_content == "candidate:new-model"
+    assert seen[0].primary_content == "primary:old"
+    assert list(router.shadow_results) == seen
+
+    router.clear_shadow()
+    router.complete(CompletionRequest(messages=[Message(role="user", content="Hi")]))
+    assert len(candidate.requests) == 1
+
+
+# --- Tokenizer tests ---
+
+def test_model_family():
+    from bp_agent.llm.tokenizer import model_family
+
+    assert model_family("gpt-5.2-codex") == "openai"
+    assert model_family("gemini-3-pro-preview") == "gemini"
+    assert model_family("claude-opus") == "anthropic"
+    assert model_family(None) == "default"
+
+
+def test_count_tokens_approximation():
+    from bp_agent.llm.tokenizer import count_tokens, count_message_tokens
+
+    assert count_tokens("gemini-3-flash-preview", "") == 0
+    assert count_tokens("gemini-3-flash-preview", "Hello world") == 2
+    long_text = "The quick brown fox jumps over the lazy dog. " * 20
+    approx = count_tokens("gemini-3-flash-preview", long_text)
+    assert 150 <= approx <= 300
+    assert count_tokens("gemini-3-flash-preview", "日本語") == 3
+
+    messages = [Message(role="user", content="Hello world")]
+    assert count_message_tokens("gemini-3-flash-preview", messages) == 6
+
+
+def test_router_rejects_unsupported_model_requests():
+    from bp_agent.llm import ModelCapabilities, ProviderError, register_model
+    from bp_agent.llm.capabilities import MODEL_CAPABILITIES
+
+    class SimpleAdapter:
+        def complete(self, request):
+            return LLMResponse(content="ok")
+
+    router = LLMRouter(default_provider="test")
+    router.register_provider("test", SimpleAdapter())
+    register_model("tiny-model", ModelCapabilities(context_window=100, max_output_tokens=50, supports_tools=False))
+    try:
+        tools_request = CompletionRequest(messages=[Message(role="user", content="Hi")], model="tiny-model", tools=[object()])
+        try:
+            router.complete(tools_request)
+            assert False, "Expected ProviderError"
+        except ProviderError as exc:
+            assert exc.code == "unsupported"
+
+        long_request = CompletionRequest(messages=[Message(role="user", content="word " * 200)], model="tiny-model")
+        try:
+            router.complete(long_request)
+            assert False, "Expected ProviderError"
+        except ProviderError as exc:
+            assert exc.code == "context_overflow"
+
+        ok = CompletionRequest(messages=[Message(role="user", content="Hi")], model="tiny-model")
+        assert router.complete(ok).content == "ok"
+    finally:
+        MODEL_CAPABILITIES.pop("tiny-model")
+
+
+def test_router_chaos_injects_latency_and_retryable_errors():
+    class OkAdapter:
+        calls = 0
+
+        def complete(self, request):
+            OkAdapter.calls += 1
+            return LLMResponse(content="ok")
+
+    router = LLMRouter(default_provider="ok")
+    router.register_provider("ok", OkAdapter())
+    (chaos,) = router.enable_chaos(ChaosConfig(latency_ms=100, rate_limit_rate=0.5, seed=7)).values()
+    slept = []
+    chaos._sleep = slept.append
+
+    codes = []
+    for _ in range(40):
+        try:
+            router.complete(CompletionRequest(messages=[Message(role="user", content="Hi")]))
+        except ProviderError as exc:
+            assert exc.retryable
+            codes.append(exc.code)
+
+    assert set(codes) == {"rate_limit"}
+    assert 5 < len(codes) < 35
+    assert OkAdapter.calls == 40 - len(codes)
+    assert slept == [0.1] * 40
+    assert ChaosConfig.from_spec("error_rate=0.2, seed=3") == ChaosConfig(error_rate=0.2, seed=3)
+
+    router.disable_chaos()
+    assert router.complete(CompletionRequest(messages=[Message(role="user", content="Hi")])).content == "ok"
+
+
+def test_router_capture_keeps_last_exchanges_redacted():
+    class EchoAdapter:
+        def complete(self, request):
+            if request.messages[-1].content == "boom":
+                raise ProviderError("server_error", "failed with key AIzaSyD-0123456789abcdefghijklmnop", retryable=True)
+            return LLMResponse(content="hi", raw={"api_key": "secret", "text": "hi", "user": "alice"})
+
+        def complete_stream(self, request):
+            yield StreamChunk(delta="he")
+            yield StreamChunk(delta="llo", finish_reason="stop")
+
+    router = LLMRouter(default_provider="echo")
+    router.register_provider("echo", EchoAdapter())
+    router.complete(CompletionRequest(messages=[Message(role="user", content="not captured")]))
+    capture = router.enable_capture(size=3, redact_fields=["user"])
+
+    router.complete(CompletionRequest(
+        messages=[Message(role="user", content="Bearer abcdefghijklmnop")], metadata={"authorization": "x"},
+    ))
+    try:
+        router.complete(CompletionRequest(messages=[Message(role="user", content="boom")]))
+    except ProviderError:
+        pass
+    assert "".join(c.delta for c in router.complete_stream(
+        CompletionRequest(messages=[Message(role="user", content="stream")])
+    )) == "hello"
+
+    ok, failed, streamed = capture.to_list()
+    assert ok["request"]["messages"][0]["content"] == "[redacted]"
+    assert ok["request"]["metadata"] == {"authorization": "[redacted]"}
+    assert ok["response"] == {"api_key": "[redacted]", "text": "hi", "user": "[redacted]"}
+    assert failed["error"].startswith("ProviderError") and "AIza" not in failed["error"]
+    assert failed["request"]["messages"][0]["content"] == "boom"
+    assert streamed["stream"] and streamed["response"]["content"] == "hello" and streamed["error"] is None
+
+    router.complete(CompletionRequest(messages=[Message(role="user", content="fourth")]))
+    assert len(capture.entries()) == 3 and capture.entries(1)[0].request["messages"][0]["content"] == "fourth"
+
+
+def test_stream_tool_args_are_repaired_and_raw_kept():
+    stream = iter([
+        StreamChunk(tool_call_delta=ToolCallDelta(index=0, name="write_file")),
+        StreamChunk(tool_call_delta=ToolCallDelta(index=0, args_delta="```json\n{'path': 'a.txt', ")),
+        StreamChunk(tool_call_delta=ToolCallDelta(index=0, args_delta="content: \"it's\", overwrite: True,}\n```")),
+        StreamChunk(tool_call_delta=ToolCallDelta(index=1, name="bash", args_delta='{"cmd": "ls"}')),
+        StreamChunk(tool_call_delta=ToolCallDelta(index=2, name="bash", args_delta="not json")),
+    ])
+    repaired, clean, broken = accumulate_stream(stream).tool_calls
+
+    assert repaired.args == {"path": "a.txt", "content": "it's", "overwrite": True}
+    assert repaired.raw_args.startswith("```json")
+    assert clean.args == {"cmd": "ls"} and clean.raw_args is None
+    assert broken.args == {} and broken.raw_args == "not json"
+
+
+def test_adapters_serialize_assistant_tool_calls_and_results():
+    from bp_agent.llm.codex_adapter import CodexAdapter, CodexConfig
+
+    call = ToolCall(name="add", args={"a": 1}, id="call_1")
+    messages = [
+        Message(role="user", content="Add"),
+        Message(role="assistant", content="", tool_calls=[call]),
+        tool_message(call, "Tool add returned: 2"),
+    ]
+    request = CompletionRequest(messages=messages)
+
+    contents = GeminiAdapter(GeminiConfig(api_keys=["k1"]))._build_request(request, 0.3)["contents"]
+    assert contents[1] == {"role": "model", "parts": [{"functionCall": {"name": "add", "args": {"a": 1}, "id": "call_1"}}]}
+    assert contents[2]["parts"][0]["functionResponse"]["name"] == "add"
+
+    items = CodexAdapter(CodexConfig(api_keys=["k1"]))._build_payload(request, "gpt-5-codex")["input"]
+    assert items[1] == {"type": "function_call", "call_id": "call_1", "name": "add", "arguments": '{"a": 1}'}
+    assert items[2] == {"type": "function_call_output", "call_id": "call_1", "output": "Tool add returned: 2"}
+
+    payload = _make_opus_adapter()._build_payload(request)["messages"]
+    assert payload[1]["tool_calls"][0]["function"]["name"] == "add"
+    assert payload[2] == {"role": "tool", "tool_call_id": "call_1", "content": "Tool add returned: 2"}
+
+    assert Message.from_dict(messages[1].to_dict()) == messages[1]
+
+
+def test_multiple_system_and_developer_messages_are_all_sent():
+    from bp_agent.llm.codex_adapter import CodexAdapter, CodexConfig
+
+    request = CompletionRequest(messages=[
+        Message(role="system", content="You are terse."),
+        Message(role="developer", content="Answer in JSON."),
+        Message(role="system", content="Never guess."),
+        Message(role="user", content="Hi"),
+    ])
+
+    payload = GeminiAdapter(GeminiConfig(api_keys=["k1"]))._build_request(request, 0.3)
+    assert [p["text"] for p in payload["systemInstruction"]["parts"]] == ["You are terse.", "Answer in JSON.", "Never guess."]
+    assert [c["role"] for c in payload["contents"]] == ["user"]
+
+    payload = CodexAdapter(CodexConfig(api_keys=["k1"]))._build_payload(request, "gpt-5-codex")
+    assert payload["instructions"] == "You are terse.\n\nNever guess."
+    assert payload["input"][0] == {"role": "developer", "content": "Answer in JSON."}
+
+    roles = [m["role"] for m in _make_opus_adapter()._build_payload(request)["messages"]]
+    assert roles == ["system", "system", "system", "user"]
+
+
+def test_logprobs_requested_and_parsed():
+    request = CompletionRequest(messages=[Message(role="user", content="Hi")], logprobs=True, top_logprobs=2)
+    adapter = GeminiAdapter(GeminiConfig(api_keys=["k1"]))
+    assert adapter._build_request(request, 0.3)["generationConfig"]["responseLogprobs"] is True
+
+    response = adapter._parse_response({"candidates": [{
+        "content": {"parts": [{"text": "Hello"}]},
+        "logprobsResult": {"chosenCandidates": [{"token": "Hel", "logProbability": -0.1}, {"token": "lo", "logProbability": -0.3}]},
+    }]})
+    assert response.logprobs == [-0.1, -0.3]
+    assert 0.81 < response.confidence < 0.82
+
+    opus = _make_opus_adapter()
+    assert opus._build_payload(request)["logprobs"] is True
+    parsed = opus._parse_response({"output": [{"content": [{"type": "output_text", "text": "Hi", "logprobs": [{"logprob": 0.0}]}]}]})
+    assert parsed.confidence == 1.0
+    assert LLMResponse(content="x").confidence is None
+
+
+def test_codex_adapter_round_trip(monkeypatch, tmp_path):
+    from bp_agent.llm.codex_adapter import CodexAdapter, CodexConfig
+    from bp_agent.tools import build_schema
+
+    auth = tmp_path / "auth.json"
+    auth.write_text(json.dumps({"tokens": {"access_token": "tok", "account_id": "acct-1"}}))
+    adapter = CodexAdapter(CodexConfig(auth_files=[str(auth)], reasoning_effort="high"))
+    sent = []
+
+    def fake_send(payload, cred, timeout=None):
+        sent.append((payload, adapter._headers(cred), cred["base_url"]))
+        return {"output": [
+            {"type": "reasoning", "summary": []},
+            {"type": "message", "content": [{"type": "output_text", "text": "Listing."}]},
+            {"type": "function_call", "call_id": "call_9", "name": "bash", "arguments": '{"command": "ls"}'},
+        ]}
+
+    monkeypatch.setattr(adapter, "_send_request", fake_send)
+    schema = build_schema("bash", "Run a command", command={"type": "string", "required": True})
+    response = adapter.complete(CompletionRequest(
+        messages=[Message(role="user", content="ls")], tools=[schema], temperature=0.3, model="gpt-5-codex",
+    ))
+
+    assert response.content == "Listing."
+    assert response.tool_calls == [ToolCall(name="bash", args={"command": "ls"}, id="call_9")]
+    payload, headers, base_url = sent[0]
+    assert payload["tools"][0]["name"] == "bash" and "function" not in payload["tools"][0]
+    assert payload["reasoning"] == {"effort": "high"} and "temperature" not in payload
+    assert headers["Authorization"] == "Bearer tok" and headers["chatgpt-account-id"] == "acct-1"
+    assert base_url == "https://chatgpt.com/backend-api/codex"
+
+
+def test_codex_adapter_uses_requests_and_reports_network_failures(monkeypatch):
+    import requests
+
+    from bp_agent.llm import codex_adapter
+    from bp_agent.llm.codex_adapter import CodexAdapter, CodexConfig
+
+    adapter = CodexAdapter(CodexConfig(api_keys=["k1"]), rotation=RotationManager(RotationPolicy(max_retries=0)))
+    request = CompletionRequest(messages=[Message(role="user", content="Hi")])
+    sent = []
+
+    class FakeResponse:
+        status_code, text = 200, ""
+
+        def json(self):
+            return {"output_text": "hello"}
+
+    def fake_post(url, **kwargs):
+        sent.append((url, kwargs.get("stream", False)))
+        if kwargs.get("stream"):
+            raise requests.ConnectionError("connection reset")
+        return FakeResponse()
+
+    monkeypatch.setattr(codex_adapter.http_requests, "post", fake_post)
+    assert adapter.complete(request).content == "hello"
+    try:
+        adapter.complete_stream(request)
+        assert False, "Expected ProviderError"
+    except ProviderError as exc:
+        assert exc.code == "network_error" and exc.retryable
+    assert sent == [("https://api.openai.com/v1/responses", False), ("https://api.openai.com/v1/responses", True)]
+    [slot] = adapter.rotation.slots()
+    assert slot.failures == 1 and slot.last_error == "connection reset"
+
+
+def test_openai_stream_reports_failures_and_closes_error_responses(monkeypatch):
+    import requests
+
+    from bp_agent.llm import openai_adapter
+    from bp_agent.llm.openai_adapter import OpenAIAdapter, OpenAIConfig
+
+    adapter = OpenAIAdapter(OpenAIConfig(api_keys=["k1"]))
+    request = CompletionRequest(messages=[Message(role="user", content="Hi")])
+    closed = []
+
+    class FakeResponse:
+        status_code, text = 503, "overloaded"
+
+        def close(self):
+            closed.append(True)
+
+    replies = [requests.ConnectionError("connection reset"), FakeResponse()]
+
+    def fake_post(url, **kwargs):
+        reply = replies.pop(0)
+        if isinstance(reply, Exception):
+            raise reply
+        return reply
+
+    monkeypatch.setattr(openai_adapter.http_requests, "post", fake_post)
+    for code in ("network_error", "server_error"):
+        try:
+            adapter.complete_stream(request)
+            assert False, "Expected ProviderError"
+        except ProviderError as exc:
+            assert exc.code == code
+    [slot] = adapter.rotation.slots()
+    assert slot.failures == 2 and closed == [True]
+
+
+def test_opus_anthropic_messages_format():
+    from dataclasses import replace
+
+    from bp_agent.errors import ConfigError
+
+    call = ToolCall(name="add", args={"a": 1}, id="toolu_1")
+    adapter = OpusAdapter(OpusConfig(api_keys=["k1"], base_url="https://api.anthropic.com/v1", endpoint="/messages"))
+    request = CompletionRequest(
+        messages=[
+            Message(role="system", content="Be terse."),
+            Message(role="developer", content="Use tools."),
+            Message(role="user", content="Add"),
+            Message(role="assistant", content="", tool_calls=[call]),
+            tool_message(call, "2"),
+            Message(role="user", content="Thanks"),
+        ],
+        tools=[ToolSchema(name="add", description="Add", parameters={"type": "object"})],
+        model="claude-opus-4",
+    )
+
+    assert OpusConfig(api_keys=["k1"], base_url="https://api.anthropic.com/v1").anthropic  # the default endpoint
+
+    payload = adapter._build_payload(request)
+    assert payload["system"] == [{"type": "text", "text": "Be terse."}, {"type": "text", "text": "Use tools."}]
+    assert [m["role"] for m in payload["messages"]] == ["user", "assistant", "user"]
+    assert payload["messages"][1]["content"] == [{"type": "tool_use", "id": "toolu_1", "name": "add", "input": {"a": 1}}]
+    assert payload["messages"][2]["content"][0] == {"type": "tool_result", "tool_use_id": "toolu_1", "content": "2"}
+    assert payload["messages"][2]["content"][1] == {"type": "text", "text": "Thanks"}
+    assert payload["tools"] == [{"name": "add", "description": "Add", "input_schema": {"type": "object"}}]
+    assert payload["max_tokens"] == 8192
+
+    adapter._send_request = lambda payload, api_key, timeout=None: {
+        "type": "message",
+        "content": [
+            {"type": "text", "text": "Adding."},
+            {"type": "tool_use", "id": "toolu_2", "name": "add", "input": {"a": 2}},
+        ],
+        "stop_reason": "tool_use",
+    }
+    response = adapter.complete(request)
+    assert response.content == "Adding."
+    assert response.tool_calls == [ToolCall(name="add", args={"a": 2}, id="toolu_2")]
+
+    # Empty turns are dropped rather than sent as empty text blocks
+    sparse = CompletionRequest(
+        messages=[Message(role="user", content="Hi"), Message(role="assistant", content=""), Message(role="user", content="")],
+        model="claude-opus-4",
+    )
+    assert adapter._build_payload(sparse)["messages"] == [{"role": "user", "content": [{"type": "text", "text": "Hi"}]}]
+    try:
+        adapter._build_payload(replace(sparse, model=None))
+        assert False, "Expected ConfigError"
+    except ConfigError as exc:
+        assert "model" in str(exc)
+
+
+def test_openai_adapter_chat_completions(monkeypatch):
+    from bp_agent.llm import OpenAIAdapter, OpenAIConfig
+    from bp_agent.llm import openai_adapter
+
+    call = ToolCall(name="add", args={"a": 1}, id="call_1")
+    request = CompletionRequest(
+        messages=[
+            Message(role="system", content="Be terse."),
+            Message(role="user", content="Add"),
+            Message(role="assistant", content="", tool_calls=[call]),
+            tool_message(call, "2"),
+        ],
+        tools=[ToolSchema(name="add", description="Add", parameters={"type": "object"})],
+        temperature=0.2,
+    )
+    adapter = OpenAIAdapter(OpenAIConfig(base_url="http://localhost:8000/v1", model="qwen3"))
+    assert "Authorization" not in adapter._headers("")
+
+    payload = adapter._build_payload(request)
+    assert payload["model"] == "qwen3" and payload["temperature"] == 0.2
+    assert payload["messages"][2]["tool_calls"][0]["function"] == {"name": "add", "arguments": '{"a": 1}'}
+    assert payload["messages"][3] == {"role": "tool", "tool_call_id": "call_1", "content": "2"}
+    assert payload["tools"][0]["function"]["name"] == "add"
+
+    # developer stays a developer message for OpenAI itself, and becomes system for compatible servers
+    instructed = CompletionRequest(messages=[Message(role="developer", content="Use tools.")])
+    assert adapter._build_payload(instructed)["messages"][0]["role"] == "system"
+    native = OpenAIAdapter(OpenAIConfig(api_keys=["k"], model="o3"))
+    assert native._build_payload(instructed)["messages"][0] == {"role": "developer", "content": "Use tools."}
+    forced = OpenAIAdapter(OpenAIConfig(base_url="http://localhost:8000/v1", developer_role=True))
+    assert forced._build_payload(instructed)["messages"][0]["role"] == "developer"
+
+    adapter._send_request = lambda payload, api_key, timeout=None: {"choices": [{
+        "message": {"role": "assistant", "content": None, "tool_calls": [
+            {"id": "call_2", "type": "function", "function": {"name": "add", "arguments": '{"a": 2}'}},
+        ]},
+        "finish_reason": "tool_calls",
+    }]}
+    assert adapter.complete(request).tool_calls == [ToolCall(name="add", args={"a": 2}, id="call_2")]
+
+    class FakeResponse:
+        status_code = 200
+
+        def iter_lines(self, decode_unicode=True):
+            yield 'data: {"choices": [{"delta": {"content": "Sum"}}]}'
+            yield 'data: {"choices": [{"delta": {"tool_calls": [{"index": 0, "id": "call_3", "function": {"name": "add", "arguments": "{\\"a\\""}}]}}]}'
+            yield 'data: {"choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": ": 3}"}}]}}]}'
+            yield 'data: {"choices": [{"delta": {}, "finish_reason": "tool_calls"}]}'
+            yield "data: [DONE]"
+
+        def close(self):
+            pass
+
+    monkeypatch.setattr(openai_adapter.http_requests, "post", lambda *args, **kwargs: FakeResponse())
+    streamed = accumulate_stream(adapter.complete_stream(request))
+    assert streamed.content == "Sum"
+    assert streamed.tool_calls == [ToolCall(name="add", args={"a": 3}, id="call_3")]
+    assert OpenAIAdapter._status_error(429, '{"error": {"code": "insufficient_quota"}}').code == "quota"
+
+
+def test_ollama_adapter_payload_models_and_errors():
+    from bp_agent.llm import OllamaAdapter, OllamaConfig
+    from bp_agent.llm.ollama_adapter import normalize_base_url
+
+    call = ToolCall(name="add", args={"a": 1}, id=None)
+    adapter = OllamaAdapter(OllamaConfig(model="qwen3", models=["qwen3", "llama3.2"], keep_alive="10m"))
+    payload = adapter._build_payload(CompletionRequest(
+        messages=[
+            Message(role="developer", content="Use tools."),
+            Message(role="assistant", content="", tool_calls=[call]),
+            tool_message(call, "2"),
+        ],
+        tools=[ToolSchema(name="add", description="Add", parameters={"type": "object"})],
+    ))
+    assert payload["model"] == "qwen3" and payload["options"] == {"temperature": 0.3} and payload["keep_alive"] == "10m"
+    assert payload["messages"][0]["role"] == "system"
+    assert payload["messages"][1]["tool_calls"] == [{"function": {"name": "add", "arguments": {"a": 1}}}]
+    assert payload["messages"][2] == {"role": "tool", "content": "2", "tool_name": "add"}
+
+    try:
+        adapter._build_payload(CompletionRequest(messages=[], model="mistral"))
+        assert False, "Expected ProviderError"
+    except ProviderError as exc:
+        assert exc.code == "invalid_model"
+    missing = OllamaAdapter._status_error(404, '{"error": "model \\"mistral\\" not found, try pulling it first"}')
+    assert missing.code == "invalid_model" and not missing.retryable
+    assert normalize_base_url("0.0.0.0:11434/") == "http://0.0.0.0:11434"
+
+
+def test_provider_fixtures_replay_through_parsers(tmp_path):
+    from bp_agent.llm import check_fixtures, load_fixtures, register_fixture, register_parser
+    from bp_agent.llm import fixtures
+
+    builtin = check_fixtures()
+    assert {r.fixture.provider for r in builtin} == {"gemini", "codex", "opus", "openai", "ollama"}
+    assert [r.errors for r in builtin if not r.ok] == []
+
+    (tmp_path / "mine.json").write_text(json.dumps([
+        {"name": "mine-ok", "provider": "echo", "response": {"text": "hi"}, "expected": {"content": "hi"}},
+        {"name": "mine-drift", "provider": "gemini", "response": {"candidates": []}, "expected": {"content": "hi"}},
+    ]))
+    register_parser("echo", lambda body: LLMResponse(content=body["text"]))
+    loaded = load_fixtures(tmp_path, register=True)
+    try:
+        results = {r.fixture.name: r for r in check_fixtures()}
+        assert results["mine-ok"].ok and results["mine-ok"].fixture.source.endswith("mine.json")
+        assert results["mine-drift"].errors == ["content: expected 'hi', got ''"]
+        register_fixture({"name": "mine-ok", "provider": "nobody", "response": {}, "expected": {}})
+        assert "No parser registered" in check_fixtures(fixtures.registered_fixtures())[0].errors[0]
+    finally:
+        fixtures._PARSERS.pop("echo", None)
+        for fixture in loaded:
+            fixtures._FIXTURES.pop(fixture.name, None)
+
+
+def test_gemini_stream_yields_function_calls_and_router_fallback_keeps_them():
+    class FakeResponse:
+        def iter_lines(self, decode_unicode=True):
+            yield 'data: {"candidates": [{"content": {"parts": [{"text": "Checking"}]}}]}'
+            yield 'data: {"candidates": [{"content": {"parts": [{"functionCall": {"name": "bash", "args": {"command": "ls"}}}, {"functionCall": {"name": "list_dir", "args": {}}}]}}]}'
+
+        def close(self):
+            pass
+
+    adapter = GeminiAdapter(GeminiConfig(api_keys=["k1"]))
+    response = accumulate_stream(adapter._iter_sse(FakeResponse()))
+    assert response.content == "Checking"
+    assert [(c.name, c.args) for c in response.tool_calls] == [("bash", {"command": "ls"}), ("list_dir", {})]
+
+    class BlockingAdapter:
+        def complete(self, request):
+            return LLMResponse(content="", tool_calls=[ToolCall(name="bash", args={"command": "pwd"}, id="c1")])
+
+    router = LLMRouter()
+    router.register_provider("plain", BlockingAdapter())
+    streamed = accumulate_stream(router.complete_stream(CompletionRequest(messages=[Message(role="user", content="x")], provider="plain")))
+    assert streamed.tool_calls == [ToolCall(name="bash", args={"command": "pwd"}, id="c1")]
+
+
+def test_router_retries_transient_errors_within_budget():
+    from bp_agent.llm import RetryPolicy
+
+    class FlakyAdapter:
+        def __init__(self):
+            self.failures = []
+
+        def complete(self, request):
+            if self.failures:
+                raise ProviderError(self.failures.pop(0), "temporary", retryable=True)
+            return LLMResponse(content="ok")
+
+    adapter = FlakyAdapter()
+    router = LLMRouter(default_provider="flaky")
+    router.register_provider("flaky", adapter)
+    request = CompletionRequest(messages=[Message(role="user", content="Hi")])
+    slept = []
+    retrier = router.set_retry_policy(RetryPolicy(max_attempts=3, backoff_base=1, jitter=0, budget=3), sleep=slept.append)
+
+    adapter.failures = ["rate_limit", "server_error"]
+    assert router.complete(request).content == "ok" and slept == [1, 2]
+
+    adapter.failures = ["network_error"] * 3
+    try:
+        router.complete(request)
+        assert False, "Expected ProviderError"
+    except ProviderError:
+        pass
+    assert adapter.failures == ["network_error"]  # budget of 3 retries spent: third attempt never made
+    assert retrier.retries == 3 and retrier.exhausted == 1
+
+    adapter.failures = ["invalid_model"]
+    router.set_retry_policy(RetryPolicy(jitter=0), sleep=slept.append)
+    try:
+        router.complete(request)
+        assert False, "Expected ProviderError"
+    except ProviderError as exc:
+        assert exc.code == "invalid_model"
+    assert "".join(c.delta or "" for c in router.complete_stream(request)) == "ok"
+
+    assert RetryPolicy.from_spec("max_attempts=5, budget=none") == RetryPolicy(max_attempts=5, budget=None)
+
+
+def test_acomplete_retries_captures_and_shadows_async_adapters():
+    import asyncio
+
+    from bp_agent.llm import RetryPolicy
+
+    class AsyncAdapter:
+        def __init__(self):
+            self.failures = ["rate_limit"]
+
+        def complete(self, request):
+            return LLMResponse(content="sync " + (request.model or ""))
+
+        async def acomplete(self, request):
+            if self.failures:
+                raise ProviderError(self.failures.pop(0), "temporary", retryable=True)
+            return LLMResponse(content="async")
+
+    router = LLMRouter(default_provider="primary")
+    router.register_provider("primary", AsyncAdapter())
+    router.register_provider("candidate", AsyncAdapter())
+    slept, shadows = [], []
+    router.set_retry_policy(RetryPolicy(max_attempts=3, backoff_base=1, jitter=0), sleep=slept.append)
+    capture = router.enable_capture()
+    router.set_shadow("candidate", model="new-model", rate=1.0, on_result=shadows.append)
+
+    response = asyncio.run(router.acomplete(CompletionRequest(messages=[Message(role="user", content="Hi")])))
+    router.flush_shadow(timeout=5)
+    assert response.content == "async" and slept == [1]
+    assert [entry.error is None for entry in capture.entries()] == [False, True]
+    assert [(r.primary_content, r.shadow_content) for r in shadows] == [("async", "sync new-model")]
+
+
+def test_request_timeout_covers_all_retries():
+    from bp_agent.llm import RetryPolicy
+    from bp_agent.llm.types import call_timeout, request_deadline
+
+    class SlowAdapter:
+        def __init__(self):
+            self.timeouts = []
+
+        def complete(self, request):
+            timeout = call_timeout(request_deadline(request), 30)
+            self.timeouts.append(timeout)
+            time.sleep(min(timeout, 0.2))  # a server that never answers in time
+            raise ProviderError("network_error", "timed out", retryable=True)
+
+    adapter = SlowAdapter()
+    router = LLMRouter(default_provider="slow")
+    router.register_provider("slow", adapter)
+    router.set_retry_policy(RetryPolicy(max_attempts=5, backoff_base=0, jitter=0, budget=None))
+    request = CompletionRequest(messages=[Message(role="user", content="Hi")], timeout=0.3)
+
+    started = time.time()
+    try:
+        router.complete(request)
+        assert False, "Expected ProviderError"
+    except ProviderError:
+        pass
+    # Both slow attempts share the 0.3s window: the second only gets what the first left over
+    assert len(adapter.timeouts) == 2 and adapter.timeouts[1] < 0.15
+    assert time.time() - started < 0.4
+    assert request.deadline is None  # the caller's request is not modified
+
+
+def test_model_catalog_serves_stale_lists_while_refreshing(monkeypatch):
+    from bp_agent.llm import ModelCatalog
+    from bp_agent.llm import gemini_adapter
+
+    class FakeResponse:
+        status_code = 200
+
+        def __init__(self, data):
+            self.data = data
+
+        def json(self):
+            return self.data
+
+    pages = {
+        None: {"models": [{"name": "models/gemini-3-flash-preview", "supportedGenerationMethods": ["generateContent"]},
+                          {"name": "models/text-embedding-004", "supportedGenerationMethods": ["embedContent"]}],
+               "nextPageToken": "p2"},
+        "p2": {"models": [{"name": "models/gemini-3-pro-preview", "supportedGenerationMethods": ["generateContent"]},
+                          {"name": "models/gemini-9-future", "supportedGenerationMethods": ["generateContent"]}]},
+    }
+    monkeypatch.setattr(gemini_adapter.requests, "get", lambda url, params, **kw: FakeResponse(pages[params.get("pageToken")]))
+    adapter = GeminiAdapter(GeminiConfig(api_keys=["k1"]))
+    assert adapter.list_models() == ["gemini-3-flash-preview", "gemini-3-pro-preview"]
+
+    import threading
+
+    class Listing:
+        config = GeminiConfig(api_keys=["k"], model="m-default")
+
+        def __init__(self):
+            self.listed, self.calls = ["m1", "m2"], 0
+            self.gate = threading.Event()
+            self.gate.set()
+
+        def complete(self, request):
+            raise NotImplementedError
+
+        def list_models(self):
+            self.gate.wait(5)
+            self.calls += 1
+            if isinstance(self.listed, Exception):
+                raise self.listed
+            return list(self.listed)
+
+    now = [1000.0]
+    router = LLMRouter()
+    live, down = Listing(), Listing()
+    down.listed = ProviderError("network_error", "unreachable", retryable=True)
+    router.register_provider("live", live)
+    router.register_provider("down", down)
+    router.register_provider("plain", OpusAdapter(OpusConfig(api_keys=["k"], base_url="http://x", model="o1")))
+    catalog = ModelCatalog(router, ttl=60, allow={"down": ["d1"]}, clock=lambda: now[0])
+
+    assert catalog.info("live") | {"error": None} == {
+        "models": ["m1", "m2"], "source": "live", "fetched_at": 1000.0, "error": None,
+    }
+    assert catalog.all() == {"live": ["m1", "m2"], "down": ["d1"], "plain": ["o1"]}
+    assert catalog.info("down")["error"] == "ProviderError: unreachable" and down.calls == 1
+
+    live.listed, now[0] = ["m2", "m3"], 1061.0
+    live.gate.clear()
+    stale = catalog.info("live")  # served at once; the refresh runs behind it
+    assert (stale["source"], stale["models"]) == ("stale", ["m1", "m2"])
+    assert catalog.models("live") == ["m1", "m2"]  # one refresh in flight, not one per call
+    live.gate.set()
+    for _ in range(100):
+        if catalog.info("live")["source"] == "live":
+            break
+        time.sleep(0.01)
+    assert catalog.models("live") == ["m2", "m3"] and live.calls == 2
+
+
+def test_url_prefix_patterns_compare_parsed_urls():
+    from bp_agent.llm.egress import url_matches
+
+    allow = ["https://api.github.com", "http://localhost:11434/api/"]
+    assert url_matches("https://api.github.com/repos/x", allow)
+    assert url_matches("https://API.github.com:443", allow)
+    assert url_matches("http://localhost:11434/api", allow)
+    for url in (
+        "https://api.github.com@evil.test/x",  # userinfo: the host is evil.test
+        "https://api.github.com.evil.test/x",
+        "http://api.github.com/repos/x",  # scheme
+        "https://api.github.com:8443/",  # port
+        "http://localhost:11434/apix",  # path prefix only on a "/" boundary
+        "http://localhost/api/tags",
+    ):
+        assert not url_matches(url, allow), url
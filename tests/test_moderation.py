@@ -0,0 +1,73 @@
+import bp_agent.agent as agent
+from bp_agent.agent import Agent, AgentConfig
+from bp_agent.llm import LLMResponse
+from bp_agent.llm.types import StreamChunk
+from bp_agent.moderation import KeywordModerator, ModelModerator
+
+
+class _Router:
+    def __init__(self, replies):
+        self.replies = list(replies)
+        self.calls = []
+
+    def complete(self, request):
+        self.calls.append(request)
+        return LLMResponse(content=self.replies.pop(0))
+
+    def complete_stream(self, request):
+        self.calls.append(request)
+        for word in self.replies.pop(0).split(" "):
+            yield StreamChunk(delta=word + " ")
+        yield StreamChunk(finish_reason="stop")
+
+
+def test_keyword_moderator_whole_words():
+    mod = KeywordModerator({"weapons": ["grenade"], "pii": ["ssn"]})
+    assert mod.check("how to build a grenade").categories == ["weapons"]
+    assert mod.check("classness is fine").flagged is False
+
+
+def test_model_moderator_parses_categories():
+    router = _Router(["UNSAFE: violence, threats", "SAFE"])
+    mod = ModelModerator(router, model="safety-model")
+    assert mod.check("x").categories == ["violence", "threats"]
+    assert mod.check("y").flagged is False
+
+
+def test_execute_blocks_flagged_instruction(monkeypatch):
+    router = _Router([])
+    monkeypatch.setattr(agent, "_build_llm_router", lambda config: router)
+    config = AgentConfig(moderation_policy="block", moderation_keywords={"weapons": ["grenade"]})
+    inst = Agent("test", config=config)
+
+    result = inst.execute("Explain how a grenade works")
+    assert result.success is False
+    assert result.error == "Blocked by moderation: weapons"
+    assert router.calls == []
+
+
+def test_execute_annotates_flagged_output(monkeypatch):
+    router = _Router(["The grenade pin keeps the lever in place."])
+    monkeypatch.setattr(agent, "_build_llm_router", lambda config: router)
+    config = AgentConfig(moderation_policy="annotate", moderation_keywords={"weapons": ["grenade"]})
+    inst = Agent("test", config=config)
+
+    result = inst.execute("How do pins work?")
+    assert result.success is True
+    assert result.output.endswith("[moderation: flagged weapons]")
+    assert result.moderation == {"input": [], "output": ["weapons"]}
+
+
+def test_chat_stream_moderates_the_reply(monkeypatch):
+    router = _Router(["Pull the grenade pin.", "Pull the grenade pin."])
+    monkeypatch.setattr(agent, "_build_llm_router", lambda config: router)
+    keywords = {"weapons": ["grenade"]}
+
+    blocking = Agent("test", config=AgentConfig(moderation_policy="block", moderation_keywords=keywords))
+    assert list(blocking.chat_stream("How do pins work?")) == ["[blocked by moderation: weapons]"]
+    assert blocking.chat_metadata["moderation"] == [{"input": [], "output": ["weapons"]}]
+
+    annotating = Agent("test", config=AgentConfig(moderation_policy="annotate", moderation_keywords=keywords))
+    chunks = list(annotating.chat_stream("How do pins work?"))
+    assert chunks[0] == "Pull " and chunks[-1] == "\n\n[moderation: flagged weapons]"
+    assert annotating.chat_metadata["moderation"] == [{"input": [], "output": ["weapons"]}]
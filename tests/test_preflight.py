@@ -0,0 +1,61 @@
+import bp_agent.agent as agent
+from bp_agent.agent import AgentConfig
+from bp_agent.llm import LLMRouter, LLMResponse, ProviderError
+from bp_agent.runner.preflight import run_preflight
+
+
+class _OkAdapter:
+    def complete(self, request):
+        return LLMResponse(content="OK")
+
+
+class _BadKeyAdapter:
+    def complete(self, request):
+        raise ProviderError("auth_error", "invalid key")
+
+
+def test_preflight_reports_provider_failures(monkeypatch):
+    router = LLMRouter()
+    router.register_provider("gemini", _OkAdapter())
+    router.register_provider("codex", _BadKeyAdapter())
+    monkeypatch.setattr(agent, "_build_llm_router", lambda config: router)
+
+    report = run_preflight(AgentConfig())
+    checks = {c.name: c for c in report.checks}
+
+    assert checks["config"].ok
+    assert checks["provider:gemini"].ok
+    assert not checks["provider:codex"].ok
+    assert "invalid key" in checks["provider:codex"].detail
+    assert checks["tools"].ok
+    assert report.ok is False
+
+
+def test_preflight_missing_credentials(monkeypatch):
+    def failing(config):
+        raise ValueError("No API keys found")
+
+    monkeypatch.setattr(agent, "_build_llm_router", failing)
+    report = run_preflight(AgentConfig())
+    assert not report.ok
+    assert "FAIL" in report.format()
+
+
+def test_diagnose_keys_masks_and_classifies():
+    from bp_agent.llm import ProviderError
+    from bp_agent.runner.preflight import diagnose_keys, format_key_checks, mask_key
+
+    def probe(provider, key):
+        if key.endswith("bad1"):
+            raise ProviderError("auth_error", "API key not valid", retryable=True)
+        if key.endswith("slow"):
+            raise ProviderError("rate_limit", "quota exceeded", retryable=True)
+
+    keys = {"gemini": ["AIzaSyGOOD0000000001", "AIzaSyBAD00000000bad1"], "codex": ["sk-proj-000000000slow"]}
+    checks = diagnose_keys(keys, probe=probe)
+
+    assert [c.status for c in checks] == ["healthy", "invalid", "rate_limited"]
+    assert checks[0].key == mask_key("AIzaSyGOOD0000000001") == "AIza…0001"
+    report = format_key_checks(checks)
+    assert "AIzaSyGOOD" not in report
+    assert "rate_limited" in report
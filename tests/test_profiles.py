@@ -0,0 +1,77 @@
+import json
+
+import pytest
+
+import bp_agent.agent as agent
+from bp_agent.llm import LLMRouter
+from bp_agent.profiles import build_agents, load_profiles
+
+
+def test_load_profiles_and_build(monkeypatch, tmp_path):
+    monkeypatch.setattr(agent, "_build_llm_router", lambda config: LLMRouter())
+    (tmp_path / "coder.txt").write_text("You write code.")
+    path = tmp_path / "agents.json"
+    path.write_text(json.dumps({"agents": [
+        {"name": "coder", "system_prompt_file": "coder.txt", "model": "gemini-3-pro-preview", "max_iterations": 20},
+        {"name": "reader", "system_prompt": "You read.", "toolsets": []},
+    ]}))
+
+    profiles = load_profiles(str(path))
+    assert profiles["coder"].config.max_iterations == 20
+    assert profiles["coder"].system_prompt == "You write code."
+
+    agents = build_agents(str(path))
+    assert agents["coder"].config.model == "gemini-3-pro-preview"
+    assert agents["coder"].tools.has("bash")
+    assert agents["reader"].tools.count() == 0
+
+
+def test_profile_unknown_field(tmp_path):
+    path = tmp_path / "agents.json"
+    path.write_text(json.dumps({"agents": [{"name": "x", "memory": "lots"}]}))
+    with pytest.raises(ValueError, match="unknown fields"):
+        load_profiles(str(path))
+
+
+def test_router_picks_profile_and_records_decision(monkeypatch, tmp_path):
+    from bp_agent.llm import LLMResponse
+    from bp_agent.profiles import build_router
+
+    monkeypatch.setattr(agent, "_build_llm_router", lambda config: LLMRouter())
+    path = tmp_path / "agents.json"
+    path.write_text(json.dumps({"agents": [
+        {"name": "general", "enable_builtin_tools": False},
+        {"name": "coding", "enable_builtin_tools": False},
+        {"name": "research", "enable_builtin_tools": False},
+        {"name": "data-extraction", "description": "Pull fields out of documents", "keywords": ["invoice", "vat"]},
+    ]}))
+    router = build_router(str(path))
+
+    assert router.route("Fix the bug in this Python function").profile == "coding"
+    decision = router.route("Extract the VAT number from this invoice")
+    assert decision.profile == "data-extraction" and decision.scores == {"data-extraction": 2}
+    assert router.route("Hello there").to_dict() == {
+        "profile": "general", "method": "default", "reason": "no keyword matched",
+    }
+
+    class Answer:
+        def __init__(self, text):
+            self.text = text
+
+        def complete(self, request):
+            assert "- data-extraction: Pull fields out of documents" in request.messages[0].content
+            return LLMResponse(content=self.text)
+
+    router.classifier_model, router.llm = "flash-lite", Answer("Research.")
+    assert router.route("Fix the bug").method == "model" and router.route("Fix the bug").profile == "research"
+    router.llm = Answer("no idea")
+    assert router.route("Fix the bug").profile == "coding"
+
+    router.classifier_model = None
+    coding = router.agents["coding"]
+    monkeypatch.setattr(coding, "execute", lambda instruction, **kwargs: agent.AgentResult(
+        success=True, output="done", task_id=coding.tasks.create(instruction).id,
+    ))
+    result = router.execute("Refactor the parser code")
+    assert result.routing["profile"] == "coding"
+    assert coding.tasks.get(result.task_id).to_dict()["routing"]["method"] == "keyword"
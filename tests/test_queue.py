@@ -0,0 +1,253 @@
+import pytest
+
+from bp_agent.runner.queue import TaskQueue
+
+
+def test_queue_emits_lifecycle_events():
+    queue = TaskQueue()
+    events = []
+    unsubscribe = queue.subscribe(events.append)
+
+    task = queue.add("Do something")
+    queue.update(task.id, status="running")
+    queue.update(task.id, status="completed", output="done")
+
+    assert [e["type"] for e in events] == ["created", "running", "completed"]
+    assert events[-1]["task"]["id"] == task.id
+    assert events[-1]["task"]["output"] == "done"
+
+    unsubscribe()
+    queue.add("Another")
+    assert len(events) == 3
+
+
+def test_queue_listener_can_read_queue():
+    queue = TaskQueue()
+    seen = []
+    queue.subscribe(lambda event: seen.append(queue.pending_count()))
+
+    queue.add("First")
+    assert seen == [1]
+
+
+def test_queue_order_and_ids_ignore_clock_skew(monkeypatch, tmp_path):
+    import bp_agent.runner.queue as queue_module
+
+    now = [500.0]
+    monkeypatch.setattr(queue_module.time, "time", lambda: now[0])
+    path = tmp_path / "queue.json"
+    queue = TaskQueue(storage_path=path)
+    first = queue.add("first")
+    now[0] = 400.0  # wall clock jumps back
+    second = queue.add("second")
+
+    now[0] = 500.0
+    restarted = TaskQueue(storage_path=path)
+    third = restarted.add("third")
+    assert [t.instruction for t in restarted.list_all()] == ["first", "second", "third"]
+    assert (first.seq, second.seq, third.seq) == (1, 2, 3)
+    assert len({first.id, second.id, third.id}) == 3
+
+
+def test_export_tasks_jsonl_and_csv():
+    import io
+    import json
+
+    from bp_agent.runner.export import export_tasks
+
+    queue = TaskQueue()
+    old = queue.add("Old task")
+    old.created_at = 1000.0
+    new = queue.add("New, \"quoted\" task")
+    queue.update(new.id, status="completed", output="done")
+
+    out = io.StringIO()
+    assert export_tasks(queue.list_all(), out, "jsonl", since=2000.0) == 1
+    row = json.loads(out.getvalue())
+    assert row["id"] == new.id
+    assert row["status"] == "completed"
+
+    out = io.StringIO()
+    assert export_tasks(queue.list_all(), out, "csv") == 2
+    lines = out.getvalue().splitlines()
+    assert lines[0].startswith("id,instruction,status")
+    assert '"New, ""quoted"" task"' in out.getvalue()
+
+
+def test_maintenance_scheduler_runs_due_jobs():
+    from bp_agent.runner.scheduler import MaintenanceScheduler, prune_queue_job
+
+    now = [1000.0]
+    scheduler = MaintenanceScheduler(clock=lambda: now[0])
+    queue = TaskQueue()
+    done = queue.add("Old")
+    queue.update(done.id, status="completed", output="ok")
+    done.completed_at = 0.0
+    queue.add("Still pending")
+
+    scheduler.add_job("prune_tasks", prune_queue_job(queue, max_age=60), interval=300)
+    scheduler.add_job("broken", lambda: 1 / 0, interval=300)
+    assert scheduler.run_pending() == 0
+
+    now[0] += 300
+    assert scheduler.run_pending() == 2
+    status = {job["name"]: job for job in scheduler.status()}
+    assert status["prune_tasks"]["last_status"] == "ok"
+    assert status["prune_tasks"]["last_result"] == "1"
+    assert status["broken"]["last_status"] == "error"
+    assert status["broken"]["next_run"] == 1600.0
+    assert [t.instruction for t in queue.list_all()] == ["Still pending"]
+
+
+def test_task_cli_browse_pages_and_filters(monkeypatch):
+    import contextlib
+    import io
+
+    from bp_agent.runner import cli
+
+    queue = TaskQueue()
+    for i in range(25):
+        queue.add(f"Task {i}")
+    done = queue.add("Finished task")
+    queue.update(done.id, status="completed", output="line1\n" * 15)
+
+    items, page, pages = cli.paginate(queue.list_all(), 5)
+    assert (len(items), page, pages) == (6, 1, 2)
+
+    answers = iter(["n", "f completed", "1", "q"])
+    monkeypatch.setattr("builtins.input", lambda prompt="": next(answers))
+    buf = io.StringIO()
+    with contextlib.redirect_stdout(buf):
+        cli.TaskCLI(queue)._cmd_browse()
+
+    out = buf.getvalue()
+    assert "page 1/2 (26 total)" in out
+    assert "page 2/2 (26 total)" in out
+    assert "Tasks [completed] - page 1/1 (1 total)" in out
+    assert out.count("line1") == 15
+
+
+def test_cli_env_expansion_uses_allowlist():
+    from bp_agent.runner.cli import expand_env
+
+    env = {"BRANCH": "feature/x", "CI_JOB": "42", "SECRET": "hunter2"}
+    assert expand_env("Review ${BRANCH} (job ${CI_JOB}), cost $$5", ["BRANCH", "CI_*"], env) == (
+        "Review feature/x (job 42), cost $5"
+    )
+    assert expand_env("plain $BRANCH stays", ["BRANCH"], env) == "plain $BRANCH stays"
+    with pytest.raises(ValueError, match="allowlist"):
+        expand_env("${SECRET}", ["BRANCH", "CI_*"], env)
+    with pytest.raises(ValueError, match="not set"):
+        expand_env("${CI_MISSING}", ["BRANCH", "CI_*"], env)
+
+
+def test_runner_defers_tasks_for_rate_limited_providers():
+    import types
+
+    from bp_agent.llm import GeminiAdapter, GeminiConfig, LLMRouter, OllamaAdapter, OllamaConfig
+    from bp_agent.runner import TaskRunner
+
+    router = LLMRouter(default_provider="gemini")
+    gemini = GeminiAdapter(GeminiConfig(api_keys=["k1"]))
+    router.register_provider("gemini", gemini)
+    router.register_provider("ollama", OllamaAdapter(OllamaConfig()))  # no keys: always has capacity
+    ran = []
+    agent = types.SimpleNamespace(
+        llm=router,
+        config=types.SimpleNamespace(provider="gemini"),
+        execute=lambda instruction, **kwargs: ran.append((instruction, None)) or types.SimpleNamespace(success=True, output="ok"),
+        execute_with=lambda instruction, options: ran.append((instruction, options.provider))
+        or types.SimpleNamespace(success=True, output="ok"),
+    )
+    queue = TaskQueue()
+    first = queue.add("on gemini")
+    queue.add("on ollama", provider="ollama")
+    runner = TaskRunner(agent, queue, workers=2, agent_factory=lambda: types.SimpleNamespace(**vars(agent)))
+
+    gemini.rotation.report_rate_limit("k1")
+    assert runner.run_once()
+    assert ran == [("on ollama", "ollama")]  # the older task waits instead of hitting a 429
+    assert not runner.run_once()
+    assert list(runner.deferred) == [first.id] and 0 < runner.deferred[first.id] <= 60
+    assert queue.get(first.id).status == "pending"
+
+    gemini.rotation.report_success("k1")  # key back
+    assert runner.run_once()
+    assert ran[-1] == ("on gemini", None) and queue.get(first.id).status == "completed"
+    assert router.available_in("missing") is None
+
+
+def test_runner_stores_the_error_and_keeps_partial_output():
+    import types
+
+    from bp_agent.runner import TaskRunner
+
+    result = types.SimpleNamespace(success=False, output="half an answer", error="rate_limit: quota exhausted")
+    agent = types.SimpleNamespace(llm=None, execute=lambda instruction, **kwargs: result)
+    queue = TaskQueue()
+    task = queue.add("long job")
+
+    assert TaskRunner(agent, queue).run_once()
+    stored = queue.get(task.id)
+    assert (stored.status, stored.error, stored.output) == ("failed", "rate_limit: quota exhausted", "half an answer")
+
+
+def test_runner_gives_each_worker_its_own_agent():
+    import threading
+    import time
+    import types
+
+    from bp_agent.errors import ConfigError
+    from bp_agent.runner import TaskRunner
+
+    both_running = threading.Barrier(2, timeout=5)
+    used = []
+
+    def make_agent():
+        def execute(instruction, **kwargs):
+            used.append(agent)
+            both_running.wait()  # the two tasks run at the same time, on different agents
+            return types.SimpleNamespace(success=True, output=instruction)
+
+        agent = types.SimpleNamespace(llm=None, execute=execute)
+        return agent
+
+    queue = TaskQueue()
+    with pytest.raises(ConfigError):
+        TaskRunner(make_agent(), queue, workers=2)
+
+    first, second = queue.add("one"), queue.add("two")
+    runner = TaskRunner(make_agent(), queue, workers=2, agent_factory=make_agent)
+    runner.start()
+    try:
+        for _ in range(100):
+            if all(queue.get(t.id).status == "completed" for t in (first, second)):
+                break
+            time.sleep(0.05)
+    finally:
+        runner.stop()
+    assert queue.get(first.id).output == "one" and queue.get(second.id).output == "two"
+    assert len(used) == 2 and used[0] is not used[1]
+
+
+def test_runner_forwards_agent_steps_to_subscribers():
+    import types
+
+    from bp_agent.runner import TaskRunner
+
+    def execute(instruction, on_event=None):
+        on_event({"type": "delta", "text": "Look"})
+        on_event({"type": "step", "phase": "model", "iteration": 0, "tool_calls": ["list_dir"]})
+        on_event({"type": "step", "phase": "tools", "iteration": 0, "tools": ["list_dir"]})
+        return types.SimpleNamespace(success=True, output="done")
+
+    queue = TaskQueue()
+    events = []
+    queue.subscribe(events.append)
+    task = queue.add("List")
+    assert TaskRunner(types.SimpleNamespace(llm=None, execute=execute), queue).run_once()
+
+    assert [e["type"] for e in events] == ["created", "running", "step", "step", "completed"]
+    assert events[2]["task"]["id"] == task.id and events[2]["task"]["status"] == "running"
+    assert events[2]["step"] == {"phase": "model", "iteration": 0, "tool_calls": ["list_dir"]}
+    assert events[3]["step"]["phase"] == "tools"
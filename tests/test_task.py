@@ -58,3 +58,153 @@ def test_persistence(tmp_path: Path):
     assert loaded is not None
     assert loaded.instruction == "Persist test"
     assert loaded.status == TaskStatus.COMPLETED
+
+
+def test_compressed_persistence_with_spilled_fields(tmp_path: Path):
+    path = tmp_path / "tasks.json.gz"
+    big = "line\n" * 1000
+
+    store1 = TaskStore(persist=True, path=str(path), compression="gzip", max_field_chars=100)
+    task = store1.create("Big output")
+    store1.update(task.id, status="completed", output=big)
+    assert path.read_bytes()[:2] == b"\x1f\x8b"
+
+    store2 = TaskStore(persist=True, path=str(path))
+    loaded = store2.get(task.id)
+    assert len(loaded.output) == 100
+    assert "output" in loaded.artifacts
+    assert store2.read_field(task.id, "output") == big
+
+
+def test_injected_ids_and_clock():
+    from datetime import datetime, timezone
+
+    from bp_agent.task import sequential_ids
+
+    now = datetime(2024, 5, 1, 12, 0, tzinfo=timezone.utc)
+    store = TaskStore(id_factory=sequential_ids("t"), clock=lambda: now)
+
+    first = store.create("one")
+    second = store.create("two")
+    store.update(first.id, status="completed", output="ok")
+
+    assert (first.id, second.id) == ("t_0001", "t_0002")
+    assert first.created_at == first.completed_at == "2024-05-01T12:00:00+00:00"
+    assert TaskStore(clock=lambda: now).create("x").id.startswith("20240501_120000_")
+
+
+def test_order_survives_clock_going_backwards(tmp_path: Path):
+    import json
+    from datetime import datetime, timedelta
+
+    times = iter([datetime(2024, 5, 1, 12, 0) - timedelta(hours=h) for h in range(10)])
+    path = tmp_path / "tasks.json"
+    store = TaskStore(persist=True, path=str(path), clock=lambda: next(times))
+    ids = [store.create(f"task {i}").id for i in range(3)]
+
+    assert [t.id for t in store.list()] == ids[::-1]
+    assert [t.seq for t in store.list(before=3)] == [2, 1]
+
+    reloaded = TaskStore(persist=True, path=str(path), clock=lambda: next(times))
+    assert reloaded.create("task 3").seq == 4
+    assert reloaded.list(limit=1)[0].instruction == "task 3"
+
+    legacy = tmp_path / "legacy.json"
+    legacy.write_text(json.dumps([
+        {"id": "b", "instruction": "later", "status": "pending", "created_at": "2024-01-02T00:00:00"},
+        {"id": "a", "instruction": "earlier", "status": "pending", "created_at": "2024-01-01T00:00:00"},
+    ]))
+    assert [(t.id, t.seq) for t in TaskStore(persist=True, path=str(legacy)).list()] == [("b", 2), ("a", 1)]
+
+
+def test_sqlite_backend_persists_and_filters(tmp_path: Path):
+    from bp_agent.errors import ConfigError
+    from bp_agent.task import SqliteBackend, sequential_ids
+
+    path = tmp_path / "tasks.db"
+    store = TaskStore(backend="sqlite", path=str(path), id_factory=sequential_ids("t"))
+    assert isinstance(store.backend, SqliteBackend)
+    for i in range(5):
+        task = store.create(f"task {i}", variant="a")
+        store.update(task.id, status="completed" if i % 2 else "failed", output=f"out {i}")
+
+    reloaded = TaskStore(backend="sqlite", path=str(path), id_factory=sequential_ids("t", 6))
+    assert reloaded.get("t_0002").output == "out 1"
+    assert reloaded.get("missing") is None
+    assert [t.id for t in reloaded.list(limit=2)] == ["t_0005", "t_0004"]
+    assert [t.id for t in reloaded.list(before=4)] == ["t_0003", "t_0002", "t_0001"]
+    assert [t.id for t in reloaded.list(status="completed")] == ["t_0004", "t_0002"]
+    assert [t.id for t in reloaded.list(status=TaskStatus.FAILED, before=5)] == ["t_0003", "t_0001"]
+    assert reloaded.variant_stats()["a"] == {"total": 5, "completed": 2, "failed": 3, "success_rate": 0.4}
+    assert reloaded.create("next").seq == 6
+
+    try:
+        TaskStore(backend="mongo")
+    except ConfigError:
+        pass
+    else:
+        raise AssertionError("unknown backend accepted")
+
+
+def test_seq_is_unique_across_stores_and_threads(tmp_path: Path):
+    import threading
+
+    from bp_agent.task import sequential_ids
+
+    # Two stores on one database stand in for two processes sharing tasks.db
+    first = TaskStore(backend="sqlite", path=str(tmp_path / "tasks.db"))
+    second = TaskStore(backend="sqlite", path=str(tmp_path / "tasks.db"))
+    seqs = [first.create("a").seq, second.create("b").seq, first.create("c").seq]
+    assert seqs == [1, 2, 3]
+    assert [t.instruction for t in second.list()] == ["c", "b", "a"]
+
+    for backend in ("json", "sqlite"):
+        path = tmp_path / f"threads.{backend}"
+        store = TaskStore(backend=backend, path=str(path), id_factory=sequential_ids())
+
+        def create_some():
+            for i in range(20):
+                task = store.create(f"task {i}")
+                store.update(task.id, status="completed", output="ok")
+
+        threads = [threading.Thread(target=create_some) for _ in range(5)]
+        for thread in threads:
+            thread.start()
+        for thread in threads:
+            thread.join()
+        reloaded = TaskStore(backend=backend, path=str(path))
+        tasks = reloaded.list(limit=200)
+        assert sorted(t.seq for t in tasks) == list(range(1, 101))
+        assert all(t.status == TaskStatus.COMPLETED for t in tasks)
+    assert not (tmp_path / "threads.json.tmp").exists()
+
+
+def test_query_filters_by_status_and_date_with_totals(tmp_path: Path):
+    from datetime import datetime, timedelta
+
+    from bp_agent.errors import ConfigError
+    from bp_agent.task import sequential_ids
+
+    start = datetime(2024, 5, 1)
+    for backend in ("memory", "sqlite"):
+        times = iter([start + timedelta(days=d) for d in range(20)])
+        store = TaskStore(
+            backend=backend, path=str(tmp_path / "tasks.db"), id_factory=sequential_ids(), clock=lambda: next(times)
+        )
+        ids = [store.create(f"task {i}").id for i in range(6)]  # created on days 0..5
+        for id in ids[::2]:
+            store.update(id, status="completed", output="ok")
+
+        page = store.query(status="pending", offset=1, limit=1)
+        assert ([t.id for t in page.tasks], page.total, page.has_more) == ([ids[3]], 3, True)
+        page = store.query(since="2024-05-02", until=start + timedelta(days=4), limit=10)
+        assert ([t.id for t in page.tasks], page.total, page.has_more) == ([ids[3], ids[2], ids[1]], 3, False)
+        assert store.query(status=TaskStatus.COMPLETED, since=(start + timedelta(days=3)).timestamp()).total == 1
+        assert [t.id for t in store.list(limit=2, offset=1, until="2024-05-05")] == [ids[2], ids[1]]
+        for bad in ({"status": "done"}, {"since": "yesterday"}, {"offset": -1}):
+            try:
+                store.query(**bad)
+            except ConfigError:
+                pass
+            else:
+                raise AssertionError(f"{bad} accepted")
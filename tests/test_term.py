@@ -0,0 +1,23 @@
+import io
+
+from bp_agent.runner import term
+
+
+def test_wrap_text_keeps_code_blocks_and_list_indent():
+    text = "- " + "word " * 30 + "\n```\n" + "x" * 120 + "\n```"
+    wrapped = term.wrap_text(text, width=40).split("\n")
+
+    assert all(len(line) <= 40 for line in wrapped if not line.startswith("x"))
+    assert wrapped[1].startswith("  word")
+    assert "x" * 120 in wrapped
+
+
+def test_ascii_fallbacks_for_non_utf8_streams():
+    cp437 = io.TextIOWrapper(io.BytesIO(), encoding="ascii")
+    utf8 = io.TextIOWrapper(io.BytesIO(), encoding="utf-8")
+
+    assert term.box_chars(cp437)["tl"] == "+"
+    assert term.box_chars(utf8)["tl"] == "╔"
+    assert term.symbol("✓", cp437) == "+"
+    assert term.display_width("日本ab") == 6
+    assert not term.supports_color(utf8)
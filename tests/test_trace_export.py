@@ -0,0 +1,35 @@
+from bp_agent.trace_export import trace_to_dot, trace_to_mermaid
+
+TRACE = {
+    "provider": "gemini",
+    "model": "gemini-3-flash-preview",
+    "tool_calls": [
+        {"name": "bash", "args": {"command": "ls"}},
+        {"name": "bash", "args": {"command": "ls"}},
+        {"name": "give_result", "args": {"result": "src"}},
+    ],
+    "tool_results": [
+        {"name": "bash", "output": "src\nbin", "error": None},
+        {"name": "give_result", "output": "src", "error": None},
+    ],
+}
+
+
+def test_trace_to_mermaid():
+    diagram = trace_to_mermaid(TRACE)
+
+    assert diagram.startswith("sequenceDiagram\n")
+    assert "participant L as gemini" in diagram
+    assert "A->>T: bash" in diagram
+    assert "T-->>A: src bin" in diagram
+    assert "Note over A: duplicate bash skipped" in diagram
+    assert "A->>T: give_result" in diagram
+
+
+def test_trace_to_dot():
+    dot = trace_to_dot(TRACE)
+
+    assert dot.startswith("digraph trace {")
+    assert 'step2 [label="bash({\\"command\\": \\"ls\\"})\\nduplicate, skipped", style=dashed];' in dot
+    assert "step2 -> step3;" in dot
+    assert dot.rstrip().endswith("}")
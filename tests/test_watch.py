@@ -0,0 +1,49 @@
+import os
+import threading
+import time
+
+from bp_agent.runner.watch import build_instruction, diff_snapshots, snapshot, watch
+
+
+def test_snapshot_diff_detects_changes(tmp_path):
+    (tmp_path / "a.py").write_text("a = 1\n")
+    (tmp_path / "__pycache__").mkdir()
+    (tmp_path / "__pycache__" / "a.pyc").write_text("x")
+    before = snapshot([str(tmp_path)])
+    assert list(before) == [str(tmp_path / "a.py")]
+
+    (tmp_path / "b.py").write_text("b = 2\n")
+    os.utime(tmp_path / "a.py", (1, 1))
+    changed = diff_snapshots(before, snapshot([str(tmp_path)]))
+    assert changed == [str(tmp_path / "a.py"), str(tmp_path / "b.py")]
+
+    text = build_instruction("review", [str(tmp_path / "b.py"), str(tmp_path / "gone.py")])
+    assert text.startswith("review\n")
+    assert "b = 2" in text
+    assert "gone.py (deleted)" in text
+
+
+def test_watch_reruns_instruction_on_change(tmp_path):
+    target = tmp_path / "main.py"
+    target.write_text("x = 1\n")
+    sent, outputs = [], []
+
+    def touch():
+        time.sleep(0.1)
+        target.write_text("x = 2\n")
+        os.utime(target, (time.time() + 5, time.time() + 5))
+
+    threading.Thread(target=touch).start()
+    runs = watch(
+        lambda text: sent.append(text) or "ok",
+        [str(target)],
+        "review the changes",
+        interval=0.05,
+        debounce=0.01,
+        max_runs=1,
+        on_output=lambda changed, output: outputs.append((changed, output)),
+    )
+
+    assert runs == 1
+    assert "x = 2" in sent[0]
+    assert outputs == [([str(target)], "ok")]